@@ -0,0 +1,13 @@
+use std::num;
+
+use thiserror::Error as ThisError;
+
+#[derive(ThisError, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Int(#[from] num::ParseIntError),
+    #[error("{0}")]
+    Syntax(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,5 @@
+use pest_derive::Parser;
+
+#[derive(Parser)]
+#[grammar = "grammar/rules.pest"]
+pub struct Grammar;
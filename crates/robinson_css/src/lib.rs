@@ -1 +1,8 @@
-pub use pest_css_parser::*;
+mod stylesheet;
+mod grammar;
+mod error;
+
+use grammar::Rule;
+
+pub use crate::stylesheet::*;
+pub use error::*;
@@ -0,0 +1,107 @@
+use super::rule::{Unit, Value};
+
+/// The expression of a math function (`calc()`, `min()`, `max()` or `clamp()`).
+///
+/// Lengths in an expression can mix units, so it can only be evaluated once every unit can be
+/// converted to px, which for percentages isn't until layout.
+///
+/// https://www.w3.org/TR/css-values-4/#math
+#[derive(Debug, Clone, PartialEq)]
+pub enum Calc {
+    Length(f32, Unit),
+    Number(f32),
+    Sum(Box<Calc>, Box<Calc>),
+    Difference(Box<Calc>, Box<Calc>),
+    Product(Box<Calc>, Box<Calc>),
+    Quotient(Box<Calc>, Box<Calc>),
+    Min(Vec<Calc>),
+    Max(Vec<Calc>),
+    /// `clamp(min, value, max)`
+    Clamp(Box<Calc>, Box<Calc>, Box<Calc>),
+}
+
+/// The result of evaluating (part of) an expression.
+#[derive(Clone, Copy)]
+enum Numeric {
+    /// A length in px.
+    Length(f32),
+    Number(f32),
+}
+
+impl Calc {
+    /// Evaluate the expression into a length in px or a number.
+    ///
+    /// `to_px` converts each length in the expression to px. Returns `None` if it can't convert
+    /// one of them, or if the expression combines lengths and numbers in a way that doesn't
+    /// produce a length or a number (such as `10px + 2` or `10px * 2px`).
+    pub fn evaluate(&self, to_px: &impl Fn(f32, Unit) -> Option<f32>) -> Option<Value> {
+        match self.evaluate_numeric(to_px)? {
+            Numeric::Length(px) => Some(Value::Length(px, Unit::Px)),
+            Numeric::Number(number) => Some(Value::Number(number)),
+        }
+    }
+
+    /// Replace the lengths that `to_px` can convert with their size in px, leaving the others.
+    pub fn map_lengths(&self, to_px: &impl Fn(f32, Unit) -> Option<f32>) -> Calc {
+        let map = |calc: &Calc| Box::new(calc.map_lengths(to_px));
+        match self {
+            Calc::Length(length, unit) => match to_px(*length, *unit) {
+                Some(px) => Calc::Length(px, Unit::Px),
+                None => Calc::Length(*length, *unit),
+            },
+            Calc::Number(number) => Calc::Number(*number),
+            Calc::Sum(a, b) => Calc::Sum(map(a), map(b)),
+            Calc::Difference(a, b) => Calc::Difference(map(a), map(b)),
+            Calc::Product(a, b) => Calc::Product(map(a), map(b)),
+            Calc::Quotient(a, b) => Calc::Quotient(map(a), map(b)),
+            Calc::Min(arguments) => Calc::Min(arguments.iter().map(|calc| calc.map_lengths(to_px)).collect()),
+            Calc::Max(arguments) => Calc::Max(arguments.iter().map(|calc| calc.map_lengths(to_px)).collect()),
+            Calc::Clamp(min, value, max) => Calc::Clamp(map(min), map(value), map(max)),
+        }
+    }
+
+    fn evaluate_numeric(&self, to_px: &impl Fn(f32, Unit) -> Option<f32>) -> Option<Numeric> {
+        use Numeric::{Length, Number};
+
+        let evaluate = |calc: &Calc| calc.evaluate_numeric(to_px);
+        match self {
+            Calc::Length(length, unit) => to_px(*length, *unit).map(Length),
+            Calc::Number(number) => Some(Number(*number)),
+            Calc::Sum(a, b) => same_type(evaluate(a)?, evaluate(b)?, |a, b| a + b),
+            Calc::Difference(a, b) => same_type(evaluate(a)?, evaluate(b)?, |a, b| a - b),
+            Calc::Product(a, b) => match (evaluate(a)?, evaluate(b)?) {
+                (Number(a), Number(b)) => Some(Number(a * b)),
+                (Length(a), Number(b)) | (Number(b), Length(a)) => Some(Length(a * b)),
+                (Length(_), Length(_)) => None,
+            },
+            Calc::Quotient(a, b) => match (evaluate(a)?, evaluate(b)?) {
+                (_, Number(0.0)) => None,
+                (Number(a), Number(b)) => Some(Number(a / b)),
+                (Length(a), Number(b)) => Some(Length(a / b)),
+                (_, Length(_)) => None,
+            },
+            Calc::Min(arguments) => arguments
+                .iter()
+                .map(evaluate)
+                .reduce(|a, b| same_type(a?, b?, f32::min))?,
+            Calc::Max(arguments) => arguments
+                .iter()
+                .map(evaluate)
+                .reduce(|a, b| same_type(a?, b?, f32::max))?,
+            // The minimum wins if it is larger than the maximum.
+            Calc::Clamp(min, value, max) => {
+                let upper = same_type(evaluate(value)?, evaluate(max)?, f32::min)?;
+                same_type(evaluate(min)?, upper, f32::max)
+            }
+        }
+    }
+}
+
+/// Combine two values of the same type, or return `None` if a length meets a number.
+fn same_type(a: Numeric, b: Numeric, combine: impl Fn(f32, f32) -> f32) -> Option<Numeric> {
+    match (a, b) {
+        (Numeric::Length(a), Numeric::Length(b)) => Some(Numeric::Length(combine(a, b))),
+        (Numeric::Number(a), Numeric::Number(b)) => Some(Numeric::Number(combine(a, b))),
+        _ => None,
+    }
+}
@@ -0,0 +1,280 @@
+use pest::{Parser, iterators::{Pairs, Pair}};
+
+use crate::{grammar::Grammar, Error, Rule, Result};
+
+use self::constants::COLORS;
+pub use self::calc::*;
+pub use self::rule::*;
+pub use self::shorthand::*;
+
+mod calc;
+mod rule;
+mod shorthand;
+mod constants;
+
+#[derive(Debug, Default, Clone)]
+pub struct StyleSheet {
+    pub rules: Vec<CssRule>,
+    pub errors: Vec<String>,
+    pub origin: Origin,
+}
+
+/// Where a stylesheet comes from, which decides how its declarations rank in the cascade.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    /// The browser's default styles.
+    UserAgent,
+    /// Styles the user has configured for every page.
+    User,
+    /// Styles from the page itself.
+    #[default]
+    Author,
+}
+
+impl StyleSheet {
+    pub fn parse(input: &str) -> Result<Self> {
+        let pairs = Grammar::parse(Rule::css, input)
+            .map_err(|error| Error::Syntax(error.to_string()))?;
+
+        Self::build_stylesheet(pairs)
+    }
+
+    /// Parse a stylesheet that comes from `origin` rather than from the page.
+    pub fn parse_with_origin(input: &str, origin: Origin) -> Result<Self> {
+        Ok(Self {
+            origin,
+            ..Self::parse(input)?
+        })
+    }
+
+    /// Parse a list of declarations without a selector or braces, such as the contents of a
+    /// `style` attribute. Shorthands are expanded into their longhands.
+    pub fn parse_declarations(input: &str) -> Result<Vec<Declaration>> {
+        let pairs = Grammar::parse(Rule::declaration_list, input)
+            .map_err(|error| Error::Syntax(error.to_string()))?;
+
+        let declarations = pairs
+            .filter(|pair| pair.as_rule() == Rule::declaration)
+            .map(|pair| Self::build_declaration(pair.into_inner()))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(declarations.into_iter().flat_map(Declaration::expand).collect())
+    }
+
+    /// Parse a single value, such as the result of substituting `var()` references.
+    pub fn parse_value(input: &str) -> Result<Value> {
+        let mut pairs = Grammar::parse(Rule::value_only, input)
+            .map_err(|error| Error::Syntax(error.to_string()))?;
+
+        Ok(pairs.next().map(Self::build_value).unwrap_or(Value::StringLiteral(String::default())))
+    }
+
+    fn build_stylesheet(pairs: Pairs<Rule>) -> Result<Self> {
+        let mut stylesheet = StyleSheet::default();
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::rule_comment => {
+                    stylesheet
+                        .rules.push(CssRule::Comment(pair.into_inner().as_str().to_string()));
+                },
+                Rule::rule_normal => match Self::build_normal_rule(pair, &mut stylesheet.errors) {
+                    Ok(rule) => {
+                        let normal_rule = CssRule::Normal(rule);
+                        stylesheet.rules.push(normal_rule);
+                    }
+                    Err(error) => {
+                        stylesheet.errors.push(error.to_string());
+                    }
+                }
+                Rule::rule_invalid => {
+                    stylesheet.errors.push(format!("unsupported rule: {}", pair.as_str().trim()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(stylesheet)
+    }
+
+    /// Build a rule, skipping the declarations in it that are invalid and recording them in
+    /// `errors`.
+    fn build_normal_rule(pair: Pair<Rule>, errors: &mut Vec<String>) -> Result<NormalRule> {
+        let mut normal_rule = NormalRule::default();
+        
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::sel_complex => {
+                    normal_rule.selectors.push(Self::build_complex_selector(pair.into_inner())?);
+                }
+                Rule::declaration => match Self::build_declaration(pair.into_inner()) {
+                    Ok(declaration) => normal_rule.declarations.extend(declaration.expand()),
+                    Err(error) => errors.push(error.to_string()),
+                },
+                Rule::declaration_invalid => {
+                    errors.push(format!("unsupported declaration: {}", pair.as_str().trim()));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(normal_rule)
+    }
+
+    fn build_complex_selector(pairs: Pairs<Rule>) -> Result<Selector> {
+        let mut selector = None;
+        let mut combinator = Combinator::Descendant;
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::sel_normal => {
+                    let right = Self::build_simple_selector(pair.into_inner())?;
+                    selector = Some(match selector {
+                        None => Selector::Simple(right),
+                        Some(left) => Selector::Complex {
+                            left: Box::new(left),
+                            combinator,
+                            right,
+                        },
+                    });
+                }
+                Rule::comb_descendant => combinator = Combinator::Descendant,
+                Rule::comb_child => combinator = Combinator::Child,
+                Rule::comb_next_sibling => combinator = Combinator::NextSibling,
+                Rule::comb_subsequent_sibling => combinator = Combinator::SubsequentSibling,
+                _ => {}
+            }
+        }
+
+        Ok(selector.unwrap_or_else(|| Selector::Simple(SimpleSelector::default())))
+    }
+
+    fn build_simple_selector(pairs: Pairs<Rule>) -> Result<SimpleSelector> {
+        let mut selector = SimpleSelector::default();
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::sel_id_body => selector.id = Some(pair.as_str().to_string()),
+                Rule::sel_class_body => selector.class.push(pair.as_str().to_string()),
+                Rule::sel_type => selector.tag_name = Some(pair.as_str().to_string()),
+                _ => {}
+            }
+        }
+
+        Ok(selector)
+    }
+
+    fn build_declaration(pairs: Pairs<Rule>) -> Result<Declaration> {
+        let mut declaration = Declaration {
+            name: String::default(),
+            value: Value::StringLiteral(String::default()),
+            important: false,
+        };
+
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::del_property | Rule::del_custom_property => declaration.name = pair.as_str().to_string(),
+                Rule::del_important => declaration.important = true,
+                // The end of the input can end the last declaration of a list.
+                Rule::EOI => {}
+                _ => declaration.value = Self::build_value(pair),
+            }
+        }
+
+        Ok(declaration)
+    }
+
+    fn build_value(pair: Pair<Rule>) -> Value {
+        match pair.as_rule() {
+            Rule::del_val_keyword => {
+                let colors = COLORS.lock().unwrap();
+                if let Some(color) = colors.get(pair.as_str()) {
+                    Value::Color(*color)
+                } else {
+                    Value::Keyword(pair.as_str().to_string())
+                }
+            },
+            Rule::del_val_length => {
+                let mut inner_pairs = pair.into_inner();
+                let len_value = inner_pairs.next().unwrap();
+                let len_type = inner_pairs.next().unwrap();
+
+                Value::Length(
+                    len_value.as_str().parse().unwrap(),
+                    Unit::from(len_type.as_str())
+                )
+            }
+            Rule::del_val_number => Value::Number(pair.as_str().parse().unwrap()),
+            Rule::math_calc | Rule::math_min | Rule::math_max | Rule::math_clamp => Value::Calc(Self::build_calc(pair)),
+            Rule::del_val_function => {
+                let mut inner = pair.into_inner();
+                let name = inner.next().unwrap().as_str().to_ascii_lowercase();
+                let arguments = match Self::build_value(inner.next().unwrap()) {
+                    Value::List(arguments, Separator::Comma) => arguments,
+                    argument => vec![argument],
+                };
+                Value::Function(name, arguments)
+            }
+            Rule::del_val_url => {
+                let url = match pair.into_inner().next().unwrap() {
+                    pair if pair.as_rule() == Rule::del_val_string => pair.into_inner().as_str().to_string(),
+                    pair => pair.as_str().to_string(),
+                };
+                Value::Function("url".to_string(), vec![Value::StringLiteral(url)])
+            }
+            Rule::del_val_color => Value::Color(Color::from_hex(pair.as_str())),
+            Rule::del_val_unparsed => Value::Unparsed(pair.as_str().trim().to_string()),
+            Rule::del_val_string => Value::StringLiteral(pair.into_inner().as_str().to_string()),
+            Rule::del_values_comma | Rule::del_values_space | Rule::del_values_slash => {
+                let separator = match pair.as_rule() {
+                    Rule::del_values_comma => Separator::Comma,
+                    Rule::del_values_space => Separator::Space,
+                    _ => Separator::Slash,
+                };
+                let mut values: Vec<Value> = pair.into_inner().map(Self::build_value).collect();
+                if values.len() == 1 {
+                    values.pop().unwrap()
+                } else {
+                    Value::List(values, separator)
+                }
+            }
+            _ => Value::StringLiteral(String::default()),
+        }
+    }
+
+    fn build_calc(pair: Pair<Rule>) -> Calc {
+        let kind = pair.as_rule();
+        let text = pair.as_str();
+        let mut inner = pair.into_inner();
+
+        match kind {
+            Rule::math_calc => Self::build_calc(inner.next().unwrap()),
+            Rule::math_min => Calc::Min(inner.map(Self::build_calc).collect()),
+            Rule::math_max => Calc::Max(inner.map(Self::build_calc).collect()),
+            Rule::math_clamp => {
+                let mut arguments = inner.map(|pair| Box::new(Self::build_calc(pair)));
+                let mut next = || arguments.next().unwrap();
+                Calc::Clamp(next(), next(), next())
+            }
+            // Operators of the same precedence apply from left to right.
+            Rule::math_sum | Rule::math_product => {
+                let mut calc = Self::build_calc(inner.next().unwrap());
+                while let (Some(operator), Some(operand)) = (inner.next(), inner.next()) {
+                    let (a, b) = (Box::new(calc), Box::new(Self::build_calc(operand)));
+                    calc = match operator.as_rule() {
+                        Rule::math_add => Calc::Sum(a, b),
+                        Rule::math_subtract => Calc::Difference(a, b),
+                        Rule::math_multiply => Calc::Product(a, b),
+                        _ => Calc::Quotient(a, b),
+                    };
+                }
+                calc
+            }
+            Rule::del_val_length => {
+                let len_value = inner.next().unwrap();
+                let len_type = inner.next().unwrap();
+                Calc::Length(len_value.as_str().parse().unwrap(), Unit::from(len_type.as_str()))
+            }
+            _ => Calc::Number(text.parse().unwrap()),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use super::calc::Calc;
+use super::constants::COLORS;
+
+pub type Specificity = (usize, usize, usize);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CssRule {
+    Normal(NormalRule),
+    Comment(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NormalRule {
+    pub selectors: Vec<Selector>,
+    pub declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub value: Value,
+    /// Whether the declaration is marked `!important`.
+    pub important: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Simple(SimpleSelector),
+    /// A simple selector whose element must also be related to an element matching `left`,
+    /// e.g. `.outer > p`.
+    Complex {
+        left: Box<Selector>,
+        combinator: Combinator,
+        right: SimpleSelector,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    /// `a b`: `b` is somewhere inside `a`.
+    Descendant,
+    /// `a > b`: `b` is a child of `a`.
+    Child,
+    /// `a + b`: `b` immediately follows `a`.
+    NextSibling,
+    /// `a ~ b`: `b` follows `a`, with the same parent.
+    SubsequentSibling,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SimpleSelector {
+    pub id: Option<String>,
+    pub class: Vec<String>,
+    pub tag_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Keyword(String),
+    Length(f32, Unit),
+    /// A number without a unit, such as a `line-height` multiplier or a `font-weight`.
+    Number(f32),
+    /// A math function such as `calc(100% - 2rem)`.
+    Calc(Calc),
+    /// Any other function, such as `repeat(3, 1fr)`, with its comma-separated arguments.
+    Function(String, Vec<Value>),
+    /// The text of a custom property, or of a value containing `var()`, which can only be parsed
+    /// once the custom properties it refers to are known.
+    Unparsed(String),
+    /// The value of a longhand set by a shorthand whose value contains `var()`. It is known once
+    /// the text of the shorthand has been substituted and expanded.
+    PendingShorthand { shorthand: String, text: String },
+    /// Several values, such as `1px solid red` or `Georgia, serif`.
+    List(Vec<Value>, Separator),
+    Color(Color),
+    StringLiteral(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Separator {
+    Space,
+    Comma,
+    Slash,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Unit {
+    /// Centimeters
+    Cm,
+    /// Millieters
+    Mm,
+    /// Inches
+    In,
+    /// Pixels
+    Px,
+    /// Points
+    Pt,
+    /// Picas
+    Pc,
+    /// Relative to the font-size of the element
+    Em,
+    /// Relative to the x-height of the current font
+    Ex,
+    /// Relative to the width of the "0"
+    Ch,
+    /// Relative to font-size of the root element
+    Rem,
+    /// Relative to 1% of the width of the viewport*
+    Vw,
+    /// Relative to 1% of the height of the viewport*
+    Vh,
+    /// Relative to 1% of viewport's* smaller dimension
+    VMin,
+    /// Relative to 1% of viewport's* larger dimension
+    VMax,
+    /// Relative to the parent element
+    Percent,
+    /// A fraction of the free space in a grid container
+    Fr,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Selector {
+    /// Computes the specificity of a CSS selector as defined by the W3C specification.
+    ///
+    /// Returns the count of ID, class, and tag name selectors, summed over every simple
+    /// selector in a [`Selector::Complex`].
+    ///
+    /// See [W3C Selectors Level 3](https://www.w3.org/TR/selectors/#specificity).
+    pub fn specificity(&self) -> Option<Specificity> {
+        match self {
+            Selector::Simple(simple) => Some(simple.specificity()),
+            Selector::Complex { left, right, .. } => {
+                let (a, b, c) = left.specificity()?;
+                let (d, e, f) = right.specificity();
+                Some((a + d, b + e, c + f))
+            }
+        }
+    }
+
+    /// The rightmost simple selector, which the matched element itself must match.
+    pub fn subject(&self) -> &SimpleSelector {
+        match self {
+            Selector::Simple(simple) => simple,
+            Selector::Complex { right, .. } => right,
+        }
+    }
+}
+
+impl SimpleSelector {
+    pub fn specificity(&self) -> Specificity {
+        let a = self.id.iter().count();
+        let b = self.class.len();
+        let c = self.tag_name.iter().count();
+        (a, b, c)
+    }
+}
+
+impl From<&str> for Unit {
+    fn from(value: &str) -> Self {
+        match value {
+            "cm" => Unit::Cm,
+            "mm" => Unit::Mm,
+            "in" => Unit::In,
+            "px" => Unit::Px,
+            "pt" => Unit::Pt,
+            "pc" => Unit::Pc,
+
+            "em" => Unit::Em,
+            "ex" => Unit::Ex,
+            "ch" => Unit::Ch,
+            "rem" => Unit::Rem,
+            "vw" => Unit::Vw,
+            "vh" => Unit::Vh,
+            "vmin" => Unit::VMin,
+            "vmax" => Unit::VMax,
+            "fr" => Unit::Fr,
+            "%" => Unit::Percent,
+            _ => Unit::Px,
+        }
+    }
+}
+
+impl Unit {
+    /// The size of one unit in px, or `None` for units relative to the font, viewport or
+    /// containing block.
+    ///
+    /// https://www.w3.org/TR/css-values-3/#absolute-lengths
+    pub fn absolute_px(self) -> Option<f32> {
+        match self {
+            Unit::Px => Some(1.0),
+            Unit::In => Some(96.0),
+            Unit::Cm => Some(96.0 / 2.54),
+            Unit::Mm => Some(96.0 / 25.4),
+            Unit::Pt => Some(96.0 / 72.0),
+            Unit::Pc => Some(96.0 / 6.0),
+            _ => None,
+        }
+    }
+}
+
+impl Value {
+    /// Return the length in px, or zero for non-lengths.
+    ///
+    /// The unit is ignored, so relative lengths must have been resolved first.
+    pub fn to_px(&self) -> f32 {
+        match *self {
+            Value::Length(f, _) => f,
+            _ => 0.0,
+        }
+    }
+}
+
+impl Color {
+    pub fn from_hex(hex: &str) -> Self {
+        let hex = hex.trim_start_matches('#');
+        let num = i32::from_str_radix(&hex[0..], 16).unwrap();
+        let r = (num >> 16) as u8;
+        let g = (num >> 8) as u8;
+        let b = num as u8;
+
+        Self {
+            r,
+            g,
+            b,
+            a: 255
+        }
+    }
+
+    pub fn from_keyword(name: &str) -> Self {
+        let unlocked = COLORS.lock().unwrap();
+        *unlocked.get(&name.to_lowercase()).unwrap()
+    }
+}
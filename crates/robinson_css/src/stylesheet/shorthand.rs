@@ -0,0 +1,400 @@
+//! Expansion of shorthand properties into the longhands they set.
+//!
+//! Shorthands are expanded as they are parsed, so that the cascade and everything after it only
+//! deal with longhands. A shorthand whose value contains `var()` can't be expanded until its
+//! references are substituted, so each of its longhands is given a [`Value::PendingShorthand`]
+//! to expand later with [`expand_shorthand`].
+
+use super::rule::{Declaration, Separator, Unit, Value};
+
+const BORDER_STYLES: &[&str] = &[
+    "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge", "inset", "outset",
+];
+
+/// The properties a shorthand sets, or an empty slice if `name` isn't a shorthand.
+pub fn longhands(name: &str) -> &'static [&'static str] {
+    match name {
+        "margin" => &["margin-top", "margin-right", "margin-bottom", "margin-left"],
+        "padding" => &["padding-top", "padding-right", "padding-bottom", "padding-left"],
+        "border-width" => &["border-top-width", "border-right-width", "border-bottom-width", "border-left-width"],
+        "border-style" => &["border-top-style", "border-right-style", "border-bottom-style", "border-left-style"],
+        "border-color" => &["border-top-color", "border-right-color", "border-bottom-color", "border-left-color"],
+        "border-top" => &["border-top-width", "border-top-style", "border-top-color"],
+        "border-right" => &["border-right-width", "border-right-style", "border-right-color"],
+        "border-bottom" => &["border-bottom-width", "border-bottom-style", "border-bottom-color"],
+        "border-left" => &["border-left-width", "border-left-style", "border-left-color"],
+        "border" => &[
+            "border-top-width", "border-right-width", "border-bottom-width", "border-left-width",
+            "border-top-style", "border-right-style", "border-bottom-style", "border-left-style",
+            "border-top-color", "border-right-color", "border-bottom-color", "border-left-color",
+        ],
+        "background" => &["background-color"],
+        "font" => &["font-style", "font-variant", "font-weight", "font-size", "line-height", "font-family"],
+        "flex" => &["flex-grow", "flex-shrink", "flex-basis"],
+        "flex-flow" => &["flex-direction", "flex-wrap"],
+        "gap" => &["row-gap", "column-gap"],
+        "grid-row" => &["grid-row-start", "grid-row-end"],
+        "grid-column" => &["grid-column-start", "grid-column-end"],
+        "grid-area" => &["grid-row-start", "grid-column-start", "grid-row-end", "grid-column-end"],
+        _ => &[],
+    }
+}
+
+impl Declaration {
+    /// Expand a shorthand declaration into a declaration for each of its longhands, with the same
+    /// importance. Other declarations are returned unchanged.
+    ///
+    /// A shorthand with an invalid value is dropped, as if it had never been written.
+    pub fn expand(self) -> Vec<Declaration> {
+        if longhands(&self.name).is_empty() {
+            return vec![self];
+        }
+
+        let values = match self.value {
+            Value::Unparsed(text) => longhands(&self.name)
+                .iter()
+                .map(|longhand| {
+                    let pending = Value::PendingShorthand { shorthand: self.name.clone(), text: text.clone() };
+                    (*longhand, pending)
+                })
+                .collect(),
+            value => expand_shorthand(&self.name, &value).unwrap_or_default(),
+        };
+
+        values
+            .into_iter()
+            .map(|(name, value)| Declaration {
+                name: name.to_string(),
+                value,
+                important: self.important,
+            })
+            .collect()
+    }
+}
+
+/// The value `value` gives each longhand of `shorthand`, or `None` if it isn't valid for it.
+///
+/// Longhands the value doesn't mention are reset to `initial`.
+pub fn expand_shorthand(shorthand: &str, value: &Value) -> Option<Vec<(&'static str, Value)>> {
+    let longhands = longhands(shorthand);
+
+    // The CSS-wide keywords apply to every longhand.
+    if let Value::Keyword(keyword) = value {
+        if matches!(keyword.as_str(), "inherit" | "initial" | "unset") {
+            return Some(longhands.iter().map(|longhand| (*longhand, value.clone())).collect());
+        }
+    }
+
+    let values = match shorthand {
+        "margin" => expand_sides(value, |value| is_length_percentage(value) || is_keyword(value, &["auto"]))?,
+        "padding" => expand_sides(value, is_length_percentage)?,
+        "border-width" => expand_sides(value, is_border_width)?,
+        "border-style" => expand_sides(value, is_border_style)?,
+        "border-color" => expand_sides(value, is_color)?,
+        "border-top" | "border-right" | "border-bottom" | "border-left" => expand_border(value)?,
+        "border" => {
+            // `border` sets the same width, style and color on every side.
+            let [width, style, color] = <[Value; 3]>::try_from(expand_border(value)?).ok()?;
+            [width, style, color].into_iter().flat_map(|value| vec![value; 4]).collect()
+        }
+        "background" => vec![expand_background(value)?],
+        "font" => expand_font(value)?,
+        "flex" => expand_flex(value)?,
+        "flex-flow" => expand_flex_flow(value)?,
+        "gap" => match components(value) {
+            [gap] if is_gap(gap) => vec![gap.clone(), gap.clone()],
+            [row, column] if is_gap(row) && is_gap(column) => vec![row.clone(), column.clone()],
+            _ => return None,
+        },
+        "grid-row" | "grid-column" | "grid-area" => expand_grid_lines(value, longhands.len())?,
+        _ => return None,
+    };
+    Some(longhands.iter().copied().zip(values).collect())
+}
+
+/// The components of a space-separated value.
+fn components(value: &Value) -> &[Value] {
+    match value {
+        Value::List(values, Separator::Space) => values,
+        value => std::slice::from_ref(value),
+    }
+}
+
+/// Expand one to four values into the top, right, bottom and left sides, in that order.
+///
+/// https://www.w3.org/TR/css-box-4/#margin-shorthand
+fn expand_sides(value: &Value, is_valid: impl Fn(&Value) -> bool) -> Option<Vec<Value>> {
+    let values = components(value);
+    if !values.iter().all(is_valid) {
+        return None;
+    }
+    let (top, right, bottom, left) = match values {
+        [all] => (all, all, all, all),
+        [vertical, horizontal] => (vertical, horizontal, vertical, horizontal),
+        [top, horizontal, bottom] => (top, horizontal, bottom, horizontal),
+        [top, right, bottom, left] => (top, right, bottom, left),
+        _ => return None,
+    };
+    Some(vec![top.clone(), right.clone(), bottom.clone(), left.clone()])
+}
+
+/// Expand the width, style and color of a border, which can be given in any order.
+fn expand_border(value: &Value) -> Option<Vec<Value>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+    for component in components(value) {
+        let slot = if is_border_width(component) {
+            &mut width
+        } else if is_border_style(component) {
+            &mut style
+        } else if is_color(component) {
+            &mut color
+        } else {
+            return None;
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+    }
+    Some([width, style, color].into_iter().map(or_initial).collect())
+}
+
+/// Only the color of a background is supported; images, gradients and their positioning are
+/// accepted but ignored.
+fn expand_background(value: &Value) -> Option<Value> {
+    // Only the last of several comma-separated layers can have a color.
+    let layer = match value {
+        Value::List(layers, Separator::Comma) => layers.last()?,
+        value => value,
+    };
+    let mut color = None;
+    for component in components(layer).iter().filter(|component| is_color(component)) {
+        if color.replace(component.clone()).is_some() {
+            return None;
+        }
+    }
+    Some(or_initial(color))
+}
+
+/// Expand `font: [style || variant || weight] size[/line-height] family`.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-prop
+fn expand_font(value: &Value) -> Option<Vec<Value>> {
+    // Commas only separate the families, so everything else is in the first item of the list.
+    let (first, more_families) = match value {
+        Value::List(values, Separator::Comma) => values.split_first()?,
+        value => (value, &[][..]),
+    };
+    let items = components(first);
+
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut rest = items;
+    while let [component, tail @ ..] = rest {
+        let slot = match component {
+            // `normal` resets whichever of the three it stands for, which is all of them.
+            Value::Keyword(keyword) if keyword == "normal" => {
+                rest = tail;
+                continue;
+            }
+            component if is_keyword(component, &["italic", "oblique"]) => &mut style,
+            component if is_keyword(component, &["small-caps"]) => &mut variant,
+            component if is_keyword(component, &["bold", "bolder", "lighter"]) => &mut weight,
+            Value::Number(weight_number) if (1.0..=1000.0).contains(weight_number) => &mut weight,
+            _ => break,
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+        rest = tail;
+    }
+
+    let [size_and_line_height, family @ ..] = rest else { return None };
+    let (size, line_height) = match size_and_line_height {
+        Value::List(values, Separator::Slash) => match values.as_slice() {
+            [size, line_height] => (size, Some(line_height.clone())),
+            _ => return None,
+        },
+        size => (size, None),
+    };
+    if !is_font_size(size) || family.is_empty() {
+        return None;
+    }
+
+    // Unquoted family names can contain spaces, as in `Times New Roman`.
+    let mut families = vec![family_name(family)?];
+    for family in more_families {
+        families.push(family_name(components(family))?);
+    }
+    let family = match families.len() {
+        1 => families.pop().unwrap(),
+        _ => Value::List(families, Separator::Comma),
+    };
+
+    Some(vec![
+        or_initial(style),
+        or_initial(variant),
+        or_initial(weight),
+        size.clone(),
+        or_initial(line_height),
+        family,
+    ])
+}
+
+/// Expand `flex: none | [grow shrink? || basis]`.
+///
+/// https://www.w3.org/TR/css-flexbox-1/#flex-property
+fn expand_flex(value: &Value) -> Option<Vec<Value>> {
+    let number = Value::Number;
+    if is_keyword(value, &["none"]) {
+        return Some(vec![number(0.0), number(0.0), Value::Keyword("auto".to_string())]);
+    }
+    if is_keyword(value, &["auto"]) {
+        return Some(vec![number(1.0), number(1.0), Value::Keyword("auto".to_string())]);
+    }
+
+    // A unitless zero is a flex factor where one can go, and a basis otherwise.
+    let is_factor = |value: &Value| matches!(value, Value::Number(factor) if *factor >= 0.0);
+    let is_basis = |value: &Value| is_length_percentage(value) || is_keyword(value, &["auto", "content"]);
+    let (grow, shrink, basis) = match components(value) {
+        [grow] if is_factor(grow) => (grow, None, None),
+        [basis] if is_basis(basis) => (&number(1.0), None, Some(basis)),
+        [grow, shrink] if is_factor(grow) && is_factor(shrink) => (grow, Some(shrink), None),
+        [grow, basis] | [basis, grow] if is_factor(grow) && is_basis(basis) => (grow, None, Some(basis)),
+        [grow, shrink, basis] | [basis, grow, shrink] if is_factor(grow) && is_factor(shrink) && is_basis(basis) => {
+            (grow, Some(shrink), Some(basis))
+        }
+        _ => return None,
+    };
+    // An omitted basis is zero, rather than the initial `auto`.
+    Some(vec![
+        grow.clone(),
+        shrink.cloned().unwrap_or(number(1.0)),
+        basis.cloned().unwrap_or(Value::Length(0.0, Unit::Percent)),
+    ])
+}
+
+/// Expand the direction and wrapping of a flex container, which can be given in either order.
+fn expand_flex_flow(value: &Value) -> Option<Vec<Value>> {
+    let mut direction = None;
+    let mut wrap = None;
+    for component in components(value) {
+        let slot = if is_keyword(component, &["row", "row-reverse", "column", "column-reverse"]) {
+            &mut direction
+        } else if is_keyword(component, &["nowrap", "wrap", "wrap-reverse"]) {
+            &mut wrap
+        } else {
+            return None;
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+    }
+    Some(vec![or_initial(direction), or_initial(wrap)])
+}
+
+/// Expand up to `count` grid lines separated by slashes, such as `span 2 / 4`. Lines that are
+/// left out are `auto`.
+///
+/// https://www.w3.org/TR/css-grid-1/#placement-shorthands
+fn expand_grid_lines(value: &Value, count: usize) -> Option<Vec<Value>> {
+    // Slashes bind more tightly than spaces in a value, so `span 2 / 4` is read as `span` and
+    // `2 / 4`, and has to be split again.
+    let mut lines = vec![Vec::new()];
+    for component in components(value) {
+        match component {
+            Value::List(values, Separator::Slash) => {
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        lines.push(Vec::new());
+                    }
+                    lines.last_mut()?.push(value.clone());
+                }
+            }
+            component => lines.last_mut()?.push(component.clone()),
+        }
+    }
+    if lines.len() > count {
+        return None;
+    }
+
+    let mut values = Vec::new();
+    for mut line in lines {
+        let line = match line.len() {
+            1 => line.pop()?,
+            _ => Value::List(line, Separator::Space),
+        };
+        if !is_grid_line(&line) {
+            return None;
+        }
+        values.push(line);
+    }
+    values.resize(count, Value::Keyword("auto".to_string()));
+    Some(values)
+}
+
+fn family_name(words: &[Value]) -> Option<Value> {
+    match words {
+        [Value::StringLiteral(name)] => Some(Value::StringLiteral(name.clone())),
+        words => {
+            let words = words
+                .iter()
+                .map(|word| match word {
+                    Value::Keyword(word) => Some(word.as_str()),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?;
+            Some(Value::Keyword(words.join(" ")))
+        }
+    }
+}
+
+fn or_initial(value: Option<Value>) -> Value {
+    value.unwrap_or_else(|| Value::Keyword("initial".to_string()))
+}
+
+fn is_keyword(value: &Value, keywords: &[&str]) -> bool {
+    matches!(value, Value::Keyword(keyword) if keywords.contains(&keyword.as_str()))
+}
+
+fn is_length_percentage(value: &Value) -> bool {
+    matches!(value, Value::Length(_, unit) if *unit != Unit::Fr) || matches!(value, Value::Calc(_) | Value::Number(0.0))
+}
+
+fn is_gap(value: &Value) -> bool {
+    is_length_percentage(value) || is_keyword(value, &["normal"])
+}
+
+/// Whether a value is `auto`, a line number, or a span of lines such as `span 2`.
+fn is_grid_line(value: &Value) -> bool {
+    let integer = |value: &Value| match value {
+        Value::Number(number) if number.fract() == 0.0 => Some(*number),
+        _ => None,
+    };
+    match components(value) {
+        [line] => is_keyword(line, &["auto"]) || integer(line).is_some_and(|line| line != 0.0),
+        [span, count] | [count, span] if is_keyword(span, &["span"]) => integer(count).is_some_and(|count| count > 0.0),
+        _ => false,
+    }
+}
+
+fn is_border_width(value: &Value) -> bool {
+    is_length_percentage(value) || is_keyword(value, &["thin", "medium", "thick"])
+}
+
+fn is_border_style(value: &Value) -> bool {
+    is_keyword(value, BORDER_STYLES)
+}
+
+fn is_color(value: &Value) -> bool {
+    matches!(value, Value::Color(_)) || is_keyword(value, &["currentcolor", "currentColor"])
+}
+
+fn is_font_size(value: &Value) -> bool {
+    is_length_percentage(value)
+        || is_keyword(value, &[
+            "xx-small", "x-small", "small", "medium", "large", "x-large", "xx-large", "larger", "smaller",
+        ])
+}
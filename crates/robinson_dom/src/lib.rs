@@ -1 +1,10 @@
+//! The document tree, and an HTML parser that builds it.
+//!
+//! The tree is that of the `html_parser` crate. Documents are parsed with [`parse`] rather than
+//! `Dom::parse`, which takes time quadratic in the size of the document.
+
+mod grammar;
+mod parser;
+
 pub use html_parser::*;
+pub use parser::parse;
@@ -0,0 +1,162 @@
+use pest::iterators::{Pair, Pairs};
+use pest::Parser;
+
+use crate::grammar::{Grammar, Rule};
+use crate::{Dom, DomVariant, Element, ElementVariant, Error, Node, Result};
+
+/// Parse an HTML document or fragment.
+///
+/// This builds the same tree as `Dom::parse`, but in linear time: `Dom::parse` finds the line and
+/// column of each element by counting from the start of the input, which takes time quadratic in
+/// the size of the document.
+pub fn parse(input: &str) -> Result<Dom> {
+    let pairs = Grammar::parse(Rule::html, input).map_err(|error| {
+        let error = error.renamed_rules(|rule| match rule {
+            Rule::EOI => "end of input".to_string(),
+            Rule::doctype => "doctype element".to_string(),
+            Rule::node_text => "text node".to_string(),
+            Rule::node_element => "element node".to_string(),
+            Rule::attr => "attribute (key=\"value\")".to_string(),
+            rule => format!("{:?}", rule),
+        });
+        Error::Parsing(error.to_string())
+    })?;
+    build_dom(pairs)
+}
+
+fn build_dom(pairs: Pairs<Rule>) -> Result<Dom> {
+    let mut dom = Dom::default();
+
+    // The document is a fragment unless it starts with a doctype, which is only parsed as one
+    // before any other tag.
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::doctype if dom.tree_type == DomVariant::Empty => dom.tree_type = DomVariant::Document,
+            Rule::node_element => match build_element(pair, &mut dom.errors) {
+                Ok(Some(node)) => {
+                    if dom.tree_type == DomVariant::Empty {
+                        dom.tree_type = DomVariant::DocumentFragment;
+                    }
+                    dom.children.push(node);
+                }
+                Ok(None) => {}
+                Err(error) => dom.errors.push(error.to_string()),
+            },
+            Rule::node_text => {
+                if dom.tree_type == DomVariant::Empty {
+                    dom.tree_type = DomVariant::DocumentFragment;
+                }
+                let text = pair.as_str();
+                if !text.trim().is_empty() {
+                    dom.children.push(Node::Text(text.to_string()));
+                }
+            }
+            Rule::node_comment => dom.children.push(Node::Comment(pair.into_inner().as_str().to_string())),
+            _ => {}
+        }
+    }
+
+    let is_html = |node: &Node| matches!(node, Node::Element(element) if element.name.eq_ignore_ascii_case("html"));
+    match dom.tree_type {
+        // Only comments, which make a fragment after all.
+        DomVariant::Empty if !dom.children.is_empty() => dom.tree_type = DomVariant::DocumentFragment,
+        DomVariant::Empty => {}
+        DomVariant::Document => {
+            if dom.children.iter().filter(|node| is_html(node)).count() > 1 {
+                return Err(Error::Parsing("Document with multiple HTML tags".to_string()));
+            }
+        }
+        // A fragment that is a single `html` element, before any other element, is a document.
+        // Other fragments can't have `head` or `body` elements at the top level.
+        DomVariant::DocumentFragment => {
+            let mut seen_elements = false;
+            for node in &dom.children {
+                let Node::Element(element) = node else {
+                    continue;
+                };
+                if is_html(node) && !seen_elements {
+                    dom.tree_type = DomVariant::Document;
+                } else if is_html(node) || element.name == "head" || element.name == "body" {
+                    return Err(Error::Parsing(format!("A document fragment should not include {}", element.name)));
+                }
+                seen_elements = true;
+            }
+        }
+    }
+    Ok(dom)
+}
+
+/// Build an element and its descendants, or `None` for an end tag without a start tag.
+fn build_element(pair: Pair<Rule>, errors: &mut Vec<String>) -> Result<Option<Node>> {
+    let mut element = Element::default();
+
+    // The pair's line and column come from an index of the lines of the input, and the end is
+    // counted from the start, so that finding them doesn't go through all of the input before.
+    let text = pair.as_str();
+    let (line, column) = pair.line_col();
+    let span = &mut element.source_span;
+    span.start_line = line;
+    span.start_column = column;
+    span.end_line = line + text.matches('\n').count();
+    span.end_column = match text.rfind('\n') {
+        Some(newline) => text[newline + 1..].chars().count() + 1,
+        None => column + text.chars().count(),
+    };
+    span.text = text.to_string();
+
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::node_element | Rule::el_raw_text => match build_element(pair, errors) {
+                Ok(Some(child)) => element.children.push(child),
+                Ok(None) => {}
+                Err(error) => errors.push(error.to_string()),
+            },
+            Rule::node_text | Rule::el_raw_text_content => {
+                let text = pair.as_str();
+                if !text.trim().is_empty() {
+                    element.children.push(Node::Text(text.to_string()));
+                }
+            }
+            Rule::node_comment => element.children.push(Node::Comment(pair.into_inner().as_str().to_string())),
+            Rule::el_name | Rule::el_void_name | Rule::el_raw_text_name => element.name = pair.as_str().to_string(),
+            Rule::attr => {
+                let (key, value) = build_attribute(pair.into_inner());
+                match key.as_str() {
+                    "id" => element.id = value,
+                    "class" => {
+                        let classes = value.iter().flat_map(|classes| classes.split_whitespace());
+                        element.classes.extend(classes.map(str::to_string));
+                    }
+                    _ => {
+                        element.attributes.insert(key, value);
+                    }
+                }
+            }
+            Rule::el_normal_end | Rule::el_raw_text_end => {
+                element.variant = ElementVariant::Normal;
+                break;
+            }
+            Rule::el_dangling | Rule::EOI => {}
+            rule => return Err(Error::Parsing(format!("Failed to create element at rule: {:?}", rule))),
+        }
+    }
+
+    Ok(match element.name.is_empty() {
+        true => None,
+        false => Some(Node::Element(element)),
+    })
+}
+
+/// Build the key of an attribute and its value, if it has one.
+fn build_attribute(pairs: Pairs<Rule>) -> (String, Option<String>) {
+    let mut attribute = (String::new(), None);
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::attr_key => attribute.0 = pair.as_str().trim().to_string(),
+            Rule::attr_non_quoted => attribute.1 = Some(pair.as_str().trim().to_string()),
+            Rule::attr_quoted => attribute.1 = pair.into_inner().next().map(|value| value.as_str().to_string()),
+            _ => {}
+        }
+    }
+    attribute
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use once_cell::sync::Lazy;
+use ttf_parser::name_id;
+
+use crate::{Error, Font};
+
+/// The font used when none of the requested families are available.
+static FALLBACK_FONT_DATA: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// Fonts loaded so far, shared by layout and all of the paint backends.
+static FONTS: Lazy<RwLock<FontContext>> = Lazy::new(|| RwLock::new(FontContext::new()));
+
+/// Families that generic CSS family names resolve to, in order of preference.
+const GENERIC_FAMILIES: &[(&str, &[&str])] = &[
+    ("sans-serif", &["DejaVu Sans", "Arial", "Helvetica", "Liberation Sans"]),
+    ("serif", &["DejaVu Serif", "Times New Roman", "Times", "Liberation Serif"]),
+    ("monospace", &["DejaVu Sans Mono", "Courier New", "Menlo", "Consolas", "Liberation Mono"]),
+];
+
+struct FontContext {
+    fallback: Arc<Font>,
+    fonts: Vec<Arc<Font>>,
+    /// Font files found in the system font directories, scanned on first use. Faces that fail
+    /// to load are removed, so that they aren't tried again.
+    system_fonts: Option<Vec<SystemFont>>,
+    /// The font selected for each `font-family` list, weight and slant requested so far,
+    /// including those that fell back to the bundled font.
+    selections: HashMap<FontQuery, Arc<Font>>,
+}
+
+/// A `font-family` list, and whether the font should be bold and italic.
+type FontQuery = (Vec<String>, bool, bool);
+
+/// A font face on disk that has not been loaded yet.
+struct SystemFont {
+    family: String,
+    bold: bool,
+    italic: bool,
+    path: PathBuf,
+    index: u32,
+}
+
+impl FontContext {
+    fn new() -> Self {
+        let fallback = Arc::new(Font::from_bytes(FALLBACK_FONT_DATA, 0).expect("bundled font is valid"));
+        Self {
+            fonts: vec![Arc::clone(&fallback)],
+            fallback,
+            system_fonts: None,
+            selections: HashMap::new(),
+        }
+    }
+
+    fn load_file(&mut self, path: &Path, index: u32) -> Result<Arc<Font>, Error> {
+        let font = Arc::new(Font::from_bytes(fs::read(path)?, index)?);
+        self.fonts.push(Arc::clone(&font));
+        Ok(font)
+    }
+
+    /// Find the face in `family` that best matches the requested weight and slant, loading it
+    /// from the system font directories if no loaded face matches exactly.
+    fn find(&mut self, family: &str, bold: bool, italic: bool) -> Option<Arc<Font>> {
+        let loaded = best_match(&self.fonts, |font| (font.family(), font.is_bold(), font.is_italic()), family, bold, italic)
+            .map(Arc::clone);
+        if let Some(font) = &loaded {
+            if font.is_bold() == bold && font.is_italic() == italic {
+                return loaded;
+            }
+        }
+
+        let system_fonts = self.system_fonts.get_or_insert_with(scan_system_fonts);
+        match best_match(system_fonts, |font| (&font.family, font.bold, font.italic), family, bold, italic) {
+            Some(found) if loaded.is_none() || (found.bold == bold && found.italic == italic) => {
+                let (path, index) = (found.path.clone(), found.index);
+                match self.load_file(&path, index) {
+                    Ok(font) => Some(font),
+                    Err(_) => {
+                        if let Some(system_fonts) = &mut self.system_fonts {
+                            system_fonts.retain(|font| font.path != path || font.index != index);
+                        }
+                        loaded
+                    }
+                }
+            }
+            _ => loaded,
+        }
+    }
+
+    fn select(&mut self, families: &[String], bold: bool, italic: bool) -> Arc<Font> {
+        let query = (families.to_vec(), bold, italic);
+        if let Some(font) = self.selections.get(&query) {
+            return Arc::clone(font);
+        }
+        let font = self.select_uncached(families, bold, italic);
+        self.selections.insert(query, Arc::clone(&font));
+        font
+    }
+
+    fn select_uncached(&mut self, families: &[String], bold: bool, italic: bool) -> Arc<Font> {
+        for family in families {
+            let candidates = GENERIC_FAMILIES
+                .iter()
+                .find(|(generic, _)| family.eq_ignore_ascii_case(generic))
+                .map(|(_, names)| names.iter().map(|name| name.to_string()).collect())
+                .unwrap_or_else(|| vec![family.clone()]);
+
+            for candidate in &candidates {
+                if let Some(font) = self.find(candidate, bold, italic) {
+                    return font;
+                }
+            }
+        }
+        Arc::clone(&self.fallback)
+    }
+}
+
+/// Find the face in `family` whose weight and slant best match the request.
+fn best_match<'a, T>(
+    fonts: &'a [T],
+    key: impl Fn(&T) -> (&str, bool, bool),
+    family: &str,
+    bold: bool,
+    italic: bool,
+) -> Option<&'a T> {
+    fonts
+        .iter()
+        .filter(|font| key(font).0.eq_ignore_ascii_case(family))
+        .max_by_key(|font| {
+            let (_, font_bold, font_italic) = key(font);
+            (font_italic == italic, font_bold == bold)
+        })
+}
+
+/// Directories that commonly contain installed fonts.
+fn font_directories() -> Vec<PathBuf> {
+    let mut directories = vec![
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+        PathBuf::from("/System/Library/Fonts"),
+        PathBuf::from("/Library/Fonts"),
+        PathBuf::from("C:\\Windows\\Fonts"),
+    ];
+    if let Some(home) = env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        directories.push(home.join(".fonts"));
+        directories.push(home.join(".local/share/fonts"));
+        directories.push(home.join("Library/Fonts"));
+    }
+    directories
+}
+
+fn scan_system_fonts() -> Vec<SystemFont> {
+    let mut fonts = Vec::new();
+    let mut pending = font_directories();
+    while let Some(directory) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&directory) else { continue };
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path.is_dir() {
+                pending.push(path);
+            } else if is_font_file(&path) {
+                scan_font_file(&path, &mut fonts);
+            }
+        }
+    }
+    fonts
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| matches!(&*extension.to_ascii_lowercase(), "ttf" | "otf" | "ttc" | "otc"))
+        .unwrap_or(false)
+}
+
+fn scan_font_file(path: &Path, fonts: &mut Vec<SystemFont>) {
+    let Ok(data) = fs::read(path) else { return };
+    let count = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+    for index in 0..count {
+        let Ok(face) = ttf_parser::Face::parse(&data, index) else { continue };
+        let family = face
+            .names()
+            .into_iter()
+            .filter(|name| {
+                (name.name_id == name_id::TYPOGRAPHIC_FAMILY || name.name_id == name_id::FAMILY)
+                    && name.is_unicode()
+            })
+            .find_map(|name| name.to_string());
+        if let Some(family) = family {
+            fonts.push(SystemFont {
+                family,
+                bold: face.is_bold(),
+                italic: face.is_italic(),
+                path: path.to_path_buf(),
+                index,
+            });
+        }
+    }
+}
+
+/// The bundled font that is used when no requested family is available.
+pub fn fallback_font() -> Arc<Font> {
+    Arc::clone(&FONTS.read().unwrap().fallback)
+}
+
+/// Load a font file so that it can be selected by its family name.
+pub fn load_font_file(path: &Path) -> Result<Arc<Font>, Error> {
+    let mut fonts = FONTS.write().unwrap();
+    // The new font can change which font a family list selects.
+    fonts.selections.clear();
+    fonts.load_file(path, 0)
+}
+
+/// Pick the first available font from a CSS `font-family` list.
+///
+/// Generic family names are resolved to common installed fonts, and the bundled fallback font is
+/// used if nothing matches.
+pub fn select_font(families: &[String], bold: bool, italic: bool) -> Arc<Font> {
+    // Fonts that were selected before are looked up without blocking other threads.
+    let query = (families.to_vec(), bold, italic);
+    if let Some(font) = FONTS.read().unwrap().selections.get(&query) {
+        return Arc::clone(font);
+    }
+    FONTS.write().unwrap().select(families, bold, italic)
+}
@@ -0,0 +1,10 @@
+use std::io;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("unsupported or malformed font data")]
+    InvalidFont,
+}
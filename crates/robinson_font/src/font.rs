@@ -0,0 +1,287 @@
+use std::{borrow::Cow, collections::HashMap, fmt, sync::{Arc, Mutex}};
+
+use ttf_parser::name_id;
+use rustybuzz::{Face, UnicodeBuffer};
+
+use crate::Error;
+
+/// A loaded font face, ready for shaping and rasterization.
+pub struct Font {
+    family: String,
+    postscript_name: String,
+    bold: bool,
+    italic: bool,
+    /// Borrows from `data`, so it is declared first to be dropped before it.
+    face: Face<'static>,
+    data: Cow<'static, [u8]>,
+    raster: fontdue::Font,
+    glyph_cache: Mutex<HashMap<(u16, u32), Arc<GlyphBitmap>>>,
+}
+
+/// The largest size glyphs are rasterized at, in px. Larger glyphs are rasterized at this size
+/// and scaled up when painted, so that a huge font size can't exhaust memory.
+pub const MAX_RASTER_SIZE: f32 = 512.0;
+
+/// Vertical metrics of a font at a given size, in px.
+#[derive(Clone, Copy, Debug)]
+pub struct FontMetrics {
+    /// Distance from the baseline to the top of the em box.
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the em box (positive downwards).
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+/// A glyph positioned relative to the start of its run, in px.
+#[derive(Clone, Copy, Debug)]
+pub struct ShapedGlyph {
+    pub glyph_id: u16,
+    pub x: f32,
+    /// Offset from the baseline (positive downwards).
+    pub y: f32,
+    pub advance: f32,
+}
+
+/// The result of shaping a run of text with a single font and size.
+#[derive(Clone, Debug, Default)]
+pub struct ShapedRun {
+    pub glyphs: Vec<ShapedGlyph>,
+    pub width: f32,
+}
+
+/// An 8-bit coverage mask for a single glyph.
+#[derive(Debug)]
+pub struct GlyphBitmap {
+    /// Offset from the glyph origin to the left edge of the bitmap, in px.
+    pub left: i32,
+    /// Offset from the baseline up to the top edge of the bitmap, in px.
+    pub top: i32,
+    /// Size of each coverage value in px, which is above 1 for glyphs larger than
+    /// `MAX_RASTER_SIZE`.
+    pub scale: f32,
+    pub width: usize,
+    pub height: usize,
+    /// Row-major coverage values, top row first.
+    pub coverage: Vec<u8>,
+}
+
+/// The extent of a glyph's outline relative to its origin on the baseline, in px. `top` and
+/// `bottom` are measured downwards.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphBounds {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+/// Font-wide values needed to embed the font in other formats, in font units.
+#[derive(Clone, Debug)]
+pub struct FontInfo {
+    pub postscript_name: String,
+    pub units_per_em: u16,
+    pub ascender: i16,
+    pub descender: i16,
+    pub cap_height: i16,
+    pub italic_angle: f32,
+    /// Bounding box of all glyphs as `[x_min, y_min, x_max, y_max]`.
+    pub bounding_box: [i16; 4],
+    pub outline_format: OutlineFormat,
+}
+
+/// How a font stores its glyph outlines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlineFormat {
+    /// Quadratic outlines in a `glyf` table.
+    TrueType,
+    /// Cubic outlines in a `CFF ` or `CFF2` table, as in most .otf files.
+    Cff,
+}
+
+impl Font {
+    /// Parse face `index` of a font file or collection.
+    pub fn from_bytes(data: impl Into<Cow<'static, [u8]>>, index: u32) -> Result<Self, Error> {
+        let data = data.into();
+        // SAFETY: the bytes are either static or owned by `data`, whose heap allocation doesn't
+        // move when the font does. `data` is never modified and outlives `face`, which is
+        // dropped first and never lent out for longer than the font.
+        let bytes: &'static [u8] = unsafe { &*(data.as_ref() as *const [u8]) };
+        let face = Face::from_slice(bytes, index).ok_or(Error::InvalidFont)?;
+        let settings = fontdue::FontSettings {
+            collection_index: index,
+            ..Default::default()
+        };
+        let raster = fontdue::Font::from_bytes(bytes, settings).map_err(|_| Error::InvalidFont)?;
+
+        let name = |id: u16| {
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == id && name.is_unicode())
+                .find_map(|name| name.to_string())
+        };
+        let family = name(name_id::TYPOGRAPHIC_FAMILY)
+            .or_else(|| name(name_id::FAMILY))
+            .unwrap_or_default();
+        let postscript_name = name(name_id::POST_SCRIPT_NAME)
+            .unwrap_or_else(|| family.replace(' ', ""));
+
+        Ok(Self {
+            family,
+            postscript_name,
+            bold: face.is_bold(),
+            italic: face.is_italic(),
+            data,
+            face,
+            raster,
+            glyph_cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn family(&self) -> &str {
+        &self.family
+    }
+
+    pub fn is_bold(&self) -> bool {
+        self.bold
+    }
+
+    pub fn is_italic(&self) -> bool {
+        self.italic
+    }
+
+    /// The font file holding just this face. Faces of a collection (.ttc or .otc) are copied
+    /// out into a standalone font file, since a collection can't be embedded as is.
+    pub fn face_data(&self) -> Cow<'_, [u8]> {
+        if !self.data.starts_with(b"ttcf") {
+            return Cow::Borrowed(&self.data);
+        }
+
+        let records = self.face.raw_face().table_records;
+        let num_tables = records.len();
+        let mut data = Vec::new();
+        // The table directory: the sfnt version, which tells the outline format, and the table
+        // count with its binary search hints.
+        data.extend_from_slice(match self.outline_format() {
+            OutlineFormat::TrueType => &[0, 1, 0, 0],
+            OutlineFormat::Cff => b"OTTO",
+        });
+        let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+        let search_range = 16 << entry_selector;
+        for value in [num_tables, search_range, entry_selector, num_tables * 16 - search_range] {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+
+        // The tables follow the directory, each aligned to 4 bytes.
+        let mut offset = 12 + 16 * num_tables as u32;
+        for record in records {
+            data.extend_from_slice(&record.tag.to_bytes());
+            data.extend_from_slice(&record.check_sum.to_be_bytes());
+            data.extend_from_slice(&offset.to_be_bytes());
+            data.extend_from_slice(&record.length.to_be_bytes());
+            offset += (record.length + 3) & !3;
+        }
+        for record in records {
+            let start = record.offset as usize;
+            let table = self.data.get(start..start + record.length as usize).unwrap_or_default();
+            data.extend_from_slice(table);
+            data.resize((data.len() + 3) & !3, 0);
+        }
+        Cow::Owned(data)
+    }
+
+    pub fn metrics(&self, size: f32) -> FontMetrics {
+        let scale = self.scale(size);
+        FontMetrics {
+            ascent: self.face.ascender() as f32 * scale,
+            descent: -self.face.descender() as f32 * scale,
+            line_gap: self.face.line_gap() as f32 * scale,
+        }
+    }
+
+    /// Shape `text` into positioned glyphs, applying kerning and ligatures.
+    pub fn shape(&self, text: &str, size: f32) -> ShapedRun {
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        let output = rustybuzz::shape(&self.face, &[], buffer);
+
+        let scale = self.scale(size);
+        let mut run = ShapedRun::default();
+        for (info, position) in output.glyph_infos().iter().zip(output.glyph_positions()) {
+            let advance = position.x_advance as f32 * scale;
+            run.glyphs.push(ShapedGlyph {
+                glyph_id: info.glyph_id as u16,
+                x: run.width + position.x_offset as f32 * scale,
+                y: -position.y_offset as f32 * scale,
+                advance,
+            });
+            run.width += advance;
+        }
+        run
+    }
+
+    /// The bounds of a glyph's outline at `size` px, or `None` if it has no outline.
+    pub fn glyph_bounds(&self, glyph_id: u16, size: f32) -> Option<GlyphBounds> {
+        let bounds = self.face.glyph_bounding_box(ttf_parser::GlyphId(glyph_id))?;
+        let scale = self.scale(size);
+        Some(GlyphBounds {
+            left: bounds.x_min as f32 * scale,
+            top: -bounds.y_max as f32 * scale,
+            right: bounds.x_max as f32 * scale,
+            bottom: -bounds.y_min as f32 * scale,
+        })
+    }
+
+    /// Render a glyph's coverage mask, caching the result.
+    pub fn rasterize(&self, glyph_id: u16, size: f32) -> Arc<GlyphBitmap> {
+        let raster_size = size.min(MAX_RASTER_SIZE);
+        let scale = size / raster_size;
+        let mut cache = self.glyph_cache.lock().unwrap();
+        Arc::clone(cache.entry((glyph_id, size.to_bits())).or_insert_with(|| {
+            let (metrics, coverage) = self.raster.rasterize_indexed(glyph_id, raster_size);
+            Arc::new(GlyphBitmap {
+                left: (metrics.xmin as f32 * scale).round() as i32,
+                top: ((metrics.ymin + metrics.height as i32) as f32 * scale).round() as i32,
+                scale,
+                width: metrics.width,
+                height: metrics.height,
+                coverage,
+            })
+        }))
+    }
+
+    pub fn info(&self) -> FontInfo {
+        let bbox = self.face.global_bounding_box();
+        FontInfo {
+            postscript_name: self.postscript_name.clone(),
+            units_per_em: self.face.units_per_em() as u16,
+            ascender: self.face.ascender(),
+            descender: self.face.descender(),
+            cap_height: self.face.capital_height().unwrap_or(self.face.ascender()),
+            italic_angle: self.face.italic_angle().unwrap_or(0.0),
+            bounding_box: [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max],
+            outline_format: self.outline_format(),
+        }
+    }
+
+    fn outline_format(&self) -> OutlineFormat {
+        if self.face.tables().glyf.is_some() {
+            OutlineFormat::TrueType
+        } else {
+            OutlineFormat::Cff
+        }
+    }
+
+    fn scale(&self, size: f32) -> f32 {
+        size / self.face.units_per_em() as f32
+    }
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("family", &self.family)
+            .field("bold", &self.bold)
+            .field("italic", &self.italic)
+            .finish()
+    }
+}
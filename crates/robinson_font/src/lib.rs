@@ -0,0 +1,9 @@
+//! Font loading, text shaping and glyph rasterization.
+
+mod context;
+mod error;
+mod font;
+
+pub use context::*;
+pub use error::*;
+pub use font::*;
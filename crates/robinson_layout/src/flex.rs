@@ -0,0 +1,560 @@
+//! Flex layout: items laid out in lines along a main axis, grown or shrunk to fill them, and
+//! aligned along both axes.
+//!
+//! https://www.w3.org/TR/css-flexbox-1/#layout-algorithm
+//!
+//! Items aren't aligned on baselines, and their minimum size is zero rather than the size of
+//! their contents.
+
+use std::ops::Range;
+use std::sync::Arc;
+
+use robinson_css::Unit::Px;
+use robinson_css::Value::{Keyword, Length, Number};
+use robinson_style::Display;
+
+use crate::intrinsic::IntrinsicSize;
+use crate::{
+    clamp_size, content_size, is_border_box, keyword, BoxType, Dimensions, FlowPosition, LayoutBox, LengthContext,
+    Rect, RenderBox,
+};
+
+/// Where items or lines go in the space along an axis, or how much of it they fill.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Alignment {
+    Start,
+    End,
+    Center,
+    Stretch,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// The properties of a flex container that its items are sized by.
+struct FlexContainer {
+    /// Whether the main axis is horizontal.
+    row: bool,
+    multi_line: bool,
+    /// The width of the content box, and the height if it doesn't depend on the items.
+    width: f32,
+    height: Option<f32>,
+    align_items: Alignment,
+    lengths: LengthContext,
+}
+
+impl FlexContainer {
+    fn main_size(&self) -> Option<f32> {
+        match self.row {
+            true => Some(self.width),
+            false => self.height,
+        }
+    }
+}
+
+/// A flex item and its sizes. Sizes are of the content box, and the edges are the margins,
+/// borders and padding around it along each axis.
+struct FlexItem {
+    /// Index of the item's box among the container's children.
+    index: usize,
+    order: i32,
+    main_edges: f32,
+    cross_edges: f32,
+    /// Which margins are `auto`: main start, main end, cross start and cross end.
+    auto_margins: [bool; 4],
+    flex_base_size: f32,
+    hypothetical_main_size: f32,
+    min_main: f32,
+    max_main: Option<f32>,
+    min_cross: f32,
+    max_cross: Option<f32>,
+    grow: f32,
+    shrink: f32,
+    align: Alignment,
+    /// Whether the item fills the cross size of its line.
+    stretch: bool,
+
+    main: f32,
+    cross: f32,
+    /// The offsets of the margin box from the start of the line along each axis.
+    main_position: f32,
+    cross_position: f32,
+    /// The item laid out at its used sizes, unless it still needs to be.
+    render_box: Option<RenderBox>,
+}
+
+impl FlexItem {
+    fn outer_main(&self) -> f32 {
+        self.main + self.main_edges
+    }
+
+    fn outer_cross(&self) -> f32 {
+        self.cross + self.cross_edges
+    }
+}
+
+impl LayoutBox {
+    pub(crate) fn is_flex_container(&self) -> bool {
+        matches!(&self.box_type, BoxType::BlockNode(node) if matches!(node.display(), Display::Flex | Display::InlineFlex))
+    }
+
+    /// Lay out the children of a flex container whose width is known, starting at the top of
+    /// the container's content box at `flow`, and move `flow` to the bottom of the content.
+    ///
+    /// Returns the items in the order they are laid out in.
+    pub(crate) fn layout_flex_children(&mut self, lengths: LengthContext, flow: &mut FlowPosition) -> Vec<RenderBox> {
+        let style = Arc::clone(self.get_style_node());
+        let content = Rect { y: flow.y, ..self.dimensions.content };
+
+        let direction = keyword(&style, "flex-direction", "row");
+        let wrap = keyword(&style, "flex-wrap", "nowrap");
+        let reverse = direction.ends_with("-reverse");
+        let wrap_reverse = wrap == "wrap-reverse";
+        let container = FlexContainer {
+            row: !direction.starts_with("column"),
+            multi_line: wrap != "nowrap",
+            width: content.width,
+            height: lengths.containing_height,
+            align_items: alignment(&keyword(&style, "align-items", "normal"), wrap_reverse, Alignment::Stretch),
+            lengths,
+        };
+        let row = container.row;
+
+        // Gaps that are percentages of a height that depends on the items are zero.
+        let column_gap = lengths.resolve(&style.lookup("column-gap", &Length(0.0, Px))).to_px();
+        let row_gap = lengths.resolve_height(&style.lookup("row-gap", &Length(0.0, Px))).to_px();
+        let (main_gap, cross_gap) = if row { (column_gap, row_gap) } else { (row_gap, column_gap) };
+
+        // Absolutely positioned children aren't flex items, and are placed where the content box
+        // starts.
+        let mut items = Vec::new();
+        for (index, child) in self.children.iter_mut().enumerate() {
+            if child.position().is_absolute() {
+                child.static_position = (content.x, content.y);
+                continue;
+            }
+            let mut item = child.flex_item(index, &container);
+            if reverse {
+                item.auto_margins.swap(0, 1);
+            }
+            if wrap_reverse {
+                item.auto_margins.swap(2, 3);
+            }
+            items.push(item);
+        }
+        // Sorting is stable, so items with the same `order` stay in tree order.
+        items.sort_by_key(|item| item.order);
+
+        // Collect the items into lines, and fit each line to the container.
+        let lines = collect_lines(&items, &container, main_gap);
+        for line in &lines {
+            resolve_flexible_lengths(&mut items[line.clone()], container.main_size(), main_gap);
+        }
+
+        // Find the cross size of each item by laying it out at its main size. Items in a column
+        // already know their width, and are laid out once it is final.
+        if row {
+            for item in &mut items {
+                let child = &mut self.children[item.index];
+                item.render_box = Some(child.layout_item(lengths, item.main, None));
+                item.cross = child.dimensions.content.height;
+            }
+        }
+
+        // A single line fills a container whose cross size is known, and otherwise each line is
+        // as big as its biggest item.
+        let available_cross = if row { container.height } else { Some(container.width) };
+        let mut line_sizes: Vec<f32> = lines
+            .iter()
+            .map(|line| items[line.clone()].iter().map(FlexItem::outer_cross).fold(0.0, f32::max))
+            .collect();
+        let (mut line_position, mut line_spacing) = (0.0, 0.0);
+        match available_cross {
+            Some(cross) if !container.multi_line => line_sizes[0] = cross,
+            Some(cross) => {
+                let free = cross - line_sizes.iter().sum::<f32>() - cross_gap * (lines.len() - 1) as f32;
+                match alignment(&keyword(&style, "align-content", "normal"), wrap_reverse, Alignment::Stretch) {
+                    Alignment::Stretch if free > 0.0 => {
+                        line_sizes.iter_mut().for_each(|size| *size += free / lines.len() as f32);
+                    }
+                    align => (line_position, line_spacing) = distribute(align, free, lines.len()),
+                }
+            }
+            None => {}
+        }
+        let cross_size = available_cross.unwrap_or_else(|| {
+            line_sizes.iter().sum::<f32>() + cross_gap * (lines.len() - 1) as f32
+        });
+
+        // Place the items along the main axis within each line, and along the cross axis within
+        // their line.
+        let justify = alignment(&keyword(&style, "justify-content", "normal"), reverse, Alignment::Start);
+        let mut content_main_size: f32 = 0.0;
+        for (line, &line_size) in lines.iter().zip(&line_sizes) {
+            let line_items = &mut items[line.clone()];
+            let used = line_items.iter().map(FlexItem::outer_main).sum::<f32>()
+                + main_gap * line_items.len().saturating_sub(1) as f32;
+            content_main_size = content_main_size.max(used);
+            place_line(line_items, container.main_size().unwrap_or(used) - used, main_gap, justify);
+
+            for item in line_items.iter_mut() {
+                if item.stretch {
+                    item.cross = clamp_size(line_size - item.cross_edges, item.min_cross, item.max_cross);
+                    item.render_box = None;
+                }
+                let free = line_size - item.outer_cross();
+                let offset = match item.auto_margins[2..] {
+                    [true, true] if free > 0.0 => free / 2.0,
+                    [true, false] if free > 0.0 => free,
+                    [false, true] => 0.0,
+                    _ => match item.align {
+                        Alignment::End => free,
+                        Alignment::Center => free / 2.0,
+                        _ => 0.0,
+                    },
+                };
+                item.cross_position = line_position + offset;
+            }
+            line_position += line_size + cross_gap + line_spacing;
+        }
+
+        // Lay out the items where they go. Reversed axes are placed from the other end.
+        let main_size = container.main_size().unwrap_or(content_main_size);
+        let (width, height) = if row { (main_size, cross_size) } else { (cross_size, main_size) };
+        let containing_block = Dimensions { content: Rect { width, height, ..content }, ..Default::default() };
+        let mut children = Vec::new();
+        for mut item in items {
+            let child = &mut self.children[item.index];
+            let mut render_box = match item.render_box.take() {
+                Some(render_box) => render_box,
+                None if row => child.layout_item(lengths, item.main, Some(item.cross)),
+                None => child.layout_item(lengths, item.cross, Some(item.main)),
+            };
+
+            let main = match reverse {
+                true => main_size - item.main_position - item.outer_main(),
+                false => item.main_position,
+            };
+            let cross = match wrap_reverse {
+                true => cross_size - item.cross_position - item.outer_cross(),
+                false => item.cross_position,
+            };
+            let (x, y) = if row { (main, cross) } else { (cross, main) };
+
+            // A relatively positioned item is also moved from where it was placed.
+            let (offset_x, offset_y) = child.relative_offset(&containing_block, lengths);
+            let (dx, dy) = (content.x + x + offset_x, content.y + y + offset_y);
+            child.dimensions.content.translate(dx, dy);
+            render_box.translate(dx, dy);
+            children.push(render_box);
+        }
+
+        flow.move_to(content.y + height);
+        children
+    }
+
+    /// The sizes of a flex item that don't depend on the other items.
+    ///
+    /// https://www.w3.org/TR/css-flexbox-1/#algo-main-item
+    fn flex_item(&mut self, index: usize, container: &FlexContainer) -> FlexItem {
+        let lengths = container.lengths;
+        let auto = Keyword("auto".to_string());
+        let zero = Length(0.0, Px);
+
+        // Runs of text are wrapped in anonymous items, which have no margins, borders or padding,
+        // and take their other properties from the container.
+        let style = match &self.box_type {
+            BoxType::BlockNode(node) => Some(Arc::clone(node)),
+            _ => None,
+        };
+        let mut auto_margins = [false; 4];
+        match &style {
+            Some(style) => {
+                let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
+                let d = &mut self.dimensions;
+                d.margin.left = lookup("margin-left");
+                d.margin.right = lookup("margin-right");
+                d.border.left = lookup("border-left-width");
+                d.border.right = lookup("border-right-width");
+                d.padding.left = lookup("padding-left");
+                d.padding.right = lookup("padding-right");
+                self.calculate_block_position(&Dimensions::default(), lengths);
+
+                // Auto margins are zero until the free space is known.
+                let sides = match container.row {
+                    true => ["margin-left", "margin-right", "margin-top", "margin-bottom"],
+                    false => ["margin-top", "margin-bottom", "margin-left", "margin-right"],
+                };
+                auto_margins = sides.map(|side| style.lookup(side, &zero) == auto);
+            }
+            None => self.dimensions = Dimensions::default(),
+        }
+        let d = self.dimensions;
+        let horizontal_edges = d.margin.left + d.margin.right + d.border.left + d.border.right + d.padding.left + d.padding.right;
+        let vertical_edges = d.margin.top + d.margin.bottom + d.border.top + d.border.bottom + d.padding.top + d.padding.bottom;
+
+        let ((width, min_width, max_width), (height, min_height, max_height)) = match &style {
+            Some(_) => {
+                let (width, min_width, max_width) = self.width_constraints(lengths);
+                let width = match width {
+                    Length(width, Px) => Some(width),
+                    _ => None,
+                };
+                ((width, min_width, max_width), self.height_constraints(lengths))
+            }
+            None => ((None, 0.0, None), (None, 0.0, None)),
+        };
+
+        let align = match style.as_ref().map(|style| keyword(style, "align-self", "auto")) {
+            Some(align) if align != "auto" => alignment(&align, false, Alignment::Stretch),
+            _ => container.align_items,
+        };
+        let cross_size = if container.row { height } else { width };
+        let stretch = align == Alignment::Stretch && cross_size.is_none() && auto_margins[2..] == [false, false];
+
+        // Items in a column are as wide as they will be before their heights are known, unless
+        // they are stretched to lines that aren't known yet, in which case they fit their
+        // contents until then.
+        let mut cross = 0.0;
+        if !container.row {
+            let available = (container.width - horizontal_edges).max(0.0);
+            let width = match width {
+                Some(width) => width,
+                None if stretch && !container.multi_line => available,
+                None => self.max_content_width(lengths).min(available),
+            };
+            cross = clamp_size(width, min_width, max_width);
+        }
+
+        // The flex base size is the `flex-basis`, or the main size if that is `auto`, or the size
+        // of the contents if that is `auto` too.
+        let (main_size, min_main, max_main, min_cross, max_cross) = match container.row {
+            true => (width, min_width, max_width, min_height, max_height),
+            false => (height, min_height, max_height, min_width, max_width),
+        };
+        let basis = style.as_ref().map_or(auto.clone(), |style| style.lookup("flex-basis", &auto));
+        let basis = match container.row {
+            true => lengths.resolve(&basis),
+            false => lengths.resolve_height(&basis),
+        };
+        let sizing = match style.as_deref().is_some_and(is_border_box) {
+            true if container.row => d.border.left + d.border.right + d.padding.left + d.padding.right,
+            true => d.border.top + d.border.bottom + d.padding.top + d.padding.bottom,
+            false => 0.0,
+        };
+        let flex_base_size = match (content_size(basis.clone(), sizing), main_size) {
+            (Length(basis, Px), _) => basis,
+            (_, Some(main_size)) if basis == auto => main_size,
+            _ if container.row => self.max_content_width(lengths),
+            _ => {
+                self.layout_item(lengths, cross, None);
+                self.dimensions.content.height
+            }
+        };
+
+        let number = |name: &str, default: f32| match style.as_ref().and_then(|style| style.get_value(name)) {
+            Some(Number(number)) => number,
+            _ => default,
+        };
+        FlexItem {
+            index,
+            order: number("order", 0.0) as i32,
+            main_edges: if container.row { horizontal_edges } else { vertical_edges },
+            cross_edges: if container.row { vertical_edges } else { horizontal_edges },
+            auto_margins,
+            flex_base_size,
+            hypothetical_main_size: clamp_size(flex_base_size, min_main, max_main),
+            min_main,
+            max_main,
+            min_cross,
+            max_cross,
+            grow: number("flex-grow", 0.0).max(0.0),
+            shrink: number("flex-shrink", 1.0).max(0.0),
+            align,
+            stretch,
+            main: 0.0,
+            cross,
+            main_position: 0.0,
+            cross_position: 0.0,
+            render_box: None,
+        }
+    }
+
+    /// The min-content or max-content width of a flex container's content box: that of its
+    /// widest item, or of the items side by side on a single line.
+    pub(crate) fn flex_intrinsic_width(&self, size: IntrinsicSize, lengths: LengthContext) -> f32 {
+        let style = self.get_style_node();
+        let items = self.children.iter().filter(|child| !child.position().is_absolute());
+        let column = keyword(style, "flex-direction", "row").starts_with("column");
+        // The min-content width of a multi-line container has each item on a line of its own.
+        let wrap = size == IntrinsicSize::MinContent && keyword(style, "flex-wrap", "nowrap") != "nowrap";
+        match column || wrap {
+            true => items.map(|item| item.outer_intrinsic_width(size, lengths)).fold(0.0, f32::max),
+            false => {
+                let gap = lengths.resolve(&style.lookup("column-gap", &Length(0.0, Px))).to_px();
+                let (count, width) = items.fold((0, 0.0), |(count, width), item| {
+                    (count + 1, width + item.outer_intrinsic_width(size, lengths))
+                });
+                width + gap * (count as f32 - 1.0).max(0.0)
+            }
+        }
+    }
+}
+
+/// Break the items into lines that fit the main size of a multi-line container, or put them all
+/// on one line.
+///
+/// https://www.w3.org/TR/css-flexbox-1/#algo-line-break
+fn collect_lines(items: &[FlexItem], container: &FlexContainer, gap: f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut used = 0.0;
+    for (index, item) in items.iter().enumerate() {
+        let outer = item.hypothetical_main_size + item.main_edges;
+        if index > start {
+            let overflows = container.main_size().is_some_and(|available| used + gap + outer > available);
+            if container.multi_line && overflows {
+                lines.push(start..index);
+                start = index;
+                used = outer;
+                continue;
+            }
+            used += gap;
+        }
+        used += outer;
+    }
+    lines.push(start..items.len());
+    lines
+}
+
+/// Grow or shrink the items on a line so that they fill the `available` main size, within their
+/// min and max sizes. Without an available size, the items keep their hypothetical sizes.
+///
+/// https://www.w3.org/TR/css-flexbox-1/#resolve-flexible-lengths
+fn resolve_flexible_lengths(items: &mut [FlexItem], available: Option<f32>, gap: f32) {
+    for item in items.iter_mut() {
+        item.main = item.hypothetical_main_size;
+    }
+    let Some(available) = available else { return };
+    let available = available - gap * items.len().saturating_sub(1) as f32;
+
+    let hypothetical: f32 = items.iter().map(FlexItem::outer_main).sum();
+    let growing = hypothetical < available;
+    let factor = |item: &FlexItem| if growing { item.grow } else { item.shrink };
+
+    // Inflexible items, and items that their min or max size keeps from flexing the right way,
+    // keep their hypothetical sizes.
+    let mut frozen: Vec<bool> = items
+        .iter()
+        .map(|item| {
+            factor(item) == 0.0
+                || (growing && item.flex_base_size > item.hypothetical_main_size)
+                || (!growing && item.flex_base_size < item.hypothetical_main_size)
+        })
+        .collect();
+    let free_space = |items: &[FlexItem], frozen: &[bool]| {
+        let used: f32 = items
+            .iter()
+            .zip(frozen)
+            .map(|(item, &frozen)| match frozen {
+                true => item.outer_main(),
+                false => item.flex_base_size + item.main_edges,
+            })
+            .sum();
+        available - used
+    };
+    let initial_free_space = free_space(items, &frozen);
+
+    // Distribute the free space by flex factor, and freeze the items that a min or max size
+    // stops, until none are left.
+    while frozen.contains(&false) {
+        let unfrozen = || items.iter().zip(&frozen).filter(|(_, &frozen)| !frozen).map(|(item, _)| item);
+        let factors: f32 = unfrozen().map(factor).sum();
+        let scaled_shrink_factors: f32 = unfrozen().map(|item| item.shrink * item.flex_base_size).sum();
+
+        // Factors that add up to less than one only take that fraction of the free space.
+        let mut free = free_space(items, &frozen);
+        if factors < 1.0 && (initial_free_space * factors).abs() < free.abs() {
+            free = initial_free_space * factors;
+        }
+
+        let mut violations = vec![0.0; items.len()];
+        for (index, item) in items.iter_mut().enumerate().filter(|(index, _)| !frozen[*index]) {
+            let target = match growing {
+                true => item.flex_base_size + free * item.grow / factors,
+                false if scaled_shrink_factors > 0.0 => {
+                    item.flex_base_size + free * item.shrink * item.flex_base_size / scaled_shrink_factors
+                }
+                false => item.flex_base_size,
+            };
+            item.main = clamp_size(target.max(0.0), item.min_main, item.max_main);
+            violations[index] = item.main - target;
+        }
+
+        // If the min sizes stop more than the max sizes, freeze the items at their min sizes, and
+        // the other way round. Otherwise, all of them are at their final sizes.
+        let total: f32 = violations.iter().sum();
+        for (frozen, violation) in frozen.iter_mut().zip(violations).filter(|(frozen, _)| !**frozen) {
+            *frozen = total == 0.0 || (total > 0.0 && violation > 0.0) || (total < 0.0 && violation < 0.0);
+        }
+    }
+}
+
+/// Place the items of a line along the main axis, given the main size that they leave free.
+/// The space goes to their `auto` margins if they have any, or is spread by `justify`.
+fn place_line(items: &mut [FlexItem], free: f32, gap: f32, justify: Alignment) {
+    let auto_margins = items.iter().flat_map(|item| &item.auto_margins[..2]).filter(|&&auto| auto).count();
+    let (mut position, spacing, auto_margin) = match free > 0.0 && auto_margins > 0 {
+        true => (0.0, 0.0, free / auto_margins as f32),
+        false => {
+            let (leading, spacing) = distribute(justify, free, items.len());
+            (leading, spacing, 0.0)
+        }
+    };
+    for item in items {
+        if item.auto_margins[0] {
+            position += auto_margin;
+        }
+        item.main_position = position;
+        position += item.outer_main() + gap + spacing;
+        if item.auto_margins[1] {
+            position += auto_margin;
+        }
+    }
+}
+
+/// The space before the first of `count` things in `free` space, and between each of them.
+///
+/// Spreading space that overflows centers the things instead.
+pub(crate) fn distribute(alignment: Alignment, free: f32, count: usize) -> (f32, f32) {
+    let count = count as f32;
+    match alignment {
+        Alignment::End => (free, 0.0),
+        Alignment::Center => (free / 2.0, 0.0),
+        Alignment::SpaceBetween if free > 0.0 && count > 1.0 => (0.0, free / (count - 1.0)),
+        Alignment::SpaceAround if free > 0.0 => (free / count / 2.0, free / count),
+        Alignment::SpaceEvenly if free > 0.0 => (free / (count + 1.0), free / (count + 1.0)),
+        Alignment::SpaceAround | Alignment::SpaceEvenly if free < 0.0 => (free / 2.0, 0.0),
+        _ => (0.0, 0.0),
+    }
+}
+
+/// The alignment a keyword of the box alignment properties stands for.
+///
+/// `reversed` is whether the flex-relative start of the axis is at its end, which `start` and
+/// `end` don't follow. `normal` behaves as `stretch` or `flex-start` depending on the property.
+pub(crate) fn alignment(keyword: &str, reversed: bool, normal: Alignment) -> Alignment {
+    match keyword {
+        "normal" => normal,
+        "flex-end" => Alignment::End,
+        "start" | "self-start" if reversed => Alignment::End,
+        "end" | "self-end" if !reversed => Alignment::End,
+        "center" => Alignment::Center,
+        "stretch" => Alignment::Stretch,
+        "space-between" => Alignment::SpaceBetween,
+        "space-around" => Alignment::SpaceAround,
+        "space-evenly" => Alignment::SpaceEvenly,
+        // `flex-start`, and baselines, which items aren't aligned on.
+        _ => Alignment::Start,
+    }
+}
@@ -0,0 +1,157 @@
+//! Floats, and the space they leave for the content around them.
+//!
+//! https://www.w3.org/TR/CSS2/visuren.html#floats
+
+use std::sync::Arc;
+
+use robinson_css::Unit::Px;
+use robinson_css::Value::Length;
+use robinson_style::{Clear, Float};
+
+use crate::{clamp_size, Dimensions, FlowPosition, LayoutBox, LengthContext, Rect, RenderBox};
+
+/// A float that has been placed in a block formatting context.
+#[derive(Clone, Copy, Debug)]
+struct PlacedFloat {
+    side: Float,
+    /// The float's margin box.
+    rect: Rect,
+}
+
+impl PlacedFloat {
+    fn bottom(&self) -> f32 {
+        self.rect.y + self.rect.height
+    }
+
+    /// Whether the float is beside any part of the band from `top` to `top + height`. A band of
+    /// zero height is the line at `top`.
+    fn overlaps(&self, top: f32, height: f32) -> bool {
+        self.bottom() > top && (self.rect.y <= top || self.rect.y < top + height)
+    }
+}
+
+/// The floats of a block formatting context, in the order they were placed.
+#[derive(Clone, Debug, Default)]
+pub struct Floats {
+    floats: Vec<PlacedFloat>,
+}
+
+impl Floats {
+    /// The left and right edges of the space between `left` and `right` that the floats beside
+    /// the band from `top` to `top + height` leave free.
+    pub fn available(&self, top: f32, height: f32, left: f32, right: f32) -> (f32, f32) {
+        let mut edges = (left, right);
+        for float in self.floats.iter().filter(|float| float.overlaps(top, height)) {
+            match float.side {
+                Float::Left => edges.0 = edges.0.max(float.rect.x + float.rect.width),
+                _ => edges.1 = edges.1.min(float.rect.x),
+            }
+        }
+        edges
+    }
+
+    /// Place a float with a margin box of `width` by `height`, as high as possible but not above
+    /// `top`, and as far to its side as possible between `left` and `right`.
+    ///
+    /// Returns the position of the float's margin box.
+    pub fn place(&mut self, side: Float, width: f32, height: f32, top: f32, left: f32, right: f32) -> (f32, f32) {
+        // A float can't be higher than an earlier float.
+        let mut y = self.floats.iter().map(|float| float.rect.y).fold(top, f32::max);
+
+        // Move down past floats until the float fits beside them, or there are none left.
+        loop {
+            let (start, end) = self.available(y, height, left, right);
+            let beside = self.floats.iter().filter(|float| float.overlaps(y, height));
+            match beside.map(PlacedFloat::bottom).reduce(f32::min) {
+                Some(bottom) if end - start < width => y = bottom,
+                _ => {
+                    let x = match side {
+                        Float::Left => start,
+                        _ => end - width,
+                    };
+                    let rect = Rect { x, y, width, height };
+                    self.floats.push(PlacedFloat { side, rect });
+                    return (x, y);
+                }
+            }
+        }
+    }
+
+    /// The bottom of the lowest float on the sides that `clear` clears, if there are any.
+    pub fn clearance(&self, clear: Clear) -> Option<f32> {
+        self.floats
+            .iter()
+            .filter(|float| match clear {
+                Clear::Left => float.side == Float::Left,
+                Clear::Right => float.side == Float::Right,
+                Clear::Both => true,
+                Clear::None => false,
+            })
+            .map(PlacedFloat::bottom)
+            .reduce(f32::max)
+    }
+}
+
+impl LayoutBox {
+    /// Lay out a float, and place it as high as possible at or below `position`.
+    pub(crate) fn layout_float(&mut self, containing_block: &Dimensions, lengths: LengthContext, position: &mut FlowPosition) -> RenderBox {
+        let style = Arc::clone(self.get_style_node());
+        self.calculate_float_width(containing_block, lengths);
+        self.calculate_block_position(containing_block, lengths);
+
+        // Where the float goes depends on its height, so it is laid out first and then moved.
+        let top = position.y + position.margin.size();
+        let (render_box, _) = self.layout_block_contents(lengths, &mut FlowPosition::at(top), None);
+        let mut render_box = RenderBox::Block(render_box);
+
+        let margin_box = self.dimensions.margin_box();
+        let top = top.max(position.floats.clearance(style.clear()).unwrap_or(top));
+        let content = containing_block.content;
+        let (x, y) = position.floats.place(
+            style.float(),
+            margin_box.width,
+            margin_box.height,
+            top,
+            content.x,
+            content.x + content.width,
+        );
+
+        // A relatively positioned float is also moved from where it was placed.
+        let (offset_x, offset_y) = self.relative_offset(containing_block, lengths);
+        let (dx, dy) = (x - margin_box.x + offset_x, y - margin_box.y + offset_y);
+        self.dimensions.content.translate(dx, dy);
+        render_box.translate(dx, dy);
+        render_box
+    }
+
+    /// Calculate the width of a float. An `auto` width shrinks to fit the contents, and `auto`
+    /// margins are zero.
+    ///
+    /// http://www.w3.org/TR/CSS2/visudet.html#float-width
+    ///
+    /// Sets the horizontal margin/padding/border dimensions, and the `width`.
+    fn calculate_float_width(&mut self, containing_block: &Dimensions, lengths: LengthContext) {
+        let style = Arc::clone(self.get_style_node());
+        let zero = Length(0.0, Px);
+        let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
+
+        let d = &mut self.dimensions;
+        d.margin.left = lookup("margin-left");
+        d.margin.right = lookup("margin-right");
+        d.border.left = lookup("border-left-width");
+        d.border.right = lookup("border-right-width");
+        d.padding.left = lookup("padding-left");
+        d.padding.right = lookup("padding-right");
+        let edges = d.margin_box().width - d.content.width;
+
+        let (width, min_width, max_width) = self.width_constraints(lengths);
+        let width = match width {
+            Length(width, Px) => width,
+            _ => {
+                let available = (containing_block.content.width - edges).max(0.0);
+                self.max_content_width(lengths).min(available)
+            }
+        };
+        self.dimensions.content.width = clamp_size(width, min_width, max_width);
+    }
+}
@@ -0,0 +1,849 @@
+//! Grid layout: items placed in the areas of a grid of rows and columns, which are sized by the
+//! track lists, by the items in them and by the space the grid has.
+//!
+//! https://www.w3.org/TR/css-grid-1/#layout-algorithm
+//!
+//! Lines can't be named, so items are placed by line numbers and spans. Implicit tracks are only
+//! added after the explicit grid, `auto-fit` repeats tracks like `auto-fill` without collapsing
+//! the empty ones, and items aren't aligned on baselines.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use robinson_css::Separator;
+use robinson_css::Unit::{Fr, Px};
+use robinson_css::Value::{self, Function, Keyword, Length, List, Number};
+use robinson_style::{Display, StyleNode};
+
+use crate::flex::{alignment, distribute, Alignment};
+use crate::intrinsic::IntrinsicSize;
+use crate::{clamp_size, keyword, BoxType, Dimensions, FlowPosition, LayoutBox, LengthContext, Rect, RenderBox};
+
+/// How a track is sized: `minmax(min, max)`, where a single size is both.
+#[derive(Clone, Copy, Debug)]
+struct TrackSize {
+    min: Sizing,
+    max: Sizing,
+}
+
+const AUTO_TRACK: TrackSize = TrackSize { min: Sizing::Auto, max: Sizing::Auto };
+
+/// The most tracks a grid has along an axis, to which line numbers, spans and repetitions are
+/// clamped.
+///
+/// https://www.w3.org/TR/css-grid-1/#overlarge-grids
+const MAX_TRACKS: usize = 1000;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Sizing {
+    /// A length in px.
+    Fixed(f32),
+    MinContent,
+    MaxContent,
+    Auto,
+    /// A share of the space the other tracks leave, which is only ever a maximum.
+    Fr(f32),
+}
+
+impl Sizing {
+    /// Whether the size depends on the items in the track.
+    fn is_intrinsic(self) -> bool {
+        matches!(self, Sizing::MinContent | Sizing::MaxContent | Sizing::Auto)
+    }
+}
+
+/// A track while it is being sized.
+#[derive(Clone, Copy, Debug)]
+struct Track {
+    size: TrackSize,
+    base: f32,
+    /// The size the track grows to when there is space for it, which is infinite until the items
+    /// in the track set it.
+    limit: f32,
+}
+
+impl Track {
+    fn flex_factor(&self) -> Option<f32> {
+        match self.size.max {
+            Sizing::Fr(factor) => Some(factor),
+            _ => None,
+        }
+    }
+}
+
+/// A line that an item starts or ends at, from `grid-row-start` and the like.
+#[derive(Clone, Copy, Debug)]
+enum GridLine {
+    Auto,
+    /// A line number, counted back from the end of the explicit grid if it is negative.
+    Line(i32),
+    Span(usize),
+}
+
+/// Where an item goes along an axis: between two lines, or across a number of tracks wherever
+/// it fits.
+#[derive(Clone, Debug)]
+enum Placement {
+    Definite(Range<usize>),
+    Auto(usize),
+}
+
+/// The tracks of a grid, explicit and implicit, and its items placed among them.
+struct Grid {
+    columns: Vec<TrackSize>,
+    rows: Vec<TrackSize>,
+    column_gap: f32,
+    row_gap: f32,
+    /// In the order they are laid out in.
+    items: Vec<GridItem>,
+}
+
+struct GridItem {
+    /// Index of the item's box among the container's children.
+    index: usize,
+    rows: Range<usize>,
+    columns: Range<usize>,
+}
+
+impl LayoutBox {
+    pub(crate) fn is_grid_container(&self) -> bool {
+        matches!(&self.box_type, BoxType::BlockNode(node) if matches!(node.display(), Display::Grid | Display::InlineGrid))
+    }
+
+    /// Lay out the children of a grid container whose width is known, starting at the top of
+    /// the container's content box at `flow`, and move `flow` to the bottom of the content.
+    ///
+    /// Returns the items in the order they are laid out in.
+    pub(crate) fn layout_grid_children(&mut self, lengths: LengthContext, flow: &mut FlowPosition) -> Vec<RenderBox> {
+        let style = Arc::clone(self.get_style_node());
+        let content = Rect { y: flow.y, ..self.dimensions.content };
+        let grid = self.grid(lengths, Some(content.width));
+
+        // Absolutely positioned children aren't grid items, and are placed where the content box
+        // starts.
+        for child in self.children.iter_mut().filter(|child| child.position().is_absolute()) {
+            child.static_position = (content.x, content.y);
+        }
+
+        // `normal` spreads the space the tracks leave between the `auto` ones.
+        let justify_content = alignment(&keyword(&style, "justify-content", "normal"), false, Alignment::Stretch);
+        let align_content = alignment(&keyword(&style, "align-content", "normal"), false, Alignment::Stretch);
+
+        // Size the columns to fit the widths of the items.
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let child = &self.children[item.index];
+                let min = child.outer_intrinsic_width(IntrinsicSize::MinContent, lengths);
+                let max = child.outer_intrinsic_width(IntrinsicSize::MaxContent, lengths);
+                (item.columns.clone(), min, max)
+            })
+            .collect();
+        let stretch_columns = justify_content == Alignment::Stretch;
+        let columns = size_tracks(&grid.columns, &contributions, Some(content.width), grid.column_gap, stretch_columns);
+        let column_positions = track_positions(&columns, content.width, grid.column_gap, justify_content);
+
+        // Lay out each item in its columns to find its height, and size the rows to fit them.
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let width = area_size(&columns, &column_positions, &item.columns);
+                let item_lengths = LengthContext { containing_width: width, containing_height: None, ..lengths };
+                let child = &mut self.children[item.index];
+                child.layout_grid_item(item_lengths, &style, width, None);
+                let height = child.dimensions.margin_box().height;
+                (item.rows.clone(), height, height)
+            })
+            .collect();
+        let stretch_rows = align_content == Alignment::Stretch;
+        let rows = size_tracks(&grid.rows, &contributions, lengths.containing_height, grid.row_gap, stretch_rows);
+        let height = lengths
+            .containing_height
+            .unwrap_or_else(|| rows.iter().sum::<f32>() + grid.row_gap * rows.len().saturating_sub(1) as f32);
+        let row_positions = track_positions(&rows, height, grid.row_gap, align_content);
+
+        // Lay out the items in their areas.
+        let mut children = Vec::new();
+        for item in &grid.items {
+            let area = Rect {
+                x: column_positions.get(item.columns.start).copied().unwrap_or(0.0),
+                y: row_positions.get(item.rows.start).copied().unwrap_or(0.0),
+                width: area_size(&columns, &column_positions, &item.columns),
+                height: area_size(&rows, &row_positions, &item.rows),
+            };
+            let item_lengths = LengthContext { containing_width: area.width, containing_height: Some(area.height), ..lengths };
+            let child = &mut self.children[item.index];
+            let (mut render_box, (x, y)) = child.layout_grid_item(item_lengths, &style, area.width, Some(area.height));
+
+            // A relatively positioned item is also moved from where it was placed.
+            let containing_block = Dimensions { content: area, ..Default::default() };
+            let (offset_x, offset_y) = child.relative_offset(&containing_block, item_lengths);
+            let (dx, dy) = (content.x + area.x + x + offset_x, content.y + area.y + y + offset_y);
+            child.dimensions.content.translate(dx, dy);
+            render_box.translate(dx, dy);
+            children.push(render_box);
+        }
+
+        flow.move_to(content.y + height);
+        children
+    }
+
+    /// The min-content or max-content width of a grid container's content box: that of its
+    /// columns when they are sized for it.
+    pub(crate) fn grid_intrinsic_width(&self, size: IntrinsicSize, lengths: LengthContext) -> f32 {
+        let grid = self.grid(lengths, None);
+        let contributions: Vec<_> = grid
+            .items
+            .iter()
+            .map(|item| {
+                let child = &self.children[item.index];
+                let min = child.outer_intrinsic_width(IntrinsicSize::MinContent, lengths);
+                let max = child.outer_intrinsic_width(IntrinsicSize::MaxContent, lengths);
+                (item.columns.clone(), min, max)
+            })
+            .collect();
+
+        // The columns are as small as they can be for the min-content width, which is the same as
+        // sizing them for no space at all.
+        let available = match size {
+            IntrinsicSize::MinContent => Some(0.0),
+            IntrinsicSize::MaxContent => None,
+        };
+        let columns = size_tracks(&grid.columns, &contributions, available, grid.column_gap, false);
+        columns.iter().sum::<f32>() + grid.column_gap * columns.len().saturating_sub(1) as f32
+    }
+
+    /// The tracks of a grid container of the given content width (if it is known), with its items
+    /// placed among them.
+    ///
+    /// https://www.w3.org/TR/css-grid-1/#placement
+    fn grid(&self, lengths: LengthContext, width: Option<f32>) -> Grid {
+        let style = self.get_style_node();
+        let none = Keyword("none".to_string());
+
+        // Gaps that are percentages of a height that depends on the items are zero.
+        let column_gap = lengths.resolve(&style.lookup("column-gap", &Length(0.0, Px))).to_px();
+        let row_gap = lengths.resolve_height(&style.lookup("row-gap", &Length(0.0, Px))).to_px();
+        let columns = template_tracks(&style.lookup("grid-template-columns", &none), width, column_gap, |value| {
+            lengths.resolve(value)
+        });
+        let rows = template_tracks(&style.lookup("grid-template-rows", &none), lengths.containing_height, row_gap, |value| {
+            lengths.resolve_height(value)
+        });
+
+        let auto_flow = style.lookup("grid-auto-flow", &Keyword("row".to_string()));
+        let auto_flow = components(&auto_flow);
+        let column_flow = auto_flow.contains(&Keyword("column".to_string()));
+        let dense = auto_flow.contains(&Keyword("dense".to_string()));
+
+        // Items are placed in order-modified document order. Sorting is stable, so items with the
+        // same `order` stay in tree order.
+        let mut children: Vec<(usize, &LayoutBox)> = self
+            .children
+            .iter()
+            .enumerate()
+            .filter(|(_, child)| !child.position().is_absolute())
+            .collect();
+        children.sort_by_key(|(_, child)| match &child.box_type {
+            BoxType::BlockNode(style) => match style.get_value("order") {
+                Some(Number(order)) => order as i32,
+                _ => 0,
+            },
+            _ => 0,
+        });
+
+        // Auto-placement fills the grid along its rows, or its columns with `grid-auto-flow:
+        // column`, so the positions are handled as along that axis and across it.
+        let placements = children
+            .iter()
+            .map(|(_, child)| {
+                let (row, column) = match &child.box_type {
+                    BoxType::BlockNode(style) => (
+                        placement(grid_line(style, "grid-row-start"), grid_line(style, "grid-row-end"), rows.len()),
+                        placement(grid_line(style, "grid-column-start"), grid_line(style, "grid-column-end"), columns.len()),
+                    ),
+                    // Runs of text take the first free cell.
+                    _ => (Placement::Auto(1), Placement::Auto(1)),
+                };
+                if column_flow { (column, row) } else { (row, column) }
+            })
+            .collect();
+        let areas = auto_place(placements, if column_flow { rows.len() } else { columns.len() }, dense);
+        let items: Vec<GridItem> = children
+            .iter()
+            .zip(areas)
+            .map(|(&(index, _), (along, across))| {
+                let (rows, columns) = if column_flow { (across, along) } else { (along, across) };
+                GridItem { index, rows, columns }
+            })
+            .collect();
+
+        // Tracks past the explicit grid are sized by `grid-auto-rows` and `grid-auto-columns`,
+        // whose sizes repeat.
+        let implicit = |explicit: Vec<TrackSize>, auto: Vec<TrackSize>, count: usize| {
+            let implicit = auto.iter().cycle().take(count.saturating_sub(explicit.len())).copied();
+            explicit.iter().copied().chain(implicit).collect::<Vec<_>>()
+        };
+        let auto = Keyword("auto".to_string());
+        let auto_columns = auto_tracks(&style.lookup("grid-auto-columns", &auto), |value| lengths.resolve(value));
+        let auto_rows = auto_tracks(&style.lookup("grid-auto-rows", &auto), |value| lengths.resolve_height(value));
+        let row_count = items.iter().map(|item| item.rows.end).fold(rows.len(), usize::max);
+        let column_count = items.iter().map(|item| item.columns.end).fold(columns.len(), usize::max);
+        Grid {
+            columns: implicit(columns, auto_columns, column_count),
+            rows: implicit(rows, auto_rows, row_count),
+            column_gap,
+            row_gap,
+            items,
+        }
+    }
+
+    /// Lay out a grid item in a grid area of the given size, with the top left corner of its
+    /// margin box at the origin. Until the rows are sized, the height of the area is `None`.
+    ///
+    /// Also returns the offset of the item in the area, where `justify-self` and `align-self`
+    /// or its `auto` margins put it.
+    fn layout_grid_item(
+        &mut self,
+        lengths: LengthContext,
+        container: &StyleNode,
+        area_width: f32,
+        area_height: Option<f32>,
+    ) -> (RenderBox, (f32, f32)) {
+        // Runs of text are wrapped in anonymous items, which have no margins, borders or padding,
+        // and fill their area.
+        let BoxType::BlockNode(style) = &self.box_type else {
+            self.dimensions = Dimensions::default();
+            return (self.layout_item(lengths, area_width, None), (0.0, 0.0));
+        };
+        let style = Arc::clone(style);
+        let zero = Length(0.0, Px);
+        let auto = Keyword("auto".to_string());
+
+        let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
+        let d = &mut self.dimensions;
+        d.margin.left = lookup("margin-left");
+        d.margin.right = lookup("margin-right");
+        d.border.left = lookup("border-left-width");
+        d.border.right = lookup("border-right-width");
+        d.padding.left = lookup("padding-left");
+        d.padding.right = lookup("padding-right");
+        self.calculate_block_position(&Dimensions::default(), lengths);
+        let margin_box = self.dimensions.margin_box();
+        let horizontal_edges = margin_box.width - self.dimensions.content.width;
+        let vertical_edges = margin_box.height - self.dimensions.content.height;
+
+        // Items without a size are stretched to fill their area, unless they are aligned or have
+        // `auto` margins.
+        let auto_margins = |start: &str, end: &str| (style.lookup(start, &zero) == auto, style.lookup(end, &zero) == auto);
+        let horizontal_margins = auto_margins("margin-left", "margin-right");
+        let vertical_margins = auto_margins("margin-top", "margin-bottom");
+        let justify = self_alignment(&style, container, "justify");
+        let align = self_alignment(&style, container, "align");
+
+        let (width, min_width, max_width) = self.width_constraints(lengths);
+        let available = (area_width - horizontal_edges).max(0.0);
+        let width = match width {
+            Length(width, Px) => width,
+            _ if justify == Alignment::Stretch && horizontal_margins == (false, false) => available,
+            _ => self.max_content_width(lengths).min(available),
+        };
+        let width = clamp_size(width, min_width, max_width);
+
+        let (height, min_height, max_height) = self.height_constraints(lengths);
+        let height = match area_height {
+            Some(area_height) if height.is_none() && align == Alignment::Stretch && vertical_margins == (false, false) => {
+                Some(clamp_size((area_height - vertical_edges).max(0.0), min_height, max_height))
+            }
+            _ => None,
+        };
+        let render_box = self.layout_item(lengths, width, height);
+
+        // `auto` margins take the space the item leaves in its area, before it is aligned.
+        let offset = |free: f32, auto_margins: (bool, bool), align: Alignment| match auto_margins {
+            (true, true) if free > 0.0 => free / 2.0,
+            (true, false) if free > 0.0 => free,
+            (false, true) => 0.0,
+            _ => match align {
+                Alignment::End => free,
+                Alignment::Center => free / 2.0,
+                _ => 0.0,
+            },
+        };
+        let margin_box = self.dimensions.margin_box();
+        let x = offset(area_width - margin_box.width, horizontal_margins, justify);
+        let y = area_height.map_or(0.0, |area_height| offset(area_height - margin_box.height, vertical_margins, align));
+        (render_box, (x, y))
+    }
+}
+
+/// The explicit tracks that a `grid-template-columns` or `grid-template-rows` value lists, with
+/// the tracks of an `auto-fill` or `auto-fit` repetition repeated as many times as fit in the
+/// `available` size. An invalid value lists none.
+///
+/// `resolve` resolves a length along the axis into px.
+///
+/// https://www.w3.org/TR/css-grid-1/#track-sizing
+fn template_tracks(value: &Value, available: Option<f32>, gap: f32, resolve: impl Fn(&Value) -> Value) -> Vec<TrackSize> {
+    let mut tracks = Vec::new();
+    let mut auto_repeat = None;
+    for component in components(value) {
+        match component {
+            Keyword(keyword) if keyword == "none" => {}
+            Function(name, arguments) if name == "repeat" => {
+                let repeated: Option<Vec<_>> = match arguments.as_slice() {
+                    [_, repeated] => components(repeated).iter().map(|value| track_size(value, &resolve)).collect(),
+                    _ => None,
+                };
+                let Some(repeated) = repeated else { return Vec::new() };
+                match &arguments[0] {
+                    Number(count) if count.fract() == 0.0 && *count >= 1.0 => {
+                        let count = (*count as usize).min(MAX_TRACKS);
+                        tracks.extend(repeated.iter().cycle().take(repeated.len() * count));
+                    }
+                    Keyword(keyword) if keyword == "auto-fill" || keyword == "auto-fit" => {
+                        auto_repeat = Some((tracks.len(), repeated));
+                    }
+                    _ => return Vec::new(),
+                }
+            }
+            component => match track_size(component, &resolve) {
+                Some(track) => tracks.push(track),
+                None => return Vec::new(),
+            },
+        }
+    }
+
+    // The tracks are repeated as many times as fit, counting each with its fixed maximum, or its
+    // fixed minimum if it doesn't have one. They are repeated once if the size isn't known.
+    if let Some((index, repeated)) = auto_repeat {
+        let fixed_size = |track: &TrackSize| match (track.min, track.max) {
+            (_, Sizing::Fixed(size)) | (Sizing::Fixed(size), _) => size,
+            _ => 0.0,
+        };
+        let size_with_gaps = |tracks: &[TrackSize]| tracks.iter().map(fixed_size).sum::<f32>() + gap * tracks.len() as f32;
+        let repetition = size_with_gaps(&repeated);
+        let count = match available {
+            Some(available) if repetition > 0.0 => {
+                ((available + gap - size_with_gaps(&tracks)) / repetition).floor().clamp(1.0, MAX_TRACKS as f32) as usize
+            }
+            _ => 1,
+        };
+        let repeated: Vec<_> = repeated.iter().cycle().take(repeated.len() * count).copied().collect();
+        tracks.splice(index..index, repeated);
+    }
+    tracks.truncate(MAX_TRACKS);
+    tracks
+}
+
+/// The sizes of the implicit tracks in a `grid-auto-rows` or `grid-auto-columns` value, which
+/// are `auto` if it is invalid.
+fn auto_tracks(value: &Value, resolve: impl Fn(&Value) -> Value) -> Vec<TrackSize> {
+    let tracks: Option<Vec<_>> = components(value).iter().map(|value| track_size(value, &resolve)).collect();
+    tracks.filter(|tracks| !tracks.is_empty()).unwrap_or(vec![AUTO_TRACK])
+}
+
+/// The size of a track: `minmax(min, max)` or a single size, where a flexible size stands for
+/// `minmax(auto, <flex>)`. Returns `None` if the value isn't a track size.
+fn track_size(value: &Value, resolve: &impl Fn(&Value) -> Value) -> Option<TrackSize> {
+    match value {
+        Function(name, arguments) if name == "minmax" => match arguments.as_slice() {
+            [min, max] => match sizing(min, resolve)? {
+                Sizing::Fr(_) => None,
+                min => Some(TrackSize { min, max: sizing(max, resolve)? }),
+            },
+            _ => None,
+        },
+        value => match sizing(value, resolve)? {
+            Sizing::Fr(factor) => Some(TrackSize { min: Sizing::Auto, max: Sizing::Fr(factor) }),
+            size => Some(TrackSize { min: size, max: size }),
+        },
+    }
+}
+
+fn sizing(value: &Value, resolve: &impl Fn(&Value) -> Value) -> Option<Sizing> {
+    match value {
+        Length(factor, Fr) if *factor >= 0.0 => Some(Sizing::Fr(*factor)),
+        Keyword(keyword) => match keyword.as_str() {
+            "auto" => Some(Sizing::Auto),
+            "min-content" => Some(Sizing::MinContent),
+            "max-content" => Some(Sizing::MaxContent),
+            _ => None,
+        },
+        Length(..) | Number(_) | Value::Calc(_) => match resolve(value) {
+            Length(size, Px) => Some(Sizing::Fixed(size.max(0.0))),
+            // A percentage of a size that isn't known yet behaves as `auto`.
+            Keyword(_) => Some(Sizing::Auto),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The line an item starts or ends at, as `grid-row-start` and the like set it.
+fn grid_line(style: &StyleNode, name: &str) -> GridLine {
+    match style.get_value(name) {
+        Some(Number(line)) if line.fract() == 0.0 && line != 0.0 => {
+            let limit = MAX_TRACKS as f32 + 1.0;
+            GridLine::Line(line.clamp(-limit, limit) as i32)
+        }
+        Some(List(values, Separator::Space)) => match values.as_slice() {
+            [Keyword(span), Number(count)] | [Number(count), Keyword(span)] if span == "span" && *count >= 1.0 => {
+                GridLine::Span((*count as usize).min(MAX_TRACKS))
+            }
+            _ => GridLine::Auto,
+        },
+        _ => GridLine::Auto,
+    }
+}
+
+/// Where an item goes along an axis with `explicit` tracks, given the lines it starts and ends
+/// at. Lines are numbered from zero.
+///
+/// https://www.w3.org/TR/css-grid-1/#line-placement
+fn placement(start: GridLine, end: GridLine, explicit: usize) -> Placement {
+    // Negative numbers count back from the last line of the explicit grid. Lines before the
+    // first line are taken as the first line.
+    let line = |number: i32| match number > 0 {
+        true => number as usize - 1,
+        false => (explicit as i32 + 1 + number).max(0) as usize,
+    };
+    let placement = match (start, end) {
+        (GridLine::Line(start), GridLine::Line(end)) => {
+            let (start, end) = (line(start), line(end));
+            match start.cmp(&end) {
+                Ordering::Less => Placement::Definite(start..end),
+                Ordering::Equal => Placement::Definite(start..start + 1),
+                Ordering::Greater => Placement::Definite(end..start),
+            }
+        }
+        (GridLine::Line(start), GridLine::Span(span)) => Placement::Definite(line(start)..line(start) + span),
+        (GridLine::Line(start), GridLine::Auto) => Placement::Definite(line(start)..line(start) + 1),
+        (GridLine::Span(span), GridLine::Line(end)) => {
+            let end = line(end).max(1);
+            Placement::Definite(end.saturating_sub(span)..end)
+        }
+        (GridLine::Auto, GridLine::Line(end)) => {
+            let end = line(end).max(1);
+            Placement::Definite(end - 1..end)
+        }
+        (GridLine::Span(span), _) | (GridLine::Auto, GridLine::Span(span)) => Placement::Auto(span),
+        (GridLine::Auto, GridLine::Auto) => Placement::Auto(1),
+    };
+
+    // Items are cut off at the last line of the largest grid.
+    match placement {
+        Placement::Definite(range) => {
+            let start = range.start.min(MAX_TRACKS - 1);
+            Placement::Definite(start..range.end.clamp(start + 1, MAX_TRACKS))
+        }
+        placement => placement,
+    }
+}
+
+/// Place the items into the grid, given where each goes along the axis that auto-placement
+/// moves along and across it, and the number of explicit tracks across it.
+///
+/// Items with a definite position go there, and the others go in the first free area for them
+/// after the one placed before, or from the start of the grid if the packing is `dense`.
+///
+/// https://www.w3.org/TR/css-grid-1/#auto-placement-algo
+fn auto_place(placements: Vec<(Placement, Placement)>, explicit: usize, dense: bool) -> Vec<(Range<usize>, Range<usize>)> {
+    // The grid has as many tracks across as the explicit grid, the items placed across it and
+    // the widest span need.
+    let across_count = placements
+        .iter()
+        .map(|(_, across)| match across {
+            Placement::Definite(across) => across.end,
+            Placement::Auto(span) => *span,
+        })
+        .fold(explicit, usize::max);
+
+    let mut areas: Vec<Option<(Range<usize>, Range<usize>)>> = vec![None; placements.len()];
+    let overlaps = |areas: &[Option<(Range<usize>, Range<usize>)>], along: &Range<usize>, across: &Range<usize>| {
+        areas.iter().flatten().any(|(other_along, other_across)| {
+            other_along.start < along.end
+                && along.start < other_along.end
+                && other_across.start < across.end
+                && across.start < other_across.end
+        })
+    };
+
+    // Items with definite positions go there first.
+    for (area, placements) in areas.iter_mut().zip(&placements) {
+        if let (Placement::Definite(along), Placement::Definite(across)) = placements {
+            *area = Some((along.clone(), across.clone()));
+        }
+    }
+
+    // Then the items locked to a track along the grid go in the first free area in it.
+    let mut cursors: HashMap<usize, usize> = HashMap::new();
+    for (index, placements) in placements.iter().enumerate() {
+        if let (Placement::Definite(along), Placement::Auto(span)) = placements {
+            let cursor = cursors.entry(along.start).or_default();
+            let mut start = if dense { 0 } else { *cursor };
+            while overlaps(&areas, along, &(start..start + span)) {
+                start += 1;
+            }
+            areas[index] = Some((along.clone(), start..start + span));
+            *cursor = start + span;
+        }
+    }
+
+    // Then the others, with a cursor that moves along the grid.
+    let (mut along, mut across) = (0, 0);
+    for (index, placements) in placements.iter().enumerate() {
+        let area = match placements {
+            (Placement::Auto(along_span), Placement::Definite(range)) => {
+                if dense {
+                    along = 0;
+                } else if range.start < across {
+                    along += 1;
+                }
+                across = range.start;
+                while overlaps(&areas, &(along..along + along_span), range) {
+                    along += 1;
+                }
+                (along..along + along_span, range.clone())
+            }
+            (Placement::Auto(along_span), Placement::Auto(across_span)) => {
+                if dense {
+                    (along, across) = (0, 0);
+                }
+                loop {
+                    if across + across_span > across_count {
+                        (along, across) = (along + 1, 0);
+                    } else if overlaps(&areas, &(along..along + along_span), &(across..across + across_span)) {
+                        across += 1;
+                    } else {
+                        break;
+                    }
+                }
+                (along..along + along_span, across..across + across_span)
+            }
+            _ => continue,
+        };
+        areas[index] = Some(area);
+    }
+
+    areas.into_iter().flatten().collect()
+}
+
+/// Size the tracks along an axis to fit the items in them, given as the tracks each item spans
+/// with its min-content and max-content contributions, and the `available` space, which is
+/// `None` when the tracks are sized for the max-content size.
+///
+/// With `stretch`, `auto` tracks grow to fill the space that is left.
+///
+/// https://www.w3.org/TR/css-grid-1/#algo-track-sizing
+fn size_tracks(
+    sizes: &[TrackSize],
+    contributions: &[(Range<usize>, f32, f32)],
+    available: Option<f32>,
+    gap: f32,
+    stretch: bool,
+) -> Vec<f32> {
+    let mut tracks: Vec<Track> = sizes
+        .iter()
+        .map(|&size| {
+            let base = match size.min {
+                Sizing::Fixed(size) => size,
+                _ => 0.0,
+            };
+            let limit = match size.max {
+                Sizing::Fixed(size) => size.max(base),
+                Sizing::Fr(_) => base,
+                _ => f32::INFINITY,
+            };
+            Track { size, base, limit }
+        })
+        .collect();
+    let gaps = |count: usize| gap * count.saturating_sub(1) as f32;
+
+    // Grow the tracks sized by their contents to fit the items, starting with the items that
+    // only span one track.
+    let mut by_span: Vec<_> = contributions.iter().filter(|(range, ..)| !range.is_empty()).collect();
+    by_span.sort_by_key(|(range, ..)| range.len());
+    for (range, min, max) in by_span {
+        let spanned = &mut tracks[range.clone()];
+        let flexible = spanned.iter().any(|track| track.flex_factor().is_some());
+
+        if let [track] = spanned {
+            track.base = match track.size.min {
+                Sizing::MinContent | Sizing::Auto => track.base.max(*min),
+                Sizing::MaxContent => track.base.max(*max),
+                _ => track.base,
+            };
+            let grow_limit = |limit: f32, size: f32| if limit.is_infinite() { size } else { limit.max(size) };
+            track.limit = match track.size.max {
+                Sizing::MinContent => grow_limit(track.limit, *min),
+                Sizing::MaxContent | Sizing::Auto => grow_limit(track.limit, *max),
+                _ => track.limit,
+            };
+            continue;
+        }
+
+        // An item spanning several tracks grows those sized by their contents equally, or only the
+        // flexible ones if it spans any.
+        let extra = min - spanned.iter().map(|track| track.base).sum::<f32>() - gaps(range.len());
+        let mut growing: Vec<_> = spanned
+            .iter_mut()
+            .filter(|track| track.size.min.is_intrinsic() && (!flexible || track.flex_factor().is_some()))
+            .collect();
+        if extra > 0.0 && !growing.is_empty() {
+            let share = extra / growing.len() as f32;
+            growing.iter_mut().for_each(|track| track.base += share);
+        }
+        if !flexible {
+            let limit = |track: &Track| if track.limit.is_infinite() { track.base } else { track.limit };
+            let extra = max - spanned.iter().map(limit).sum::<f32>() - gaps(range.len());
+            let mut growing: Vec<_> = spanned.iter_mut().filter(|track| track.size.max.is_intrinsic()).collect();
+            if extra > 0.0 && !growing.is_empty() {
+                let share = extra / growing.len() as f32;
+                growing.iter_mut().for_each(|track| track.limit = limit(track) + share);
+            }
+        }
+    }
+    for track in &mut tracks {
+        if track.limit.is_infinite() {
+            track.limit = track.base;
+        }
+        track.limit = track.limit.max(track.base);
+    }
+
+    // Grow the tracks towards their limits with the space that is left, sharing it equally
+    // between the tracks that haven't reached them.
+    let used = |tracks: &[Track]| tracks.iter().map(|track| track.base).sum::<f32>() + gaps(tracks.len());
+    match available {
+        Some(available) => {
+            let mut free = available - used(&tracks);
+            loop {
+                let growing = tracks.iter().filter(|track| track.base < track.limit).count();
+                if free <= 0.0 || growing == 0 {
+                    break;
+                }
+                let share = free / growing as f32;
+                for track in tracks.iter_mut().filter(|track| track.base < track.limit) {
+                    let grown = share.min(track.limit - track.base);
+                    track.base += grown;
+                    free -= grown;
+                }
+            }
+        }
+        None => tracks.iter_mut().for_each(|track| track.base = track.limit),
+    }
+
+    // Flexible tracks share what is left after that by their flex factors. Without an available
+    // size, `1fr` is as big as it needs to be for every flexible track and item to get its size.
+    if tracks.iter().any(|track| track.flex_factor().is_some()) {
+        let fr = match available {
+            Some(available) => fr_size(&tracks, available - gaps(tracks.len())),
+            None => {
+                let tracks_fr = tracks
+                    .iter()
+                    .filter_map(|track| Some(track.base / track.flex_factor()?.max(1.0)));
+                let items_fr = contributions
+                    .iter()
+                    .filter(|(range, ..)| tracks[range.clone()].iter().any(|track| track.flex_factor().is_some()))
+                    .map(|(range, _, max)| fr_size(&tracks[range.clone()], max - gaps(range.len())));
+                tracks_fr.chain(items_fr).fold(0.0, f32::max)
+            }
+        };
+        for track in &mut tracks {
+            if let Some(factor) = track.flex_factor() {
+                track.base = track.base.max(fr * factor);
+            }
+        }
+    }
+
+    // `auto` tracks are stretched to fill the rest.
+    if let Some(available) = available.filter(|_| stretch) {
+        let free = available - used(&tracks);
+        let stretched = tracks.iter().filter(|track| track.size.max == Sizing::Auto).count();
+        if free > 0.0 && stretched > 0 {
+            for track in tracks.iter_mut().filter(|track| track.size.max == Sizing::Auto) {
+                track.base += free / stretched as f32;
+            }
+        }
+    }
+
+    tracks.iter().map(|track| track.base).collect()
+}
+
+/// The size of `1fr` that makes the tracks fill `space`, leaving out the flexible tracks that
+/// are bigger than their share would be. Flex factors that add up to less than one count as one.
+///
+/// https://www.w3.org/TR/css-grid-1/#algo-find-fr-size
+fn fr_size(tracks: &[Track], space: f32) -> f32 {
+    let mut inflexible: Vec<bool> = tracks.iter().map(|track| track.flex_factor().is_none()).collect();
+    loop {
+        let leftover = space
+            - tracks.iter().zip(&inflexible).filter(|(_, &inflexible)| inflexible).map(|(track, _)| track.base).sum::<f32>();
+        let factors: f32 = tracks
+            .iter()
+            .zip(&inflexible)
+            .filter(|(_, &inflexible)| !inflexible)
+            .filter_map(|(track, _)| track.flex_factor())
+            .sum();
+        let fr = leftover / factors.max(1.0);
+
+        let mut changed = false;
+        for (track, inflexible) in tracks.iter().zip(&mut inflexible).filter(|(_, inflexible)| !**inflexible) {
+            if track.base > fr * track.flex_factor().unwrap_or(0.0) {
+                *inflexible = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            return fr.max(0.0);
+        }
+    }
+}
+
+/// Where each track starts, with the space the tracks leave spread by `align`.
+fn track_positions(sizes: &[f32], available: f32, gap: f32, align: Alignment) -> Vec<f32> {
+    let used = sizes.iter().sum::<f32>() + gap * sizes.len().saturating_sub(1) as f32;
+    let (mut position, spacing) = distribute(align, available - used, sizes.len());
+    sizes
+        .iter()
+        .map(|size| {
+            let start = position;
+            position += size + gap + spacing;
+            start
+        })
+        .collect()
+}
+
+/// The size of the area that spans the tracks in `range`, with the gaps between them.
+fn area_size(sizes: &[f32], positions: &[f32], range: &Range<usize>) -> f32 {
+    match (positions.get(range.start), positions.get(range.end - 1), sizes.get(range.end - 1)) {
+        (Some(start), Some(last), Some(size)) => last + size - start,
+        _ => 0.0,
+    }
+}
+
+/// The alignment of a grid item along an axis (`"justify"` or `"align"`), from its `*-self`
+/// property or the container's `*-items`. `normal` stretches the item.
+fn self_alignment(style: &StyleNode, container: &StyleNode, axis: &str) -> Alignment {
+    let value = match keyword(style, &format!("{axis}-self"), "auto") {
+        value if value == "auto" => keyword(container, &format!("{axis}-items"), "normal"),
+        value => value,
+    };
+    match value.as_str() {
+        // `legacy` only means something for blocks.
+        "legacy" => Alignment::Stretch,
+        value => alignment(value, false, Alignment::Stretch),
+    }
+}
+
+/// The components of a space-separated value.
+fn components(value: &Value) -> &[Value] {
+    match value {
+        List(values, Separator::Space) => values,
+        value => std::slice::from_ref(value),
+    }
+}
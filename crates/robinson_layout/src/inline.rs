@@ -0,0 +1,666 @@
+//! Inline formatting context.
+//!
+//! https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+//!
+//! The inline descendants of an anonymous block are flattened into a stream of words, spaces and
+//! box boundaries, broken greedily into line boxes that fit the containing block, and then
+//! aligned vertically on a shared baseline. White space is collapsed, kept and wrapped as
+//! `white-space` says.
+//!
+//! https://www.w3.org/TR/CSS2/text.html#white-space-prop
+
+use robinson_css::Unit::Px;
+use robinson_css::Value::{Keyword, Length, Number};
+use robinson_dom::Node;
+use robinson_font::{ShapedGlyph, ShapedRun};
+use robinson_style::StyleNode;
+
+use crate::float::Floats;
+use crate::intrinsic::IntrinsicSize;
+use crate::text::{ascent, descent, font, font_size};
+use crate::{
+    border_colors, BoxType, Dimensions, EdgeSizes, InlineBoxFragment, InlineFragment, LayoutBox,
+    LengthContext, LineBox, Rect, TextFragment,
+};
+
+/// Used value of `line-height: normal`, as a multiple of the font size.
+const NORMAL_LINE_HEIGHT: f32 = 1.2;
+
+/// What a preserved tab is drawn as. Tabs advance by a fixed 8 spaces (the initial `tab-size`)
+/// rather than to the next tab stop.
+const TAB: &str = "        ";
+
+/// A single unit of inline content, in document order.
+enum InlineItem<'a> {
+    /// The start of an inline element.
+    Open(&'a StyleNode),
+    /// The end of the most recently opened inline element.
+    Close(&'a StyleNode),
+    /// An unbreakable run of text.
+    Word(&'a StyleNode, &'a str),
+    /// A collapsed sequence of white space; a line break opportunity.
+    Space(&'a StyleNode),
+    /// A sequence of spaces kept as is, which isn't removed at the start or end of a line, but
+    /// is still followed by a line break opportunity if the text wraps.
+    PreservedSpace(&'a StyleNode, &'a str),
+    /// A forced line break (`<br>`).
+    LineBreak,
+    /// Where an absolutely positioned box would have been.
+    Anchor,
+}
+
+/// An item that has been assigned a horizontal position on a line.
+struct Placed {
+    item: usize,
+    x: f32,
+    width: f32,
+    /// The glyphs of a word or space, shaped when it was measured.
+    glyphs: Vec<ShapedGlyph>,
+}
+
+/// The result of laying out inline content.
+pub struct Lines {
+    pub lines: Vec<LineBox>,
+    /// The total height of the lines.
+    pub height: f32,
+    /// The static positions of the absolutely positioned boxes among the content, in tree order.
+    pub anchors: Vec<(f32, f32)>,
+}
+
+/// Lay out `boxes` into line boxes within `rect`, starting at its top edge.
+///
+/// `block_style` is the style of the block container, which supplies the strut and `text-align`.
+/// Percentage margins and padding of inline boxes refer to `lengths.containing_width`. Each line
+/// is shortened to fit beside the `floats` next to it.
+pub fn layout_lines(
+    boxes: &[LayoutBox],
+    block_style: &StyleNode,
+    rect: Rect,
+    lengths: LengthContext,
+    floats: &Floats,
+) -> Lines {
+    let mut items = Vec::new();
+    for layout_box in boxes {
+        collect_items(layout_box, block_style, &mut items);
+    }
+
+    // The space beside the floats is measured over the height of an empty line.
+    let (above, below) = line_metrics(block_style);
+
+    let mut lines = Vec::new();
+    let mut open = Vec::new();
+    let mut y = rect.y;
+    let mut breaker = LineBreaker::new(&items, lengths);
+    loop {
+        let (left, right) = floats.available(y, above + below, rect.x, rect.x + rect.width);
+        let line_rect = Rect { x: left, width: (right - left).max(0.0), ..rect };
+        let Some(placed) = breaker.next_line(line_rect.width) else { break };
+        let line = build_line_box(&items, &placed, &mut open, block_style, line_rect, y, lengths);
+        y += line.rect.height;
+        lines.push(line);
+    }
+
+    // Boxes after the last line go where the next line would start.
+    let anchors = breaker
+        .anchors
+        .iter()
+        .map(|&(line, x)| match lines.get(line) {
+            Some(line) => (line.rect.x + x, line.rect.y),
+            None => (rect.x, y),
+        })
+        .collect();
+
+    Lines { lines, height: y - rect.y, anchors }
+}
+
+/// The width of the widest line that `boxes` would make if lines were broken wherever they can
+/// be (for the min-content width) or only where they are forced to (for the max-content width).
+pub fn intrinsic_width(boxes: &[LayoutBox], block_style: &StyleNode, lengths: LengthContext, size: IntrinsicSize) -> f32 {
+    let mut items = Vec::new();
+    for layout_box in boxes {
+        collect_items(layout_box, block_style, &mut items);
+    }
+
+    let available_width = match size {
+        IntrinsicSize::MinContent => 0.0,
+        IntrinsicSize::MaxContent => f32::INFINITY,
+    };
+    let mut breaker = LineBreaker::new(&items, lengths);
+    let mut width: f32 = 0.0;
+    while let Some(placed) = breaker.next_line(available_width) {
+        width = width.max(placed.last().map_or(0.0, |last| last.x + last.width));
+    }
+    width
+}
+
+/// Flatten an inline box and its descendants into `items`, processing white space.
+fn collect_items<'a>(layout_box: &'a LayoutBox, parent_style: &'a StyleNode, items: &mut Vec<InlineItem<'a>>) {
+    let style = match &layout_box.box_type {
+        BoxType::InlineNode(node) => node,
+        BoxType::BlockNode(node) if node.position().is_absolute() => {
+            items.push(InlineItem::Anchor);
+            return;
+        }
+        // Block-level boxes were split out of the inline content when building the tree, so
+        // only floats are left here, which aren't supported inside inlines yet.
+        _ => return,
+    };
+
+    match &style.node {
+        Node::Text(text) => match WhiteSpace::of(parent_style) {
+            WhiteSpace::Normal | WhiteSpace::NoWrap => collect_collapsed(text, parent_style, items),
+            WhiteSpace::PreLine => {
+                for (index, line) in text.split('\n').enumerate() {
+                    if index > 0 {
+                        items.push(InlineItem::LineBreak);
+                    }
+                    collect_collapsed(line, parent_style, items);
+                }
+            }
+            WhiteSpace::Pre | WhiteSpace::PreWrap => collect_preserved(text, parent_style, items),
+        },
+        Node::Element(element) if element.name == "br" => items.push(InlineItem::LineBreak),
+        Node::Element(_) => {
+            items.push(InlineItem::Open(style));
+            for child in &layout_box.children {
+                collect_items(child, style, items);
+            }
+            items.push(InlineItem::Close(style));
+        }
+        Node::Comment(_) => {}
+    }
+}
+
+/// Split text into words, collapsing each sequence of white space into a single space.
+fn collect_collapsed<'a>(text: &'a str, style: &'a StyleNode, items: &mut Vec<InlineItem<'a>>) {
+    let mut words = text.split_whitespace().peekable();
+    if text.starts_with(char::is_whitespace) {
+        push_space(items, style);
+    }
+    while let Some(word) = words.next() {
+        items.push(InlineItem::Word(style, word));
+        if words.peek().is_some() || text.ends_with(char::is_whitespace) {
+            push_space(items, style);
+        }
+    }
+}
+
+/// Split text into words and the spaces between them, keeping every space and turning each
+/// newline into a forced line break.
+fn collect_preserved<'a>(text: &'a str, style: &'a StyleNode, items: &mut Vec<InlineItem<'a>>) {
+    let is_space = |c: char| c == ' ' || c == '\t';
+    for (index, line) in text.split('\n').enumerate() {
+        if index > 0 {
+            items.push(InlineItem::LineBreak);
+        }
+        let mut rest = line.strip_suffix('\r').unwrap_or(line);
+        while let Some(first) = rest.chars().next() {
+            let end = rest.find(|c| is_space(c) != is_space(first)).unwrap_or(rest.len());
+            let (segment, tail) = rest.split_at(end);
+            if !is_space(first) {
+                items.push(InlineItem::Word(style, segment));
+            } else {
+                for (index, spaces) in segment.split('\t').enumerate() {
+                    if index > 0 {
+                        items.push(InlineItem::PreservedSpace(style, TAB));
+                    }
+                    if !spaces.is_empty() {
+                        items.push(InlineItem::PreservedSpace(style, spaces));
+                    }
+                }
+            }
+            rest = tail;
+        }
+    }
+}
+
+/// The values of `white-space`.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WhiteSpace {
+    Normal,
+    NoWrap,
+    Pre,
+    PreWrap,
+    PreLine,
+}
+
+impl WhiteSpace {
+    fn of(style: &StyleNode) -> Self {
+        match style.get_value("white-space") {
+            Some(Keyword(value)) => match value.as_str() {
+                "nowrap" => WhiteSpace::NoWrap,
+                "pre" => WhiteSpace::Pre,
+                "pre-wrap" => WhiteSpace::PreWrap,
+                "pre-line" => WhiteSpace::PreLine,
+                _ => WhiteSpace::Normal,
+            },
+            _ => WhiteSpace::Normal,
+        }
+    }
+
+    /// Whether lines may be broken at the spaces in text with this value.
+    fn wraps(self) -> bool {
+        !matches!(self, WhiteSpace::NoWrap | WhiteSpace::Pre)
+    }
+}
+
+/// Push a space unless the previous content already ends in one.
+fn push_space<'a>(items: &mut Vec<InlineItem<'a>>, style: &'a StyleNode) {
+    let previous = items
+        .iter()
+        .rev()
+        .find(|item| !matches!(item, InlineItem::Open(_) | InlineItem::Close(_)));
+    if matches!(previous, Some(InlineItem::Word(..))) {
+        items.push(InlineItem::Space(style));
+    }
+}
+
+/// State of the greedy line breaking pass, which assigns items to lines one line at a time.
+struct LineBreaker<'a, 'b> {
+    items: &'b [InlineItem<'a>],
+    lengths: LengthContext,
+    /// Index of the next item to place.
+    next: usize,
+    line: Vec<Placed>,
+    x: f32,
+    /// Element starts are held back until the content that follows them is placed, so that they
+    /// move to the next line together with it.
+    pending_opens: Vec<usize>,
+    /// Number of lines broken off so far.
+    line_count: usize,
+    /// The line and position on it of each anchor. Anchors take no space, and aren't part of
+    /// the line, so that a line with only anchors isn't laid out.
+    anchors: Vec<(usize, f32)>,
+}
+
+impl<'a, 'b> LineBreaker<'a, 'b> {
+    fn new(items: &'b [InlineItem<'a>], lengths: LengthContext) -> Self {
+        LineBreaker {
+            items,
+            lengths,
+            next: 0,
+            line: Vec::new(),
+            x: 0.0,
+            pending_opens: Vec::new(),
+            line_count: 0,
+            anchors: Vec::new(),
+        }
+    }
+
+    /// Break off the next line, no wider than `available_width` unless a single word is, or
+    /// return `None` once every item is placed.
+    fn next_line(&mut self, available_width: f32) -> Option<Vec<Placed>> {
+        while let Some(item) = self.items.get(self.next) {
+            let index = self.next;
+            match item {
+                InlineItem::Open(_) => self.pending_opens.push(index),
+                InlineItem::Close(style) => {
+                    self.flush_opens();
+                    self.place(index, inline_edges(style, self.lengths).right_sum());
+                }
+                InlineItem::Space(style) => {
+                    // White space at the start of a line is removed.
+                    if self.has_content() {
+                        self.place_text(index, font(style).shape(" ", font_size(style)));
+                    }
+                }
+                InlineItem::PreservedSpace(style, spaces) => {
+                    self.flush_opens();
+                    self.place_text(index, font(style).shape(spaces, font_size(style)));
+                }
+                InlineItem::Word(style, text) => {
+                    let run = font(style).shape(text, font_size(style));
+                    let needed = self.pending_width() + run.width;
+                    // The word starts the next line instead.
+                    if self.has_content()
+                        && self.can_break_before(style)
+                        && self.x - self.trailing_space() + needed > available_width
+                    {
+                        return Some(self.finish_line());
+                    }
+                    self.flush_opens();
+                    self.place_text(index, run);
+                }
+                InlineItem::LineBreak => {
+                    self.flush_opens();
+                    self.next += 1;
+                    return Some(self.finish_line());
+                }
+                InlineItem::Anchor => self.anchors.push((self.line_count, self.x + self.pending_width())),
+            }
+            self.next += 1;
+        }
+
+        self.flush_opens();
+        match self.line.is_empty() {
+            true => None,
+            false => Some(self.finish_line()),
+        }
+    }
+
+    fn place(&mut self, item: usize, width: f32) {
+        self.place_text(item, ShapedRun { glyphs: Vec::new(), width });
+    }
+
+    fn place_text(&mut self, item: usize, run: ShapedRun) {
+        self.line.push(Placed { item, x: self.x, width: run.width, glyphs: run.glyphs });
+        self.x += run.width;
+    }
+
+    fn flush_opens(&mut self) {
+        for index in std::mem::take(&mut self.pending_opens) {
+            if let InlineItem::Open(style) = self.items[index] {
+                self.place(index, inline_edges(style, self.lengths).left_sum());
+            }
+        }
+    }
+
+    fn pending_width(&self) -> f32 {
+        self.pending_opens
+            .iter()
+            .map(|&index| match self.items[index] {
+                InlineItem::Open(style) => inline_edges(style, self.lengths).left_sum(),
+                _ => 0.0,
+            })
+            .sum()
+    }
+
+    /// Whether the current line holds any text yet.
+    fn has_content(&self) -> bool {
+        self.line
+            .iter()
+            .any(|placed| matches!(self.items[placed.item], InlineItem::Word(..) | InlineItem::PreservedSpace(..)))
+    }
+
+    /// Whether the line may be broken before a word with the given style. The space before the
+    /// word decides, if there is one.
+    fn can_break_before(&self, style: &StyleNode) -> bool {
+        match self.line.last().map(|placed| &self.items[placed.item]) {
+            Some(InlineItem::Space(space_style) | InlineItem::PreservedSpace(space_style, _)) => {
+                WhiteSpace::of(space_style).wraps()
+            }
+            _ => WhiteSpace::of(style).wraps(),
+        }
+    }
+
+    /// Width of the space at the end of the current line, which would be removed by a break, or
+    /// hang past its end if it is preserved.
+    fn trailing_space(&self) -> f32 {
+        match self.line.last() {
+            Some(placed) if matches!(self.items[placed.item], InlineItem::Space(_) | InlineItem::PreservedSpace(..)) => {
+                placed.width
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn finish_line(&mut self) -> Vec<Placed> {
+        // White space at the end of a line is removed.
+        while matches!(self.line.last(), Some(placed) if matches!(self.items[placed.item], InlineItem::Space(_))) {
+            self.line.pop();
+        }
+        self.x = 0.0;
+        self.line_count += 1;
+        std::mem::take(&mut self.line)
+    }
+}
+
+/// An inline box that is open while a line's fragments are generated.
+struct OpenBox<'a> {
+    style: &'a StyleNode,
+    /// Left edge of the fragment's border box, relative to the start of the line.
+    start: f32,
+    /// Whether the box starts on this line, so its left edges belong to this fragment.
+    first: bool,
+    /// Index of the fragment's slot in paint order.
+    slot: usize,
+}
+
+/// Position the placed items of a single line vertically and generate its fragments.
+///
+/// `open` holds the inline boxes that were left open at the end of the previous line, and is
+/// updated to those still open at the end of this one.
+fn build_line_box<'a>(
+    items: &[InlineItem<'a>],
+    placed: &[Placed],
+    open: &mut Vec<&'a StyleNode>,
+    block_style: &StyleNode,
+    rect: Rect,
+    y: f32,
+    lengths: LengthContext,
+) -> LineBox {
+    // Every line starts with a zero-width strut with the block's font and line height.
+    let (mut above, mut below) = line_metrics(block_style);
+    for placed in placed {
+        let style = match items[placed.item] {
+            InlineItem::Open(style)
+            | InlineItem::Close(style)
+            | InlineItem::Word(style, _)
+            | InlineItem::Space(style)
+            | InlineItem::PreservedSpace(style, _) => style,
+            InlineItem::LineBreak | InlineItem::Anchor => continue,
+        };
+        let (a, b) = line_metrics(style);
+        above = above.max(a);
+        below = below.max(b);
+    }
+    let baseline = y + above;
+
+    let line_width = placed.last().map_or(0.0, |last| last.x + last.width);
+    let offset = match block_style.get_value("text-align") {
+        Some(Keyword(align)) if align == "center" => (rect.width - line_width) / 2.0,
+        Some(Keyword(align)) if align == "right" => rect.width - line_width,
+        _ => 0.0,
+    };
+    let origin = rect.x + offset.max(0.0);
+
+    let mut slots: Vec<Option<InlineBoxFragment>> = Vec::new();
+    let mut stack: Vec<OpenBox> = Vec::new();
+    for style in open.drain(..) {
+        slots.push(None);
+        stack.push(OpenBox { style, start: 0.0, first: false, slot: slots.len() - 1 });
+    }
+
+    let mut texts = Vec::new();
+    let mut run: Option<TextRun> = None;
+    for placed in placed {
+        let (style, text) = match items[placed.item] {
+            InlineItem::Word(style, word) | InlineItem::PreservedSpace(style, word) => (style, word),
+            InlineItem::Space(style) => (style, " "),
+            _ => {
+                texts.extend(run.take().map(|run| text_fragment(run, origin, baseline)));
+                match items[placed.item] {
+                    InlineItem::Open(style) => {
+                        slots.push(None);
+                        stack.push(OpenBox {
+                            style,
+                            start: placed.x + inline_edges(style, lengths).margin.left,
+                            first: true,
+                            slot: slots.len() - 1,
+                        });
+                    }
+                    InlineItem::Close(_) => {
+                        if let Some(open_box) = stack.pop() {
+                            let end = placed.x + placed.width - inline_edges(open_box.style, lengths).margin.right;
+                            slots[open_box.slot] = Some(box_fragment(&open_box, end, true, origin, baseline, lengths));
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+        };
+
+        if !matches!(&run, Some(run) if std::ptr::eq(run.style, style)) {
+            texts.extend(run.take().map(|run| text_fragment(run, origin, baseline)));
+        }
+        let run = run.get_or_insert_with(|| TextRun {
+            style,
+            start: placed.x,
+            end: placed.x,
+            text: String::new(),
+            glyphs: Vec::new(),
+        });
+        run.text.push_str(text);
+        run.end = placed.x + placed.width;
+        let offset = placed.x - run.start;
+        run.glyphs.extend(placed.glyphs.iter().map(|glyph| ShapedGlyph { x: glyph.x + offset, ..*glyph }));
+    }
+    texts.extend(run.take().map(|run| text_fragment(run, origin, baseline)));
+
+    // Boxes that continue onto the next line are split here.
+    for open_box in &stack {
+        slots[open_box.slot] = Some(box_fragment(open_box, line_width, false, origin, baseline, lengths));
+    }
+    open.extend(stack.iter().map(|open_box| open_box.style));
+
+    // Inline box backgrounds are painted beneath the text of the line.
+    let mut fragments: Vec<InlineFragment> = slots.into_iter().flatten().map(InlineFragment::Box).collect();
+    fragments.extend(texts.into_iter().map(InlineFragment::Text));
+
+    LineBox {
+        rect: Rect { x: rect.x, y, width: rect.width, height: above + below },
+        baseline,
+        fragments,
+    }
+}
+
+/// Adjacent words and spaces on a line with the same style, which become one text fragment.
+struct TextRun<'a> {
+    style: &'a StyleNode,
+    /// Horizontal extent of the run, relative to the start of the line.
+    start: f32,
+    end: f32,
+    text: String,
+    /// The glyphs of the words and spaces, positioned relative to the start of the run.
+    glyphs: Vec<ShapedGlyph>,
+}
+
+fn text_fragment(run: TextRun, origin: f32, baseline: f32) -> TextFragment {
+    let TextRun { style, start, end, text, glyphs } = run;
+    let font = font(style);
+    let size = font_size(style);
+    TextFragment {
+        rect: Rect {
+            x: origin + start,
+            y: baseline - ascent(&font, size),
+            width: end - start,
+            height: ascent(&font, size) + descent(&font, size),
+        },
+        baseline,
+        text,
+        glyphs,
+        font,
+        font_size: size,
+        color: style.get_color("color"),
+    }
+}
+
+/// The fragment of an inline box on one line, ending at `end` (relative to the line start).
+fn box_fragment(
+    open_box: &OpenBox,
+    end: f32,
+    last: bool,
+    origin: f32,
+    baseline: f32,
+    lengths: LengthContext,
+) -> InlineBoxFragment {
+    let style = open_box.style;
+    let edges = inline_edges(style, lengths);
+    let font = font(style);
+    let size = font_size(style);
+
+    // Horizontal edges only apply to the first and last fragments of a box.
+    let mut padding = edges.padding;
+    let mut border = edges.border;
+    let mut margin = EdgeSizes { top: 0.0, bottom: 0.0, ..edges.margin };
+    if !open_box.first {
+        padding.left = 0.0;
+        border.left = 0.0;
+        margin.left = 0.0;
+    }
+    if !last {
+        padding.right = 0.0;
+        border.right = 0.0;
+        margin.right = 0.0;
+    }
+
+    let x = origin + open_box.start + border.left + padding.left;
+    let right = origin + end - border.right - padding.right;
+
+    InlineBoxFragment {
+        dimensions: Dimensions {
+            content: Rect {
+                x,
+                y: baseline - ascent(&font, size),
+                width: (right - x).max(0.0),
+                height: ascent(&font, size) + descent(&font, size),
+            },
+            padding,
+            border,
+            margin,
+        },
+        background_color: style.get_color("background-color"),
+        border_colors: border_colors(style),
+    }
+}
+
+/// Margin, border and padding of an inline box.
+struct InlineEdges {
+    margin: EdgeSizes,
+    border: EdgeSizes,
+    padding: EdgeSizes,
+}
+
+impl InlineEdges {
+    fn left_sum(&self) -> f32 {
+        self.margin.left + self.border.left + self.padding.left
+    }
+
+    fn right_sum(&self) -> f32 {
+        self.margin.right + self.border.right + self.padding.right
+    }
+}
+
+fn inline_edges(style: &StyleNode, lengths: LengthContext) -> InlineEdges {
+    let zero = Length(0.0, Px);
+    let edge = |name: &str| lengths.px(&style.lookup(name, &zero));
+
+    InlineEdges {
+        margin: EdgeSizes {
+            left: edge("margin-left"),
+            right: edge("margin-right"),
+            top: edge("margin-top"),
+            bottom: edge("margin-bottom"),
+        },
+        border: EdgeSizes {
+            left: edge("border-left-width"),
+            right: edge("border-right-width"),
+            top: edge("border-top-width"),
+            bottom: edge("border-bottom-width"),
+        },
+        padding: EdgeSizes {
+            left: edge("padding-left"),
+            right: edge("padding-right"),
+            top: edge("padding-top"),
+            bottom: edge("padding-bottom"),
+        },
+    }
+}
+
+/// The space an inline box with the given style needs above and below the baseline, including
+/// half-leading.
+///
+/// https://www.w3.org/TR/CSS2/visudet.html#leading
+fn line_metrics(style: &StyleNode) -> (f32, f32) {
+    let font = font(style);
+    let size = font_size(style);
+    let line_height = match style.get_value("line-height") {
+        Some(Length(height, Px)) => height,
+        Some(Number(multiplier)) => size * multiplier,
+        _ => size * NORMAL_LINE_HEIGHT,
+    };
+    let (ascent, descent) = (ascent(&font, size), descent(&font, size));
+    let half_leading = (line_height - (ascent + descent)) / 2.0;
+    (ascent + half_leading, descent + half_leading)
+}
@@ -0,0 +1,67 @@
+//! Intrinsic sizes: how wide boxes are when their width depends on their contents.
+//!
+//! https://www.w3.org/TR/css-sizing-3/#intrinsic-sizes
+
+use std::sync::Arc;
+
+use robinson_css::Unit::Px;
+use robinson_css::Value::Length;
+
+use crate::{clamp_size, inline, BoxType, LayoutBox, LengthContext};
+
+/// Which of the intrinsic widths of a box to find.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IntrinsicSize {
+    /// The narrowest the box can be without its contents overflowing, with every line broken
+    /// where it can be.
+    MinContent,
+    /// The width of the box with lines only broken where they are forced to be.
+    MaxContent,
+}
+
+impl LayoutBox {
+    /// The width of the content box if none of its lines were broken.
+    pub(crate) fn max_content_width(&self, lengths: LengthContext) -> f32 {
+        self.intrinsic_width(IntrinsicSize::MaxContent, lengths)
+    }
+
+    /// The min-content or max-content width of the content box.
+    pub(crate) fn intrinsic_width(&self, size: IntrinsicSize, lengths: LengthContext) -> f32 {
+        let style = Arc::clone(self.get_style_node());
+        match self.box_type {
+            BoxType::InlineNode(_) => inline::intrinsic_width(std::slice::from_ref(self), &style, lengths, size),
+            BoxType::AnonymousBlock(_) => inline::intrinsic_width(&self.children, &style, lengths, size),
+            BoxType::BlockNode(_) if self.is_flex_container() => self.flex_intrinsic_width(size, lengths),
+            BoxType::BlockNode(_) if self.is_grid_container() => self.grid_intrinsic_width(size, lengths),
+            BoxType::BlockNode(_) if self.is_table() => self.table_intrinsic_width(size, lengths),
+            BoxType::BlockNode(_) => self
+                .children
+                .iter()
+                .map(|child| child.outer_intrinsic_width(size, lengths))
+                .fold(0.0, f32::max),
+        }
+    }
+
+    /// The intrinsic width of the margin box, which takes a length `width` as it is.
+    pub(crate) fn outer_intrinsic_width(&self, size: IntrinsicSize, lengths: LengthContext) -> f32 {
+        if !matches!(self.box_type, BoxType::BlockNode(_)) {
+            return self.intrinsic_width(size, lengths);
+        }
+
+        let style = self.get_style_node();
+        let zero = Length(0.0, Px);
+        let edges: f32 = [
+            "margin-left", "margin-right", "border-left-width", "border-right-width", "padding-left", "padding-right",
+        ]
+        .iter()
+        .map(|name| lengths.px(&style.lookup(name, &zero)))
+        .sum();
+
+        let (width, min_width, max_width) = self.width_constraints(lengths);
+        let width = match width {
+            Length(width, Px) => width,
+            _ => self.intrinsic_width(size, lengths),
+        };
+        clamp_size(width, min_width, max_width) + edges
+    }
+}
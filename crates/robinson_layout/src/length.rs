@@ -0,0 +1,69 @@
+//! Resolution of lengths that depend on layout.
+//!
+//! The style system has already converted font-relative and absolute lengths into px, which
+//! leaves percentages and viewport units to be resolved here.
+
+use robinson_css::Unit::{self, Fr, Percent, Px, VMax, VMin, Vh, Vw};
+use robinson_css::Value::{self, Calc, Keyword, Length, Number};
+
+use crate::Rect;
+
+/// What the relative lengths of a box are resolved against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LengthContext {
+    pub viewport: Rect,
+    /// Width of the containing block, which percentage widths, margins and padding refer to.
+    pub containing_width: f32,
+    /// Height of the containing block, or `None` if it depends on its contents.
+    pub containing_height: Option<f32>,
+}
+
+impl LengthContext {
+    /// Resolve a horizontal size, margin or padding into px. Keywords are returned unchanged.
+    pub fn resolve(self, value: &Value) -> Value {
+        self.resolve_against(value, Some(self.containing_width))
+    }
+
+    /// Resolve a vertical size into px.
+    ///
+    /// A percentage of a containing block whose height depends on its contents behaves as `auto`.
+    ///
+    /// https://www.w3.org/TR/CSS2/visudet.html#the-height-property
+    pub fn resolve_height(self, value: &Value) -> Value {
+        self.resolve_against(value, self.containing_height)
+    }
+
+    /// Resolve a horizontal size, margin or padding into px, or zero for non-lengths.
+    pub fn px(self, value: &Value) -> f32 {
+        self.resolve(value).to_px()
+    }
+
+    /// Math functions are evaluated here, since they can mix percentages with other units. Like
+    /// a percentage, a length that can't be resolved is treated as `auto`. A unitless zero is a
+    /// length of zero.
+    fn resolve_against(self, value: &Value, percent_base: Option<f32>) -> Value {
+        let to_px = |length, unit| self.length_px(length, unit, percent_base);
+        let resolved = match *value {
+            Number(0.0) => Some(Length(0.0, Px)),
+            Length(length, unit) => to_px(length, unit).map(|px| Length(px, Px)),
+            Calc(ref calc) => calc.evaluate(&to_px),
+            ref value => return value.clone(),
+        };
+        resolved.unwrap_or_else(|| Keyword("auto".to_string()))
+    }
+
+    fn length_px(self, length: f32, unit: Unit, percent_base: Option<f32>) -> Option<f32> {
+        let viewport = self.viewport;
+        match unit {
+            Percent => percent_base.map(|base| length / 100.0 * base),
+            Vw => Some(length / 100.0 * viewport.width),
+            Vh => Some(length / 100.0 * viewport.height),
+            VMin => Some(length / 100.0 * viewport.width.min(viewport.height)),
+            VMax => Some(length / 100.0 * viewport.width.max(viewport.height)),
+            // Flexible lengths are only for grid tracks, which resolve them on their own.
+            Fr => None,
+            // Other units have been converted to px when computing the style.
+            _ => Some(length),
+        }
+    }
+}
@@ -1,17 +1,31 @@
-///! Basic CSS block layout.
+//! Basic CSS block and inline layout.
 
-use robinson_style::{StyleNode, Display};
-use robinson_css::Value::{Keyword, Length};
+use robinson_style::{Clear, Display, Float, Overflow, Position, StyleNode};
+use robinson_css::Value::{self, Keyword, Length};
 use robinson_css::Unit::Px;
-use std::rc::Rc;
+use robinson_dom::Node;
+use std::sync::Arc;
 
 pub use render::*;
 
+use float::Floats;
+use length::LengthContext;
+use table::TableCache;
+
+mod flex;
+mod float;
+mod grid;
+mod inline;
+mod intrinsic;
+mod length;
+mod positioned;
 mod render;
+mod table;
+mod text;
 
 // CSS box model. All sizes are in px.
 
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
 pub struct Rect {
     pub x: f32,
     pub y: f32,
@@ -42,12 +56,22 @@ pub struct LayoutBox {
     pub dimensions: Dimensions,
     pub box_type: BoxType,
     pub children: Vec<LayoutBox>,
+    /// Whether the box establishes a new block formatting context, whose margins don't collapse
+    /// with those of its children.
+    pub new_formatting_context: bool,
+    /// Where the top left corner of the margin box would be if the box were in normal flow,
+    /// which is where an absolutely positioned box without offsets goes.
+    pub static_position: (f32, f32),
+    /// The position of the box's element among the elements of the document, in tree order.
+    pub tree_order: usize,
+    /// The grid of a table, kept between the times it's needed.
+    table_cache: TableCache,
 }
 
 pub enum BoxType {
-    BlockNode(Rc<StyleNode>),
-    InlineNode(Rc<StyleNode>),
-    AnonymousBlock(Rc<StyleNode>),
+    BlockNode(Arc<StyleNode>),
+    InlineNode(Arc<StyleNode>),
+    AnonymousBlock(Arc<StyleNode>),
 }
 
 impl LayoutBox {
@@ -56,109 +80,537 @@ impl LayoutBox {
             box_type,
             dimensions: Default::default(),
             children: Vec::new(),
+            new_formatting_context: false,
+            static_position: (0.0, 0.0),
+            tree_order: 0,
+            table_cache: TableCache::default(),
         }
     }
 
-    fn get_style_node(&self) -> &Rc<StyleNode> {
+    fn get_style_node(&self) -> &Arc<StyleNode> {
         match &self.box_type {
             BoxType::BlockNode(node)
             | BoxType::InlineNode(node)
             | BoxType::AnonymousBlock(node) => node,
         }
     }
+
+    fn is_float(&self) -> bool {
+        matches!(&self.box_type, BoxType::BlockNode(node) if node.float() != Float::None)
+    }
 }
 
 /// Build the tree of LayoutBoxes, but don't perform any layout calculations yet.
-fn build_layout_tree(style_node: &Rc<StyleNode>) -> LayoutBox {
+///
+/// `tree_order` counts the elements visited so far, and `item` is whether the element is a child
+/// of a flex or grid container.
+fn build_layout_tree(style_node: &Arc<StyleNode>, tree_order: &mut usize, item: bool) -> LayoutBox {
     // Create the root box.
+    // Display types without a layout mode of their own yet (list items and inline blocks) are
+    // laid out as blocks, and so are floats and absolutely positioned boxes whatever their
+    // display.
     let mut root = LayoutBox::new(match style_node.display() {
-        Display::Block => BoxType::BlockNode(Rc::clone(style_node)),
-        Display::Inline => BoxType::InlineNode(Rc::clone(style_node)),
-        _ => panic!("Root node has display: none.")
+        Display::None => panic!("Root node has display: none."),
+        // Flex and grid items are block-level whatever their display.
+        Display::Inline if is_in_flow(style_node) && !item => BoxType::InlineNode(Arc::clone(style_node)),
+        _ => BoxType::BlockNode(Arc::clone(style_node)),
     });
+    root.new_formatting_context = item || establishes_formatting_context(style_node);
+    root.tree_order = *tree_order;
+    *tree_order += 1;
 
     // Create the descendant boxes.
-    for child in style_node.children.borrow().iter() {
+    let item_container = root.is_flex_container() || root.is_grid_container();
+    let table_container = root.contains_table_parts();
+    for child in style_node.children.iter() {
         match child.display() {
-            Display::Block => root.children.push(build_layout_tree(child)),
-            Display::Inline => root.get_inline_container().children.push(build_layout_tree(child)),
-            _ => {} // Don't lay out nodes with `display: none;`
+            Display::None => {} // Don't lay out nodes with `display: none;`
+            // Each child element of a flex or grid container is an item, and so is each run of
+            // text between them, except for runs of only white space.
+            _ if item_container => match &child.node {
+                Node::Text(text) if text.trim().is_empty() => {}
+                Node::Text(_) => root.get_inline_container().children.push(build_layout_tree(child, tree_order, false)),
+                _ => root.children.push(build_layout_tree(child, tree_order, true)),
+            },
+            // White space and comments between the parts of a table don't make boxes.
+            _ if table_container && is_insignificant(&child.node) => {}
+            // Absolutely positioned boxes that would have been inline stay among the inline
+            // content, so that the lines around them aren't broken.
+            Display::Inline if child.float() == Float::None => {
+                root.get_inline_container().children.push(build_layout_tree(child, tree_order, false))
+            }
+            _ => root.children.push(build_layout_tree(child, tree_order, false)),
         }
     }
+    root.split_inlines_around_blocks();
+    root.wrap_table_parts();
     root
 }
 
+/// Whether a node is only white space or a comment, which don't make boxes where only boxes of
+/// certain kinds are allowed.
+fn is_insignificant(node: &Node) -> bool {
+    match node {
+        Node::Text(text) => text.trim().is_empty(),
+        Node::Comment(_) => true,
+        Node::Element(_) => false,
+    }
+}
+
+/// Whether a box among inline content is a block-level box in normal flow, which the inline
+/// content is split around.
+fn is_block_in_inline(layout_box: &LayoutBox) -> bool {
+    matches!(&layout_box.box_type, BoxType::BlockNode(node) if is_in_flow(node))
+}
+
+/// Whether an inline box has a block-level box in normal flow among its descendants.
+fn contains_block(layout_box: &LayoutBox) -> bool {
+    matches!(layout_box.box_type, BoxType::InlineNode(_))
+        && layout_box.children.iter().any(|child| is_block_in_inline(child) || contains_block(child))
+}
+
+/// Split an inline box around the block-level boxes inside it, returning the fragments of the
+/// inline box and the block-level boxes between them, in order. Fragments with nothing in them
+/// but white space are left out.
+fn split_inline(layout_box: LayoutBox) -> Vec<LayoutBox> {
+    if !contains_block(&layout_box) {
+        return vec![layout_box];
+    }
+
+    let (style, tree_order) = (Arc::clone(layout_box.get_style_node()), layout_box.tree_order);
+    let fragment = || LayoutBox { tree_order, ..LayoutBox::new(BoxType::InlineNode(Arc::clone(&style))) };
+    let mut pieces = Vec::new();
+    let mut current = fragment();
+    for child in layout_box.children.into_iter().flat_map(split_inline) {
+        if is_block_in_inline(&child) {
+            pieces.push(std::mem::replace(&mut current, fragment()));
+            pieces.push(child);
+        } else {
+            current.children.push(child);
+        }
+    }
+    pieces.push(current);
+    pieces.retain(|piece| is_block_in_inline(piece) || !is_blank_inline(piece));
+    pieces
+}
+
+/// Whether an inline box holds nothing but white space, so that it would make an empty line.
+fn is_blank_inline(layout_box: &LayoutBox) -> bool {
+    match &layout_box.box_type {
+        BoxType::InlineNode(node) => match &node.node {
+            Node::Element(element) if element.name == "br" => false,
+            Node::Element(_) => layout_box.children.iter().all(is_blank_inline),
+            node => is_insignificant(node),
+        },
+        _ => false,
+    }
+}
+
+/// Whether an element's box is in normal flow, rather than floated or absolutely positioned.
+fn is_in_flow(style_node: &StyleNode) -> bool {
+    style_node.float() == Float::None && !style_node.position().is_absolute()
+}
+
+/// Whether an element's box is the root of a new block formatting context, rather than taking
+/// part in that of its parent. The root element's box always is.
+///
+/// https://www.w3.org/TR/CSS2/visuren.html#block-formatting
+fn establishes_formatting_context(style_node: &StyleNode) -> bool {
+    // Unlike the other values, `overflow: clip` only clips, without changing layout.
+    !is_in_flow(style_node)
+        || matches!(
+            style_node.display(),
+            Display::InlineBlock
+                | Display::Flex
+                | Display::InlineFlex
+                | Display::Grid
+                | Display::InlineGrid
+                | Display::Table
+                | Display::InlineTable
+                | Display::TableCell
+                | Display::TableCaption
+        )
+        || !matches!(style_node.overflow(), Overflow::Visible | Overflow::Clip)
+}
+
+/// Whether an element's box is the root of a stacking context, which is painted as a whole
+/// among the other boxes in its parent stacking context. The root element's box always is.
+///
+/// Fixed and sticky boxes always are, and other positioned boxes are unless their `z-index` is
+/// `auto`.
+///
+/// https://www.w3.org/TR/CSS2/visuren.html#z-index
+fn establishes_stacking_context(style_node: &StyleNode) -> bool {
+    match style_node.position() {
+        Position::Static => false,
+        Position::Fixed | Position::Sticky => true,
+        Position::Relative | Position::Absolute => style_node.z_index().is_some(),
+    }
+}
+
+/// Adjoining vertical margins that haven't been turned into space yet.
+///
+/// Adjoining margins collapse into a single margin, as large as the largest positive margin
+/// minus the magnitude of the most negative one.
+///
+/// http://www.w3.org/TR/CSS2/box.html#collapsing-margins
+#[derive(Clone, Copy, Default, Debug)]
+struct CollapsedMargin {
+    positive: f32,
+    negative: f32,
+}
+
+impl CollapsedMargin {
+    fn adjoin(&mut self, margin: f32) {
+        self.positive = self.positive.max(margin);
+        self.negative = self.negative.min(margin);
+    }
+
+    fn size(self) -> f32 {
+        self.positive + self.negative
+    }
+}
+
+/// Where the next block goes in a block formatting context.
+#[derive(Clone, Default, Debug)]
+struct FlowPosition {
+    /// Where the pending margin starts: the bottom border edge of the previous block, or the top
+    /// content edge of the container.
+    y: f32,
+    /// Margins that the next block's top margin collapses with.
+    margin: CollapsedMargin,
+    /// The floats placed in the formatting context so far.
+    floats: Floats,
+}
+
+impl FlowPosition {
+    fn at(y: f32) -> Self {
+        FlowPosition { y, ..Default::default() }
+    }
+
+    /// Continue at `y`, with no pending margin.
+    fn move_to(&mut self, y: f32) {
+        self.y = y;
+        self.margin = CollapsedMargin::default();
+    }
+
+    /// Turn the pending margin into space, and return where the content after it starts.
+    fn resolve(&mut self) -> f32 {
+        self.move_to(self.y + self.margin.size());
+        self.y
+    }
+
+    /// Where a box with the given top margin would start if nothing separated its margin from
+    /// the pending one.
+    fn after_margin(&self, margin_top: f32) -> f32 {
+        let mut margin = self.margin;
+        margin.adjoin(margin_top);
+        self.y + margin.size()
+    }
+}
+
 impl LayoutBox {
-    /// Lay out a box and its descendants.
-    fn layout(&mut self, containing_block: &mut Dimensions) -> RenderBox {
+    /// Lay out a box and its descendants, below the blocks already at `position`.
+    ///
+    /// Also returns the top of the box's border box, or `None` if the box is empty and its
+    /// margins collapse through it.
+    fn layout(
+        &mut self,
+        containing_block: &Dimensions,
+        lengths: LengthContext,
+        position: &mut FlowPosition,
+    ) -> (RenderBox, Option<f32>) {
         match self.box_type {
-            BoxType::BlockNode(_) => RenderBox::Block(self.layout_block(containing_block)),
-            BoxType::InlineNode(_) => RenderBox::Inline,
-            BoxType::AnonymousBlock(_) => RenderBox::Anonymous,
+            BoxType::BlockNode(_) => {
+                let (render_box, top) = self.layout_block(containing_block, lengths, position);
+                (RenderBox::Block(render_box), top)
+            }
+            BoxType::InlineNode(_) | BoxType::AnonymousBlock(_) => {
+                let (render_box, top) = self.layout_anonymous(containing_block, lengths, position);
+                (RenderBox::Anonymous(render_box), top)
+            }
         }
     }
 
+    /// Lay out a block containing only inline-level boxes into line boxes.
+    ///
+    /// An inline box that is laid out on its own (such as an inline root) is treated as the only
+    /// content of such a block.
+    fn layout_anonymous(
+        &mut self,
+        containing_block: &Dimensions,
+        lengths: LengthContext,
+        position: &mut FlowPosition,
+    ) -> (RenderAnonymousBox, Option<f32>) {
+        // Anonymous boxes have no margins, borders or padding, and fill their container.
+        let d = &mut self.dimensions;
+        d.content.x = containing_block.content.x;
+        d.content.y = position.y + position.margin.size();
+        d.content.width = containing_block.content.width;
+
+        let style = Arc::clone(self.get_style_node());
+        let content = self.dimensions.content;
+        let inline::Lines { lines, height, anchors } = match self.box_type {
+            BoxType::InlineNode(_) => inline::layout_lines(std::slice::from_ref(self), &style, content, lengths, &position.floats),
+            _ => inline::layout_lines(&self.children, &style, content, lengths, &position.floats),
+        };
+        self.dimensions.content.height = height;
+        self.set_static_positions(&mut anchors.into_iter());
+
+        // Without any lines, the margins around the box collapse through it.
+        let top = match lines.is_empty() {
+            true => None,
+            false => {
+                let top = position.resolve();
+                position.move_to(top + height);
+                Some(top)
+            }
+        };
+
+        let render_box = RenderAnonymousBox {
+            dimensions: self.dimensions,
+            lines,
+        };
+        (render_box, top)
+    }
+
     /// Lay out a block-level element and its descendants.
-    fn layout_block(&mut self, containing_block: &mut Dimensions) -> RenderBlockBox {
+    fn layout_block(
+        &mut self,
+        containing_block: &Dimensions,
+        lengths: LengthContext,
+        position: &mut FlowPosition,
+    ) -> (RenderBlockBox, Option<f32>) {
+        let style = Arc::clone(self.get_style_node());
+        let zero = Length(0.0, Px);
+        let margin_top = lengths.px(&style.lookup("margin-top", &zero));
+
+        // Clearance moves the top border edge below the floats that the box clears.
+        if let Some(clear_y) = position.floats.clearance(style.clear()) {
+            if position.after_margin(margin_top) < clear_y {
+                position.move_to(clear_y - margin_top);
+            }
+        }
+
+        // The border box of a formatting context root can't overlap the floats in the context
+        // around it, so it is narrowed to fit beside them.
+        let mut containing_block = *containing_block;
+        if self.new_formatting_context {
+            let content = containing_block.content;
+            let top = position.after_margin(margin_top);
+            let (left, right) = position.floats.available(top, 0.0, content.x, content.x + content.width);
+            containing_block.content.x = left;
+            containing_block.content.width = (right - left).max(0.0);
+        }
+
         // Child width can depend on parent width, so we need to calculate this box's width before
         // laying out its children.
-        self.calculate_block_width(containing_block);
+        self.calculate_block_width(&containing_block, lengths);
 
         // Determine where the box is located within its container.
-        self.calculate_block_position(containing_block);
+        self.calculate_block_position(&containing_block, lengths);
+
+        let (mut render_box, top) = self.layout_block_contents(lengths, position, None);
+
+        // Relative and sticky offsets move the box without affecting the boxes around it.
+        let (dx, dy) = self.relative_offset(&containing_block, lengths);
+        if (dx, dy) != (0.0, 0.0) {
+            self.dimensions.content.translate(dx, dy);
+            render_box.translate(dx, dy);
+        }
+        (render_box, top)
+    }
+
+    /// Lay out the children of a block whose horizontal dimensions are known, and place it
+    /// vertically.
+    ///
+    /// `used_height` is the height to use instead of the `height` property, for boxes whose
+    /// height is decided by the space they fill.
+    fn layout_block_contents(
+        &mut self,
+        lengths: LengthContext,
+        position: &mut FlowPosition,
+        used_height: Option<f32>,
+    ) -> (RenderBlockBox, Option<f32>) {
+        // A height that doesn't depend on the children is known before laying them out, so that
+        // their percentage heights can refer to it.
+        let (height, min_height, max_height) = self.height_constraints(lengths);
+        let height = used_height.or(height.map(|height| clamp_size(height, min_height, max_height)));
+
+        // The top margin collapses with the margins before the box, and with the top margin of
+        // its first child unless a border or padding separates them.
+        let d = self.dimensions;
+        position.margin.adjoin(d.margin.top);
+        let mut content_top = None;
+        if self.new_formatting_context || d.border.top + d.padding.top != 0.0 {
+            let top = position.resolve() + d.border.top + d.padding.top;
+            position.move_to(top);
+            content_top = Some(top);
+        }
+
+        // A formatting context root lays out its children in a flow of their own, so that their
+        // margins and floats stay inside it.
+        let mut inner = None;
+        let flow = match content_top {
+            Some(top) if self.new_formatting_context => inner.insert(FlowPosition::at(top)),
+            _ => &mut *position,
+        };
 
         // Recursively lay out the children of this box.
-        let children = self.layout_block_children();
+        let child_lengths = LengthContext {
+            containing_width: self.dimensions.content.width,
+            containing_height: height,
+            ..lengths
+        };
+        // Flex and grid containers and tables are formatting context roots, so where their
+        // content starts is already known.
+        let (children, first_child_top) = if self.is_flex_container() {
+            (self.layout_flex_children(child_lengths, flow), None)
+        } else if self.is_grid_container() {
+            (self.layout_grid_children(child_lengths, flow), None)
+        } else if self.is_table() {
+            (self.layout_table_children(child_lengths, flow), None)
+        } else {
+            self.layout_block_children(child_lengths, flow)
+        };
+
+        // It also grows to contain its floats.
+        if let Some(bottom) = flow.floats.clearance(Clear::Both).filter(|_| self.new_formatting_context) {
+            let end = flow.resolve();
+            flow.move_to(end.max(bottom));
+        }
 
         // Parent height can depend on child height, so `calculate_height` must be called after the
         // children are laid out.
-        self.calculate_block_height();
+        let top = self.calculate_block_height(
+            height,
+            (min_height, max_height),
+            content_top.or(first_child_top),
+            flow,
+        );
+        if let Some(inner) = inner {
+            position.y = inner.y;
+            position.margin = inner.margin;
+        }
 
-        let zero = Length(0.0, Px);
-        let style = self.get_style_node();
+        let mut render_box = self.render_block(children);
 
+        // A positioned box is the containing block of its absolutely positioned descendants, which
+        // can be placed now that its size is known.
+        if self.position() != Position::Static {
+            let padding_box = self.dimensions.padding_box();
+            let descendants = self.layout_absolute_descendants(padding_box, lengths.viewport, Position::Absolute);
+            render_box.children.extend(descendants);
+        }
+        (render_box, top)
+    }
+
+    /// The render box of a block that has been laid out, with its children's render boxes.
+    fn render_block(&self, children: Vec<RenderBox>) -> RenderBlockBox {
+        let style = self.get_style_node();
         RenderBlockBox {
-            dimensions: Dimensions {
-                border: EdgeSizes {
-                    top: style.lookup_with_fallback("border-top-width", "border-width", &zero).to_px(),
-                    bottom: style.lookup_with_fallback("border-bottom-width", "border-width", &zero).to_px(),
-                    left: style.lookup_with_fallback("border-left-width", "border-width", &zero).to_px(),
-                    right: style.lookup_with_fallback("border-bottom-right", "border-width", &zero).to_px(),
-                },
-                ..self.dimensions
-            },
+            dimensions: self.dimensions,
+            position: style.position(),
+            float: style.float(),
+            z_index: establishes_stacking_context(style).then(|| style.z_index().unwrap_or(0)),
+            tree_order: self.tree_order,
+            overflow: style.overflow(),
 
             color: style.get_color("color"),
-            background_color: style.get_color("background"),
-            border_color: style.get_color("border-color"),
+            background_color: style.get_color("background-color"),
+            border_colors: border_colors(style),
 
             children,
         }
     }
 
-    /// Calculate the width of a block-level non-replaced element in normal flow.
+    /// Lay out a flex or grid item with the given content width, and height if it is known, with
+    /// the top left corner of its margin box at the origin.
+    fn layout_item(&mut self, lengths: LengthContext, width: f32, height: Option<f32>) -> RenderBox {
+        match self.box_type {
+            BoxType::BlockNode(_) => {
+                self.dimensions.content.width = width;
+                self.calculate_block_position(&Dimensions::default(), lengths);
+                let (render_box, _) = self.layout_block_contents(lengths, &mut FlowPosition::at(0.0), height);
+                RenderBox::Block(render_box)
+            }
+            BoxType::InlineNode(_) | BoxType::AnonymousBlock(_) => {
+                let containing_block = Dimensions { content: Rect { width, ..Default::default() }, ..Default::default() };
+                let (render_box, _) = self.layout_anonymous(&containing_block, lengths, &mut FlowPosition::at(0.0));
+                RenderBox::Anonymous(render_box)
+            }
+        }
+    }
+
+    /// Calculate the width of a block-level non-replaced element in normal flow, within its
+    /// `min-width` and `max-width`.
     ///
-    /// http://www.w3.org/TR/CSS2/visudet.html#blockwidth
+    /// http://www.w3.org/TR/CSS2/visudet.html#min-max-widths
     ///
     /// Sets the horizontal margin/padding/border dimensions, and the `width`.
-    fn calculate_block_width(&mut self, containing_block: &mut Dimensions) {
+    fn calculate_block_width(&mut self, containing_block: &Dimensions, lengths: LengthContext) {
+        let (width, min_width, max_width) = self.width_constraints(lengths);
+        let width = match self.is_table() {
+            true => self.table_width(containing_block, lengths, width),
+            false => width,
+        };
+
+        // The tentative width is replaced by `max-width` if it's wider, and then by `min-width` if
+        // it's narrower.
+        self.solve_block_width(containing_block, lengths, width);
+        if let Some(max_width) = max_width {
+            if self.dimensions.content.width > max_width {
+                self.solve_block_width(containing_block, lengths, Length(max_width, Px));
+            }
+        }
+        if self.dimensions.content.width < min_width {
+            self.solve_block_width(containing_block, lengths, Length(min_width, Px));
+        }
+    }
+
+    /// The `width`, `min-width` and `max-width` of a box, as sizes of its content box.
+    /// `max-width` is `None` if it doesn't apply.
+    fn width_constraints(&self, lengths: LengthContext) -> (Value, f32, Option<f32>) {
         let style = self.get_style_node();
 
-        // `width` has initial value `auto`.
+        // With `box-sizing: border-box`, the sizes include the horizontal padding and border.
+        let zero = Length(0.0, Px);
+        let sizing = match is_border_box(style) {
+            true => sum(["padding-left", "padding-right", "border-left-width", "border-right-width"]
+                .iter()
+                .map(|name| lengths.px(&style.lookup(name, &zero)))),
+            false => 0.0,
+        };
+        let size = |name: &str, default: &Value| content_size(lengths.resolve(&style.lookup(name, default)), sizing);
+
+        let width = size("width", &Keyword("auto".to_string()));
+        let min_width = size("min-width", &zero).to_px();
+        let max_width = match size("max-width", &Keyword("none".to_string())) {
+            Length(max_width, Px) => Some(max_width),
+            _ => None,
+        };
+        (width, min_width, max_width)
+    }
+
+    /// Solve for the used width and horizontal margins, given the width of the content box.
+    ///
+    /// http://www.w3.org/TR/CSS2/visudet.html#blockwidth
+    fn solve_block_width(&mut self, containing_block: &Dimensions, lengths: LengthContext, mut width: Value) {
+        let style = self.get_style_node();
         let auto = Keyword("auto".to_string());
-        let mut width = style.get_value("width").unwrap_or(auto.clone());
 
         // margin, border, and padding have initial value 0.
         let zero = Length(0.0, Px);
+        let lookup = |name: &str| lengths.resolve(&style.lookup(name, &zero));
 
-        let mut margin_left = style.lookup_with_fallback("margin-left", "margin", &zero);
-        let mut margin_right = style.lookup_with_fallback("margin-right", "margin", &zero);
+        let mut margin_left = lookup("margin-left");
+        let mut margin_right = lookup("margin-right");
 
-        let border_left = style.lookup_with_fallback("border-left-width", "border-width", &zero);
-        let border_right = style.lookup_with_fallback("border-right-width", "border-width", &zero);
+        let border_left = lookup("border-left-width");
+        let border_right = lookup("border-right-width");
 
-        let padding_left = style.lookup_with_fallback("padding-left", "padding", &zero);
-        let padding_right = style.lookup_with_fallback("padding-right", "padding", &zero);
+        let padding_left = lookup("padding-left");
+        let padding_right = lookup("padding-right");
 
         let total = sum([&margin_left, &margin_right, &border_left, &border_right,
                          &padding_left, &padding_right, &width].iter().map(|v| v.to_px()));
@@ -223,32 +675,36 @@ impl LayoutBox {
         d.margin.right = margin_right.to_px();
     }
 
-    /// Finish calculating the block's edge sizes, and position it within its containing block.
+    /// Finish calculating the block's edge sizes, and position it horizontally within its
+    /// containing block. The vertical position depends on margin collapsing, and is set once the
+    /// children are laid out.
     ///
     /// http://www.w3.org/TR/CSS2/visudet.html#normal-block
     ///
-    /// Sets the vertical margin/padding/border dimensions, and the `x`, `y` values.
-    fn calculate_block_position(&mut self, containing_block: &mut Dimensions) {
+    /// Sets the vertical margin/padding/border dimensions, and the `x` value.
+    fn calculate_block_position(&mut self, containing_block: &Dimensions, lengths: LengthContext) {
         let style = self.get_style_node();
 
         // margin, border, and padding have initial value 0.
         let zero = Length(0.0, Px);
+        // Percentages of vertical margins and padding also refer to the containing block's width.
+        let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
 
         // If margin-top or margin-bottom is `auto`, the used value is zero.
         let margin = EdgeSizes {
-            top: style.lookup_with_fallback("margin-top", "margin", &zero).to_px(),
-            bottom: style.lookup_with_fallback("margin-bottom", "margin", &zero).to_px(),
+            top: lookup("margin-top"),
+            bottom: lookup("margin-bottom"),
             ..(self.dimensions.margin)
         };
 
         let border = EdgeSizes {
-            top: style.lookup_with_fallback("border-top-width", "border-width", &zero).to_px(),
-            bottom: style.lookup_with_fallback("border-bottom-width", "border-width", &zero).to_px(),
+            top: lookup("border-top-width"),
+            bottom: lookup("border-bottom-width"),
             ..(self.dimensions.border)
         };
         let padding = EdgeSizes {
-            top: style.lookup_with_fallback("padding-top", "padding", &zero).to_px(),
-            bottom: style.lookup_with_fallback("padding-bottom", "padding", &zero).to_px(),
+            top: lookup("padding-top"),
+            bottom: lookup("padding-bottom"),
             ..(self.dimensions.padding)
         };
 
@@ -261,36 +717,169 @@ impl LayoutBox {
         d.content.x = containing_block.content.x +
                       d.margin.left + d.border.left + d.padding.left;
 
-        // Position the box below all the previous boxes in the container.
-        d.content.y = containing_block.content.height + containing_block.content.y +
-                      d.margin.top + d.border.top + d.padding.top;
+        if self.is_table() && self.collapses_borders() {
+            self.collapse_table_edges();
+        }
     }
 
-    /// Lay out the block's children within its content area.
+    /// Lay out the block's children within its content area, each below the previous one.
     ///
-    /// Sets `self.dimensions.height` to the total content height.
-    fn layout_block_children(&mut self) -> Vec<RenderBox> {
+    /// Also returns the top of the first child that isn't collapsed through.
+    fn layout_block_children(
+        &mut self,
+        lengths: LengthContext,
+        position: &mut FlowPosition,
+    ) -> (Vec<RenderBox>, Option<f32>) {
         let mut children = Vec::new();
-        let d = &mut self.dimensions;
+        let mut floats = Vec::new();
+        let mut first_child_top = None;
+        let d = self.dimensions;
         for child in &mut self.children {
-            let render_box = child.layout(d);
-            // Increment the height so each child is laid out below the previous one.
-            d.content.height += child.dimensions.margin_box().height;
+            if child.position().is_absolute() {
+                // Absolutely positioned boxes are laid out once their containing block is, but
+                // remember where they would have been.
+                child.static_position = (d.content.x, position.y + position.margin.size());
+                continue;
+            }
+            if child.is_float() {
+                floats.push(child.layout_float(&d, lengths, position));
+                continue;
+            }
+            let (render_box, top) = child.layout(&d, lengths, position);
+            first_child_top = first_child_top.or(top);
             children.push(render_box);
         }
-        children
+        // Floats are painted over the backgrounds of the blocks beside them.
+        children.extend(floats);
+        (children, first_child_top)
+    }
+
+    /// The used `height`, `min-height` and `max-height` of a block, as sizes of its content box.
+    /// `height` and `max-height` are `None` if they depend on the contents or don't apply.
+    ///
+    /// A percentage of a containing block whose height depends on its contents behaves as `auto`
+    /// for `height`, `0` for `min-height` and `none` for `max-height`.
+    fn height_constraints(&self, lengths: LengthContext) -> (Option<f32>, f32, Option<f32>) {
+        let style = self.get_style_node();
+        let d = self.dimensions;
+
+        // With `box-sizing: border-box`, the sizes include the vertical padding and border.
+        let sizing = match is_border_box(style) {
+            true => d.padding.top + d.padding.bottom + d.border.top + d.border.bottom,
+            false => 0.0,
+        };
+        let size = |name: &str| match style.get_value(name) {
+            Some(value) => match content_size(lengths.resolve_height(&value), sizing) {
+                Length(size, Px) => Some(size),
+                _ => None,
+            },
+            None => None,
+        };
+
+        (size("height"), size("min-height").unwrap_or(0.0), size("max-height"))
     }
 
-    /// Height of a block-level non-replaced element in normal flow with overflow visible.
-    fn calculate_block_height(&mut self) {
+    /// Height of a block-level non-replaced element in normal flow with overflow visible, and its
+    /// vertical position.
+    ///
+    /// http://www.w3.org/TR/CSS2/visudet.html#min-max-heights
+    ///
+    /// `height` is the used value of the `height` property, or `None` if it is `auto`, and
+    /// `limits` are the used `min-height` and `max-height`. `content_top` is where the content
+    /// starts, if something has fixed it: a top border or padding, or a child that isn't collapsed
+    /// through. `position` is where the children ended.
+    ///
+    /// Returns the top of the border box, or `None` if the margins collapse through the block.
+    fn calculate_block_height(
+        &mut self,
+        height: Option<f32>,
+        limits: (f32, Option<f32>),
+        content_top: Option<f32>,
+        position: &mut FlowPosition,
+    ) -> Option<f32> {
+        let (min_height, max_height) = limits;
+        let d = &mut self.dimensions;
+
+        // The bottom margin collapses with the last child's bottom margin, unless a border,
+        // padding or explicit height separates them.
+        let bottom_separated = self.new_formatting_context
+            || d.border.bottom + d.padding.bottom != 0.0
+            || height.is_some();
+
+        let Some(content_top) = content_top else {
+            if !bottom_separated && min_height == 0.0 {
+                // An empty block's top and bottom margins collapse together. It is placed where
+                // its top border edge would be without the bottom margin.
+                d.content.y = position.y + position.margin.size();
+                d.content.height = 0.0;
+                position.margin.adjoin(d.margin.bottom);
+                return None;
+            }
+            let top = position.resolve();
+            return self.calculate_block_height(height, limits, Some(top), position);
+        };
+        d.content.y = content_top;
+
         // If the height is set to an explicit length, use that exact length.
-        // Otherwise, just keep the value set by `layout_block_children`.
-        if let Some(Length(h, Px)) = self.get_style_node().get_value("height") {
-            self.dimensions.content.height = h;
+        // Otherwise, the content ends after the last child, within `min-height` and `max-height`.
+        if bottom_separated {
+            d.content.height = height
+                .unwrap_or_else(|| clamp_size(position.resolve() - content_top, min_height, max_height));
+            position.move_to(content_top + d.content.height + d.padding.bottom + d.border.bottom);
+        } else {
+            let height = position.y - content_top;
+            d.content.height = clamp_size(height, min_height, max_height);
+            // A limit moves the bottom edge away from the last child's bottom margin.
+            if d.content.height != height {
+                position.move_to(content_top + d.content.height);
+            }
         }
+        position.margin.adjoin(d.margin.bottom);
+
+        Some(content_top - d.padding.top - d.border.top)
     }
 
     /// Where a new inline child should go.
+    /// Move the block-level boxes inside the inline boxes of this box's anonymous blocks out to
+    /// be children of this box. The inline boxes are split around them, and the inline content
+    /// before and after each goes in an anonymous block of its own.
+    ///
+    /// https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
+    fn split_inlines_around_blocks(&mut self) {
+        let needs_split = |child: &LayoutBox| {
+            matches!(child.box_type, BoxType::AnonymousBlock(_)) && child.children.iter().any(contains_block)
+        };
+        if !self.children.iter().any(needs_split) {
+            return;
+        }
+
+        let mut children = Vec::new();
+        for child in std::mem::take(&mut self.children) {
+            if !needs_split(&child) {
+                children.push(child);
+                continue;
+            }
+            let style = Arc::clone(child.get_style_node());
+            let anonymous_block = || LayoutBox::new(BoxType::AnonymousBlock(Arc::clone(&style)));
+            let mut inline_content = anonymous_block();
+            for piece in child.children.into_iter().flat_map(split_inline) {
+                if is_block_in_inline(&piece) {
+                    let before = std::mem::replace(&mut inline_content, anonymous_block());
+                    if !before.children.iter().all(is_blank_inline) {
+                        children.push(before);
+                    }
+                    children.push(piece);
+                } else {
+                    inline_content.children.push(piece);
+                }
+            }
+            if !inline_content.children.iter().all(is_blank_inline) {
+                children.push(inline_content);
+            }
+        }
+        self.children = children;
+    }
+
     fn get_inline_container(&mut self) -> &mut LayoutBox {
         match &self.box_type {
             BoxType::InlineNode(_) | BoxType::AnonymousBlock(_) => self,
@@ -302,7 +891,7 @@ impl LayoutBox {
                     _ => false
                 };
                 if !is_anon {
-                    self.children.push(LayoutBox::new(BoxType::AnonymousBlock(Rc::clone(node))))
+                    self.children.push(LayoutBox::new(BoxType::AnonymousBlock(Arc::clone(node))))
                 }
                 self.children.last_mut().unwrap()
             }
@@ -311,6 +900,20 @@ impl LayoutBox {
 }
 
 impl Rect {
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+    }
+
+    /// The area covered by both rectangles, which is empty if they don't overlap.
+    pub fn intersection(self, other: Rect) -> Rect {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        Rect { x, y, width: (right - x).max(0.0), height: (bottom - y).max(0.0) }
+    }
+
     pub fn expanded_by(self, edge: EdgeSizes) -> Rect {
         Rect {
             x: self.x - edge.left,
@@ -336,6 +939,42 @@ impl Dimensions {
     }
 }
 
+/// The border colors of a box, from its `border-*-color` longhands.
+fn border_colors(style: &StyleNode) -> BorderColors {
+    BorderColors {
+        top: style.get_color("border-top-color"),
+        right: style.get_color("border-right-color"),
+        bottom: style.get_color("border-bottom-color"),
+        left: style.get_color("border-left-color"),
+    }
+}
+
+/// The keyword a property is set to, or `default` if it isn't set to one.
+fn keyword(style: &StyleNode, name: &str, default: &str) -> String {
+    match style.get_value(name) {
+        Some(Keyword(keyword)) => keyword,
+        _ => default.to_string(),
+    }
+}
+
+/// Whether the sizes of a box (`width`, `min-height` and so on) include its padding and border.
+fn is_border_box(style: &StyleNode) -> bool {
+    matches!(style.get_value("box-sizing"), Some(Keyword(sizing)) if sizing == "border-box")
+}
+
+/// The size of the content box for a size that includes `sizing` px of padding and border.
+fn content_size(size: Value, sizing: f32) -> Value {
+    match size {
+        Length(size, Px) => Length((size - sizing).max(0.0), Px),
+        size => size,
+    }
+}
+
+/// Limit a size to `max`, then to `min`, so that `min` wins if they conflict.
+fn clamp_size(size: f32, min: f32, max: Option<f32>) -> f32 {
+    max.map_or(size, |max| size.min(max)).max(min)
+}
+
 fn sum<I>(iter: I) -> f32 where I: Iterator<Item=f32> {
     iter.fold(0., |a, b| a + b)
 }
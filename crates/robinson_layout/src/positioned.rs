@@ -0,0 +1,235 @@
+//! Positioned layout: boxes offset from their normal flow position, and boxes taken out of the
+//! flow and placed against a containing block.
+//!
+//! https://www.w3.org/TR/CSS2/visuren.html#positioning-scheme
+
+use std::sync::Arc;
+
+use robinson_css::Unit::Px;
+use robinson_css::Value::{self, Keyword, Length};
+use robinson_style::Position;
+
+use crate::{clamp_size, BoxType, Dimensions, FlowPosition, LayoutBox, LengthContext, Rect, RenderBox};
+
+impl LayoutBox {
+    pub(crate) fn position(&self) -> Position {
+        match &self.box_type {
+            BoxType::BlockNode(node) => node.position(),
+            _ => Position::Static,
+        }
+    }
+
+    /// Lay out the absolutely positioned descendants that `containing_block` is the containing
+    /// block of.
+    ///
+    /// For `Position::Fixed` those are the fixed boxes anywhere in the tree, and the containing
+    /// block is the viewport. Otherwise they are the absolutely positioned boxes that aren't inside
+    /// another positioned box, which is the containing block of its own.
+    pub(crate) fn layout_absolute_descendants(
+        &mut self,
+        containing_block: Rect,
+        viewport: Rect,
+        scheme: Position,
+    ) -> Vec<RenderBox> {
+        let mut boxes = Vec::new();
+        for child in &mut self.children {
+            let position = child.position();
+            if position == scheme {
+                boxes.push(child.layout_absolute(containing_block, viewport));
+            }
+            if scheme == Position::Fixed || position == Position::Static {
+                boxes.extend(child.layout_absolute_descendants(containing_block, viewport, scheme));
+            }
+        }
+        boxes
+    }
+
+    /// Record where the absolutely positioned boxes among this box's inline descendants would
+    /// have been in normal flow, given those positions in tree order.
+    pub(crate) fn set_static_positions(&mut self, positions: &mut impl Iterator<Item = (f32, f32)>) {
+        for child in &mut self.children {
+            match child.box_type {
+                BoxType::InlineNode(_) => child.set_static_positions(positions),
+                _ if child.position().is_absolute() => {
+                    child.static_position = positions.next().unwrap_or(child.static_position);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Lay out an absolutely positioned box against the padding box of its containing block.
+    ///
+    /// http://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-width
+    /// http://www.w3.org/TR/CSS2/visudet.html#abs-non-replaced-height
+    fn layout_absolute(&mut self, containing_block: Rect, viewport: Rect) -> RenderBox {
+        let style = Arc::clone(self.get_style_node());
+        let lengths = LengthContext {
+            viewport,
+            containing_width: containing_block.width,
+            containing_height: Some(containing_block.height),
+        };
+
+        // Offsets and margins are `None` when they are `auto`.
+        let auto = Keyword("auto".to_string());
+        let zero = Length(0.0, Px);
+        let px = |value: Value| match value {
+            Length(length, Px) => Some(length),
+            _ => None,
+        };
+        let horizontal = |name: &str| px(lengths.resolve(&style.lookup(name, &auto)));
+        let vertical = |name: &str| px(lengths.resolve_height(&style.lookup(name, &auto)));
+        let margin = |name: &str| px(lengths.resolve(&style.lookup(name, &zero)));
+        let edge = |name: &str| lengths.px(&style.lookup(name, &zero));
+
+        // Horizontal dimensions.
+        let (left, right) = (horizontal("left"), horizontal("right"));
+        let (margin_left, margin_right) = (margin("margin-left"), margin("margin-right"));
+        let d = &mut self.dimensions;
+        d.border.left = edge("border-left-width");
+        d.border.right = edge("border-right-width");
+        d.padding.left = edge("padding-left");
+        d.padding.right = edge("padding-right");
+        let edges = d.border.left + d.border.right + d.padding.left + d.padding.right;
+
+        let (width, min_width, max_width) = self.width_constraints(lengths);
+        let margins = margin_left.unwrap_or(0.0) + margin_right.unwrap_or(0.0);
+        let width = match (width, left, right) {
+            (Length(width, Px), _, _) => width,
+            // An `auto` width between two offsets fills the space between them.
+            (_, Some(left), Some(right)) => containing_block.width - left - right - margins - edges,
+            // Otherwise it shrinks to fit the contents.
+            _ => {
+                let available = containing_block.width - left.unwrap_or(0.0) - right.unwrap_or(0.0) - margins - edges;
+                self.max_content_width(lengths).min(available)
+            }
+        };
+        let width = clamp_size(width.max(0.0), min_width, max_width);
+
+        // With both offsets set, `auto` margins take up the remaining space, centering the box if
+        // they both are. If nothing is `auto`, `right` is ignored.
+        let (margin_left, margin_right) = match (left, right) {
+            (Some(left), Some(right)) => {
+                let remaining = containing_block.width - left - right - width - edges;
+                match (margin_left, margin_right) {
+                    (None, None) if remaining >= 0.0 => (remaining / 2.0, remaining / 2.0),
+                    (None, None) => (0.0, remaining),
+                    (None, Some(margin_right)) => (remaining - margin_right, margin_right),
+                    (Some(margin_left), margin_right) => (margin_left, margin_right.unwrap_or(remaining - margin_left)),
+                }
+            }
+            _ => (margin_left.unwrap_or(0.0), margin_right.unwrap_or(0.0)),
+        };
+        let d = &mut self.dimensions;
+        d.content.width = width;
+        d.margin.left = margin_left;
+        d.margin.right = margin_right;
+
+        // Vertical dimensions, with `auto` margins as zero for now.
+        let containing_dimensions = Dimensions { content: containing_block, ..Default::default() };
+        self.calculate_block_position(&containing_dimensions, lengths);
+        let (top, bottom) = (vertical("top"), vertical("bottom"));
+        let (margin_top, margin_bottom) = (margin("margin-top"), margin("margin-bottom"));
+        let d = self.dimensions;
+        let edges = d.border.top + d.border.bottom + d.padding.top + d.padding.bottom;
+
+        let (height, min_height, max_height) = self.height_constraints(lengths);
+        let height = height.map(|height| clamp_size(height, min_height, max_height));
+        let mut stretched_height = None;
+        if let (Some(top), Some(bottom)) = (top, bottom) {
+            let margins = margin_top.unwrap_or(0.0) + margin_bottom.unwrap_or(0.0);
+            match height {
+                // An `auto` height between two offsets fills the space between them.
+                None => {
+                    let height = containing_block.height - top - bottom - margins - edges;
+                    stretched_height = Some(clamp_size(height.max(0.0), min_height, max_height));
+                }
+                // Otherwise `auto` margins take up the remaining space.
+                Some(height) => {
+                    let remaining = containing_block.height - top - bottom - height - edges;
+                    let d = &mut self.dimensions;
+                    match (margin_top, margin_bottom) {
+                        (None, None) => (d.margin.top, d.margin.bottom) = (remaining / 2.0, remaining / 2.0),
+                        (None, Some(margin_bottom)) => d.margin.top = remaining - margin_bottom,
+                        (Some(margin_top), None) => d.margin.bottom = remaining - margin_top,
+                        (Some(_), Some(_)) => {}
+                    }
+                }
+            }
+        }
+
+        // Where the box goes can depend on its height, so it is laid out first and then moved.
+        let (render_box, _) = self.layout_block_contents(lengths, &mut FlowPosition::at(0.0), stretched_height);
+        let mut render_box = RenderBox::Block(render_box);
+
+        let margin_box = self.dimensions.margin_box();
+        let x = match (left, right) {
+            (Some(left), _) => containing_block.x + left,
+            (None, Some(right)) => containing_block.x + containing_block.width - right - margin_box.width,
+            (None, None) => self.static_position.0,
+        };
+        let y = match (top, bottom) {
+            (Some(top), _) => containing_block.y + top,
+            (None, Some(bottom)) => containing_block.y + containing_block.height - bottom - margin_box.height,
+            (None, None) => self.static_position.1,
+        };
+
+        let (dx, dy) = (x - margin_box.x, y - margin_box.y);
+        self.dimensions.content.translate(dx, dy);
+        render_box.translate(dx, dy);
+        render_box
+    }
+
+    /// How far a relatively positioned or sticky box is moved from where it was laid out.
+    ///
+    /// The page is never scrolled, so a sticky box only moves if it starts closer to an edge of
+    /// the viewport than its offset from that edge, and then not past the edges of its
+    /// containing block that are known while it is laid out.
+    pub(crate) fn relative_offset(&self, containing_block: &Dimensions, lengths: LengthContext) -> (f32, f32) {
+        let style = self.get_style_node();
+        let auto = Keyword("auto".to_string());
+        let px = |value: Value| match value {
+            Length(length, Px) => Some(length),
+            _ => None,
+        };
+        let left = px(lengths.resolve(&style.lookup("left", &auto)));
+        let right = px(lengths.resolve(&style.lookup("right", &auto)));
+        let top = px(lengths.resolve_height(&style.lookup("top", &auto)));
+        let bottom = px(lengths.resolve_height(&style.lookup("bottom", &auto)));
+
+        match self.position() {
+            // `left` wins over `right`, and `top` over `bottom`.
+            Position::Relative => (
+                left.or(right.map(|right| -right)).unwrap_or(0.0),
+                top.or(bottom.map(|bottom| -bottom)).unwrap_or(0.0),
+            ),
+            Position::Sticky => {
+                let viewport = lengths.viewport;
+                let border_box = self.dimensions.border_box();
+                let margin_box = self.dimensions.margin_box();
+                let content = containing_block.content;
+
+                let dx = match (left, right) {
+                    (Some(left), _) => (viewport.x + left - border_box.x).max(0.0),
+                    (None, Some(right)) => {
+                        (viewport.x + viewport.width - right - (border_box.x + border_box.width)).min(0.0)
+                    }
+                    (None, None) => 0.0,
+                };
+                let dx = dx
+                    .min(content.x + content.width - (margin_box.x + margin_box.width))
+                    .max(content.x - margin_box.x);
+
+                let dy = match (top, bottom) {
+                    (Some(top), _) => (viewport.y + top - border_box.y).max(0.0),
+                    (None, Some(bottom)) => {
+                        (viewport.y + viewport.height - bottom - (border_box.y + border_box.height)).min(0.0)
+                    }
+                    (None, None) => 0.0,
+                };
+                (dx, dy.max(content.y - margin_box.y))
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
@@ -1,9 +1,10 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
 use robinson_css::Color;
-use robinson_style::StyleNode;
+use robinson_font::{Font, ShapedGlyph};
+use robinson_style::{Float, Overflow, Position, StyleNode};
 
-use crate::{build_layout_tree, Dimensions};
+use crate::{build_layout_tree, Dimensions, FlowPosition, LengthContext, Rect};
 
 #[derive(Debug)]
 pub struct RenderTree {
@@ -13,30 +14,166 @@ pub struct RenderTree {
 #[derive(Debug)]
 pub enum RenderBox {
     Block(RenderBlockBox),
-    Inline,
-    Anonymous,
+    Anonymous(RenderAnonymousBox),
 }
 
 #[derive(Debug)]
 pub struct RenderBlockBox {
     pub dimensions: Dimensions,
+    /// The positioning scheme that placed the box, which decides the order boxes are painted in.
+    pub position: Position,
+    pub float: Float,
+    /// The stack level of the stacking context the box is the root of, or `None` if it isn't
+    /// one.
+    pub z_index: Option<i32>,
+    /// The position of the box's element in tree order, which positioned boxes in the same
+    /// stacking context are painted in.
+    pub tree_order: usize,
+    pub overflow: Overflow,
 
     pub color: Option<Color>,
     pub background_color: Option<Color>,
-    pub border_color: Option<Color>,
+    pub border_colors: BorderColors,
     
     pub children: Vec<RenderBox>,
 }
 
+/// The color of each side of a box's border.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BorderColors {
+    pub top: Option<Color>,
+    pub right: Option<Color>,
+    pub bottom: Option<Color>,
+    pub left: Option<Color>,
+}
+
+/// A block containing only inline content, laid out as a stack of line boxes.
+#[derive(Debug)]
+pub struct RenderAnonymousBox {
+    pub dimensions: Dimensions,
+
+    pub lines: Vec<LineBox>,
+}
+
+#[derive(Debug)]
+pub struct LineBox {
+    pub rect: Rect,
+    /// Vertical position of the line's baseline.
+    pub baseline: f32,
+
+    /// Fragments in paint order.
+    pub fragments: Vec<InlineFragment>,
+}
+
+#[derive(Debug)]
+pub enum InlineFragment {
+    Text(TextFragment),
+    Box(InlineBoxFragment),
+}
+
+/// A run of text with a single style, positioned on a line.
+#[derive(Debug)]
+pub struct TextFragment {
+    pub rect: Rect,
+    pub baseline: f32,
+
+    pub text: String,
+    /// Shaped glyphs, positioned relative to the left edge and the baseline.
+    pub glyphs: Vec<ShapedGlyph>,
+    pub font: Arc<Font>,
+    pub font_size: f32,
+    pub color: Option<Color>,
+}
+
+/// The part of an inline element's box that falls on one line.
+#[derive(Debug)]
+pub struct InlineBoxFragment {
+    pub dimensions: Dimensions,
+
+    pub background_color: Option<Color>,
+    pub border_colors: BorderColors,
+}
+
+impl RenderBox {
+    /// Move the box and everything in it.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        match self {
+            RenderBox::Block(block) => block.translate(dx, dy),
+            RenderBox::Anonymous(anonymous) => anonymous.translate(dx, dy),
+        }
+    }
+}
+
+impl RenderBlockBox {
+    /// Whether the box is positioned, and so painted in a layer of its own above the boxes in
+    /// normal flow.
+    pub fn is_positioned(&self) -> bool {
+        self.position != Position::Static
+    }
+
+    /// The rectangle the box's contents are clipped to, if they are.
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.overflow.clips().then(|| self.dimensions.padding_box())
+    }
+
+    /// Whether the box floats without being positioned.
+    pub fn is_float(&self) -> bool {
+        self.float != Float::None && !self.is_positioned()
+    }
+
+    /// Move the box and everything in it.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.dimensions.content.translate(dx, dy);
+        for child in &mut self.children {
+            child.translate(dx, dy);
+        }
+    }
+}
+
+impl RenderAnonymousBox {
+    /// Move the box and everything in it.
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.dimensions.content.translate(dx, dy);
+        for line in &mut self.lines {
+            line.rect.translate(dx, dy);
+            line.baseline += dy;
+            for fragment in &mut line.fragments {
+                match fragment {
+                    InlineFragment::Text(text) => {
+                        text.rect.translate(dx, dy);
+                        text.baseline += dy;
+                    }
+                    InlineFragment::Box(inline_box) => inline_box.dimensions.content.translate(dx, dy),
+                }
+            }
+        }
+    }
+}
+
 impl RenderTree {
-    pub fn new(node: &Rc<StyleNode>, containing_block: &mut Dimensions) -> Self {
-        let og_height = containing_block.content.height;
-        containing_block.content.height = 0.0;
+    pub fn new(node: &Arc<StyleNode>, containing_block: &Dimensions) -> Self {
+        // The initial containing block has the dimensions of the viewport.
+        let lengths = LengthContext {
+            viewport: containing_block.content,
+            containing_width: containing_block.content.width,
+            containing_height: Some(containing_block.content.height),
+        };
 
-        let mut bbox = build_layout_tree(node);
-        let root = bbox.layout(containing_block);
+        // The margins of the root element don't collapse with those of its children.
+        let mut bbox = build_layout_tree(node, &mut 0, false);
+        bbox.new_formatting_context = true;
+        let mut position = FlowPosition::at(containing_block.content.y);
+        let (mut root, _) = bbox.layout(containing_block, lengths, &mut position);
 
-        containing_block.content.height = og_height;
+        // Absolutely positioned boxes that no positioned ancestor contains are placed against the
+        // initial containing block, and fixed boxes against the viewport, which are the same.
+        if let RenderBox::Block(block) = &mut root {
+            let viewport = containing_block.content;
+            if bbox.position() == Position::Static {
+                block.children.extend(bbox.layout_absolute_descendants(viewport, viewport, Position::Absolute));
+            }
+            block.children.extend(bbox.layout_absolute_descendants(viewport, viewport, Position::Fixed));
+        }
 
         Self {
             root,
@@ -0,0 +1,1032 @@
+//! Table layout: the cells of a table are placed in a grid of rows and columns, which are sized to
+//! fit them.
+//!
+//! https://www.w3.org/TR/CSS2/tables.html
+//!
+//! Columns only give their widths, and aren't painted. Cells are aligned to the top, middle or
+//! bottom of their rows rather than on baselines, and captions are inside the table's box, above
+//! or below the grid.
+
+use std::cell::{OnceCell, RefCell};
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use robinson_css::Unit::{self, Px};
+use robinson_css::Color;
+use robinson_css::Value::{self, Keyword, Length, List};
+use robinson_style::{Display, StyleNode};
+
+use crate::intrinsic::IntrinsicSize;
+use crate::{
+    establishes_formatting_context, is_in_flow, keyword, sum, BorderColors, BoxType, Dimensions, EdgeSizes, FlowPosition,
+    LayoutBox, LengthContext, Rect, RenderBlockBox, RenderBox,
+};
+use SpecifiedWidth::{Fixed, Percent};
+
+/// The most columns a table can have. Cells and columns past the last one are put in it, so that
+/// a huge `colspan` or `span` can't exhaust memory.
+const MAX_COLUMNS: usize = 1000;
+
+/// The part of a table that a box is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TablePart {
+    Table,
+    RowGroup,
+    HeaderGroup,
+    FooterGroup,
+    Row,
+    ColumnGroup,
+    Column,
+    Cell,
+    Caption,
+}
+
+impl TablePart {
+    fn is_row_group(self) -> bool {
+        matches!(self, TablePart::RowGroup | TablePart::HeaderGroup | TablePart::FooterGroup)
+    }
+}
+
+/// A width given to a column or a cell.
+#[derive(Clone, Copy, Debug)]
+enum SpecifiedWidth {
+    /// A length in px.
+    Fixed(f32),
+    /// A percentage of the table.
+    Percent(f32),
+}
+
+/// The grid of a table: its rows in the order they are laid out, and the cells placed in them.
+struct Table {
+    /// Where each row is: the index of its row group among the table's children, or `None` if it
+    /// is a child of the table itself, and its index among the children of that.
+    rows: Vec<(Option<usize>, usize)>,
+    /// The row groups, and the runs of rows that are children of the table itself, with the
+    /// rows in them. Cells can't span rows outside their section.
+    sections: Vec<(Option<usize>, Range<usize>)>,
+    cells: Vec<Cell>,
+    /// The number of columns.
+    columns: usize,
+    /// The widths of the columns given by column boxes.
+    column_widths: Vec<Option<SpecifiedWidth>>,
+    /// The horizontal and vertical space between the cells, and between them and the table's
+    /// padding.
+    spacing: (f32, f32),
+    /// The borders of the cells if the borders collapse, in the order of `cells`.
+    collapsed_borders: Option<Vec<CellBorder>>,
+    /// How wide the columns can be with the automatic table layout algorithm, found when first
+    /// needed.
+    auto_column_widths: OnceCell<Vec<ColumnWidth>>,
+}
+
+/// The grid of a table, and the lengths it was placed with. Sizing and laying out a table needs
+/// its grid several times, and sizing its columns means sizing every cell, so it is reused for as
+/// long as the lengths stay the same rather than found again each time, which would take
+/// exponential time in the depth of nested tables.
+#[derive(Default)]
+pub(crate) struct TableCache(RefCell<Option<(LengthContext, Rc<Table>)>>);
+
+/// A cell placed in the grid of a table.
+struct Cell {
+    /// The row the cell is in, and its index among the children of the row.
+    row: usize,
+    index: usize,
+    rows: Range<usize>,
+    columns: Range<usize>,
+}
+
+/// The border a cell draws in the collapsing border model. Each border between two cells is
+/// drawn by the cell after it, so a cell only draws its right and bottom borders where there is
+/// no cell beyond them.
+#[derive(Clone, Copy, Debug, Default)]
+struct CellBorder {
+    widths: EdgeSizes,
+    colors: BorderColors,
+}
+
+/// One side of the border of a box, as a candidate for a collapsed border.
+#[derive(Clone, Copy, Debug, Default)]
+struct BorderSide {
+    width: f32,
+    /// How the border's style ranks: `double` is the highest and `none` the lowest.
+    rank: usize,
+    hidden: bool,
+    color: Option<Color>,
+}
+
+/// How wide a column can be, from the cells in it.
+#[derive(Clone, Copy, Debug, Default)]
+struct ColumnWidth {
+    min: f32,
+    max: f32,
+    width: Option<SpecifiedWidth>,
+}
+
+impl LayoutBox {
+    /// The part of a table that the box is, if any. Rows, cells and the other internal parts of
+    /// tables are blocks if they are floated or absolutely positioned.
+    fn table_part(&self) -> Option<TablePart> {
+        let BoxType::BlockNode(node) = &self.box_type else {
+            return None;
+        };
+        Some(match node.display() {
+            Display::Table | Display::InlineTable => TablePart::Table,
+            _ if !is_in_flow(node) => return None,
+            Display::TableRowGroup => TablePart::RowGroup,
+            Display::TableHeaderGroup => TablePart::HeaderGroup,
+            Display::TableFooterGroup => TablePart::FooterGroup,
+            Display::TableRow => TablePart::Row,
+            Display::TableColumnGroup => TablePart::ColumnGroup,
+            Display::TableColumn => TablePart::Column,
+            Display::TableCell => TablePart::Cell,
+            Display::TableCaption => TablePart::Caption,
+            _ => return None,
+        })
+    }
+
+    pub(crate) fn is_table(&self) -> bool {
+        self.table_part() == Some(TablePart::Table)
+    }
+
+    /// Whether the box's children can only be certain parts of a table.
+    pub(crate) fn contains_table_parts(&self) -> bool {
+        self.table_part()
+            .is_some_and(|part| !matches!(part, TablePart::Cell | TablePart::Caption))
+    }
+
+    /// Whether the box is a table whose cells share their borders.
+    pub(crate) fn collapses_borders(&self) -> bool {
+        keyword(self.get_style_node(), "border-collapse", "separate") == "collapse"
+    }
+
+    /// Generate the anonymous boxes that a table needs around the box's children: rows around
+    /// anything else in a table or row group, cells around anything else in a row, and tables
+    /// around the parts of tables that aren't in one. Columns only contain columns.
+    ///
+    /// https://www.w3.org/TR/CSS2/tables.html#anonymous-boxes
+    pub(crate) fn wrap_table_parts(&mut self) {
+        use TablePart::*;
+
+        let belongs: fn(Option<TablePart>) -> bool;
+        let wrapper = match self.table_part() {
+            Some(Table) => {
+                belongs = |part| part.is_some_and(|part| part != Table && part != Cell);
+                "table-row"
+            }
+            Some(RowGroup | HeaderGroup | FooterGroup) => {
+                belongs = |part| part == Some(Row);
+                "table-row"
+            }
+            Some(Row) => {
+                belongs = |part| part == Some(Cell);
+                "table-cell"
+            }
+            Some(ColumnGroup) => {
+                self.children.retain(|child| child.table_part() == Some(Column));
+                return;
+            }
+            Some(Column) => {
+                self.children.clear();
+                return;
+            }
+            _ => {
+                belongs = |part| part.is_none() || part == Some(Table);
+                "table"
+            }
+        };
+        if self.children.iter().all(|child| belongs(child.table_part())) {
+            return;
+        }
+
+        // Each run of children that don't belong shares a wrapper, which is in turn completed.
+        let style = Arc::clone(self.get_style_node());
+        let mut children: Vec<LayoutBox> = Vec::new();
+        let mut wrapping = false;
+        for child in std::mem::take(&mut self.children) {
+            if belongs(child.table_part()) {
+                if wrapping {
+                    children.last_mut().unwrap().wrap_table_parts();
+                    wrapping = false;
+                }
+                children.push(child);
+                continue;
+            }
+            if !wrapping {
+                let style = style.anonymous(wrapper);
+                let mut wrapper = LayoutBox::new(BoxType::BlockNode(Arc::clone(&style)));
+                wrapper.new_formatting_context = establishes_formatting_context(&style);
+                wrapper.tree_order = child.tree_order;
+                children.push(wrapper);
+                wrapping = true;
+            }
+            children.last_mut().unwrap().children.push(child);
+        }
+        if wrapping {
+            children.last_mut().unwrap().wrap_table_parts();
+        }
+        self.children = children;
+    }
+
+    /// The used width of a table's content box: at least as wide as its columns can be, and if
+    /// `width` is `auto`, as wide as they would like to be within the space available.
+    pub(crate) fn table_width(&mut self, containing_block: &Dimensions, lengths: LengthContext, width: Value) -> Value {
+        let table = self.table(lengths);
+        let (min_width, max_width) = match self.has_fixed_layout() {
+            true => {
+                let width = sum(self.fixed_column_widths(&table, lengths, 0.0).into_iter()) + table.spacing_width();
+                (width, width)
+            }
+            false => self.table_widths(&table, lengths),
+        };
+        let edges = self.collapsed_edges(lengths);
+        let width = match width {
+            Length(width, Px) => width,
+            _ => {
+                self.solve_block_width(containing_block, lengths, Keyword("auto".to_string()));
+                (self.dimensions.content.width + edges).min(max_width) - edges
+            }
+        };
+        Length(width.max(min_width - edges), Px)
+    }
+
+    /// Where the borders collapse, a table's border is drawn by its cells instead, and it has no
+    /// padding, so its border and padding become part of its content box.
+    pub(crate) fn collapse_table_edges(&mut self) {
+        let d = &mut self.dimensions;
+        d.content.x -= d.padding.left + d.border.left;
+        d.content.width += d.padding.left + d.padding.right + d.border.left + d.border.right;
+        d.padding = EdgeSizes::default();
+        d.border = EdgeSizes::default();
+    }
+
+    /// The horizontal border and padding of a table that become part of its content box, which
+    /// they do where the borders collapse.
+    fn collapsed_edges(&self, lengths: LengthContext) -> f32 {
+        if !self.collapses_borders() {
+            return 0.0;
+        }
+        let style = self.get_style_node();
+        let zero = Length(0.0, Px);
+        sum(["border-left-width", "border-right-width", "padding-left", "padding-right"]
+            .iter()
+            .map(|name| lengths.px(&style.lookup(name, &zero))))
+    }
+
+    /// The min-content or max-content width of a table's content box, which doesn't include the
+    /// border and padding that collapsing borders make part of it.
+    pub(crate) fn table_intrinsic_width(&self, size: IntrinsicSize, lengths: LengthContext) -> f32 {
+        let (min_width, max_width) = self.table_widths(&self.table(lengths), lengths);
+        let width = match size {
+            IntrinsicSize::MinContent => min_width,
+            IntrinsicSize::MaxContent => max_width,
+        };
+        (width - self.collapsed_edges(lengths)).max(0.0)
+    }
+
+    /// Lay out the captions, rows and cells of a table whose width is known, starting at the top
+    /// of its content box at `flow`, and move `flow` to the bottom of the content.
+    ///
+    /// Returns the captions above the grid, the row groups and rows in the order they are laid
+    /// out in, and then the captions below.
+    pub(crate) fn layout_table_children(&mut self, lengths: LengthContext, flow: &mut FlowPosition) -> Vec<RenderBox> {
+        let table = self.table(lengths);
+        let d = self.dimensions;
+        let (spacing_x, spacing_y) = table.spacing;
+        let mut children = Vec::new();
+
+        // Captions are laid out as blocks above or below the grid.
+        let captions = |table: &LayoutBox, side: &str| -> Vec<usize> {
+            (0..table.children.len())
+                .filter(|&index| {
+                    let caption = &table.children[index];
+                    caption.table_part() == Some(TablePart::Caption)
+                        && keyword(caption.get_style_node(), "caption-side", "top") == side
+                })
+                .collect()
+        };
+        for index in captions(self, "top") {
+            children.push(self.children[index].layout(&d, lengths, flow).0);
+        }
+        let top = flow.resolve();
+
+        // Size the columns to fill the table.
+        let available = (d.content.width - table.spacing_width()).max(0.0);
+        let widths = match self.has_fixed_layout() {
+            true => self.fixed_column_widths(&table, lengths, available),
+            false => distribute_widths(self.column_widths(&table, lengths), available),
+        };
+        let mut x = spacing_x;
+        let column_positions: Vec<f32> = widths
+            .iter()
+            .map(|width| {
+                let position = x;
+                x += width + spacing_x;
+                position
+            })
+            .collect();
+        let span = |positions: &[f32], sizes: &[f32], range: &Range<usize>| match range.is_empty() {
+            true => 0.0,
+            false => positions[range.end - 1] + sizes[range.end - 1] - positions[range.start],
+        };
+
+        // Lay out each cell in its columns to find its height.
+        let cell_lengths = LengthContext { containing_height: None, ..lengths };
+        let mut cell_boxes = Vec::new();
+        for (index, cell) in table.cells.iter().enumerate() {
+            let width = span(&column_positions, &widths, &cell.columns);
+            let border = table.collapsed_borders.as_ref().map(|borders| borders[index]);
+            cell_boxes.push(self.table_cell_mut(&table, cell).layout_table_cell(cell_lengths, width, border));
+        }
+
+        // Rows are as tall as their `height` and their cells, and cells that span rows grow the
+        // rows they span equally.
+        let mut heights: Vec<f32> = table
+            .rows
+            .iter()
+            .map(|&row| self.table_row(row).height_constraints(lengths).0.unwrap_or(0.0))
+            .collect();
+        let mut spanning: Vec<_> = table.cells.iter().zip(&cell_boxes).collect();
+        spanning.sort_by_key(|(cell, _)| cell.rows.len());
+        for (cell, (render_box, _)) in spanning {
+            let height = render_box.dimensions.border_box().height;
+            let spanned = sum(heights[cell.rows.clone()].iter().copied()) + spacing_y * cell.rows.len().saturating_sub(1) as f32;
+            if height > spanned && !cell.rows.is_empty() {
+                let extra = (height - spanned) / cell.rows.len() as f32;
+                heights[cell.rows.clone()].iter_mut().for_each(|row| *row += extra);
+            }
+        }
+
+        // A table taller than its rows gives them the extra height equally.
+        let grid_height = |heights: &[f32]| match heights.is_empty() {
+            true => 0.0,
+            false => sum(heights.iter().copied()) + spacing_y * (heights.len() + 1) as f32,
+        };
+        if let Some(height) = lengths.containing_height {
+            let extra = height - (top - d.content.y) - grid_height(&heights);
+            if extra > 0.0 && !heights.is_empty() {
+                let extra = extra / heights.len() as f32;
+                heights.iter_mut().for_each(|row| *row += extra);
+            }
+        }
+        let mut y = top + spacing_y;
+        let row_positions: Vec<f32> = heights
+            .iter()
+            .map(|height| {
+                let position = y;
+                y += height + spacing_y;
+                position
+            })
+            .collect();
+
+        // Stretch each cell over its rows, with its content aligned by `vertical-align`, and
+        // move it into place.
+        let mut rows: Vec<Vec<RenderBox>> = table.rows.iter().map(|_| Vec::new()).collect();
+        for (cell, (mut render_box, content_height)) in table.cells.iter().zip(cell_boxes) {
+            let area = Rect {
+                x: d.content.x + column_positions.get(cell.columns.start).copied().unwrap_or(0.0),
+                y: row_positions[cell.row],
+                width: span(&column_positions, &widths, &cell.columns),
+                height: span(&row_positions, &heights, &cell.rows),
+            };
+            let child = self.table_cell_mut(&table, cell);
+            let extra = (area.height - child.dimensions.border_box().height).max(0.0);
+            let free = child.dimensions.content.height + extra - content_height;
+            let offset = match &*keyword(child.get_style_node(), "vertical-align", "baseline") {
+                "middle" => free / 2.0,
+                "bottom" => free,
+                _ => 0.0,
+            };
+            render_box.children.iter_mut().for_each(|content| content.translate(0.0, offset));
+            child.dimensions.content.height += extra;
+            render_box.dimensions.content.height += extra;
+
+            // A relatively positioned cell is also moved from where it was placed.
+            let containing_block = Dimensions { content: area, ..Default::default() };
+            let (offset_x, offset_y) = child.relative_offset(&containing_block, cell_lengths);
+            let (dx, dy) = (area.x + offset_x, area.y + offset_y);
+            child.dimensions.content.translate(dx, dy);
+            render_box.translate(dx, dy);
+            rows[cell.row].push(RenderBox::Block(render_box));
+        }
+
+        // Rows and row groups stretch across the columns, and are painted beneath their cells.
+        let columns = 0..widths.len();
+        let x = d.content.x + column_positions.first().copied().unwrap_or(0.0);
+        let width = span(&column_positions, &widths, &columns);
+        let mut rows = rows.into_iter();
+        for (group, range) in &table.sections {
+            let mut row_boxes = Vec::new();
+            for row in range.clone() {
+                let content = Rect { x, y: row_positions[row], width, height: heights[row] };
+                let row_box = self.table_row_mut(table.rows[row]);
+                row_box.dimensions = Dimensions { content, ..Default::default() };
+                row_boxes.push(RenderBox::Block(row_box.render_block(rows.next().unwrap_or_default())));
+            }
+            match group {
+                Some(group) => {
+                    let y = row_positions.get(range.start).copied().unwrap_or(top);
+                    let height = span(&row_positions, &heights, range);
+                    let group = &mut self.children[*group];
+                    group.dimensions = Dimensions { content: Rect { x, y, width, height }, ..Default::default() };
+                    children.push(RenderBox::Block(group.render_block(row_boxes)));
+                }
+                None => children.extend(row_boxes),
+            }
+        }
+
+        flow.move_to(top + grid_height(&heights));
+        for index in captions(self, "bottom") {
+            children.push(self.children[index].layout(&d, lengths, flow).0);
+        }
+        children
+    }
+
+    /// Lay out a table cell `width` px wide, with the top left corner of its border box at the
+    /// origin. Cells have no margins, and where the borders collapse, `border` replaces their own.
+    ///
+    /// A cell's `height` is the least it can be, so it also grows to fit its content, whose height
+    /// is returned too.
+    fn layout_table_cell(&mut self, lengths: LengthContext, width: f32, border: Option<CellBorder>) -> (RenderBlockBox, f32) {
+        let style = Arc::clone(self.get_style_node());
+        let zero = Length(0.0, Px);
+        let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
+
+        let d = &mut self.dimensions;
+        d.margin = EdgeSizes::default();
+        d.padding = EdgeSizes {
+            left: lookup("padding-left"),
+            right: lookup("padding-right"),
+            top: lookup("padding-top"),
+            bottom: lookup("padding-bottom"),
+        };
+        d.border = match border {
+            Some(border) => border.widths,
+            None => EdgeSizes {
+                left: lookup("border-left-width"),
+                right: lookup("border-right-width"),
+                top: lookup("border-top-width"),
+                bottom: lookup("border-bottom-width"),
+            },
+        };
+        d.content.x = d.border.left + d.padding.left;
+        d.content.width = (width - d.border.left - d.border.right - d.padding.left - d.padding.right).max(0.0);
+
+        let (mut render_box, _) = self.layout_block_contents(lengths, &mut FlowPosition::at(0.0), None);
+        if let Some(border) = border {
+            render_box.border_colors = border.colors;
+        }
+
+        let content = render_box.dimensions.content;
+        let content_height = render_box
+            .children
+            .iter()
+            .map(|child| {
+                let margin_box = match child {
+                    RenderBox::Block(block) => block.dimensions.margin_box(),
+                    RenderBox::Anonymous(anonymous) => anonymous.dimensions.margin_box(),
+                };
+                margin_box.y + margin_box.height - content.y
+            })
+            .fold(0.0, f32::max);
+        if content_height > content.height {
+            self.dimensions.content.height = content_height;
+            render_box.dimensions.content.height = content_height;
+        }
+        (render_box, content_height)
+    }
+
+    /// The grid of a table, placed with `lengths` unless it already was.
+    fn table(&self, lengths: LengthContext) -> Rc<Table> {
+        if let Some((cached_lengths, table)) = &*self.table_cache.0.borrow() {
+            if *cached_lengths == lengths {
+                return Rc::clone(table);
+            }
+        }
+        let table = Rc::new(self.place_cells(lengths));
+        *self.table_cache.0.borrow_mut() = Some((lengths, Rc::clone(&table)));
+        table
+    }
+
+    /// Place the cells of a table in its grid.
+    fn place_cells(&self, lengths: LengthContext) -> Table {
+        // Header groups are laid out first and footer groups last, wherever they are. Rows that
+        // are children of the table itself are grouped with the rows next to them.
+        let mut sections: Vec<(Option<usize>, Vec<usize>)> = Vec::new();
+        for (index, child) in self.children.iter().enumerate() {
+            match child.table_part() {
+                Some(TablePart::Row) => match sections.last_mut() {
+                    Some((None, rows)) => rows.push(index),
+                    _ => sections.push((None, vec![index])),
+                },
+                Some(part) if part.is_row_group() => {
+                    let rows = (0..child.children.len()).collect();
+                    sections.push((Some(index), rows));
+                }
+                _ => {}
+            }
+        }
+        sections.sort_by_key(|(group, _)| match group.and_then(|group| self.children[group].table_part()) {
+            Some(TablePart::HeaderGroup) => 0,
+            Some(TablePart::FooterGroup) => 2,
+            _ => 1,
+        });
+
+        // Each cell goes in the first column of its row that no cell above spans into.
+        let mut table = Table {
+            rows: Vec::new(),
+            sections: Vec::new(),
+            cells: Vec::new(),
+            columns: 0,
+            column_widths: Vec::new(),
+            spacing: (0.0, 0.0),
+            collapsed_borders: None,
+            auto_column_widths: OnceCell::new(),
+        };
+        let mut taken: Vec<Vec<bool>> = Vec::new();
+        for (group, indices) in sections {
+            let start = table.rows.len();
+            let end = start + indices.len();
+            taken.resize(end, Vec::new());
+            for index in indices {
+                let row = table.rows.len();
+                table.rows.push((group, index));
+                let mut column = 0;
+                for (index, cell) in self.table_row((group, index)).children.iter().enumerate() {
+                    while taken[row].get(column) == Some(&true) {
+                        column += 1;
+                    }
+                    // A `rowspan` of zero spans the rest of the section.
+                    let style = cell.get_style_node();
+                    let column_span = span_attribute(style, "colspan").unwrap_or(1).clamp(1, MAX_COLUMNS);
+                    let row_span = match span_attribute(style, "rowspan").unwrap_or(1) {
+                        0 => end - row,
+                        span => span.min(end - row),
+                    };
+                    let start = column.min(MAX_COLUMNS - 1);
+                    let columns = start..(start + column_span).min(MAX_COLUMNS);
+                    let cell = Cell { row, index, rows: row..row + row_span, columns };
+                    for taken in &mut taken[cell.rows.clone()] {
+                        if taken.len() < cell.columns.end {
+                            taken.resize(cell.columns.end, false);
+                        }
+                        taken[cell.columns.clone()].fill(true);
+                    }
+                    column = cell.columns.end;
+                    table.cells.push(cell);
+                }
+            }
+            table.sections.push((group, start..end));
+        }
+
+        // A column group without columns stands for `span` columns of its own width.
+        for child in &self.children {
+            let span = |column: &LayoutBox| span_attribute(column.get_style_node(), "span").unwrap_or(1).clamp(1, MAX_COLUMNS);
+            let group_width = specified_width(child, lengths);
+            let columns = match child.table_part() {
+                Some(TablePart::Column) => vec![(group_width, span(child))],
+                Some(TablePart::ColumnGroup) if child.children.is_empty() => vec![(group_width, span(child))],
+                Some(TablePart::ColumnGroup) => child
+                    .children
+                    .iter()
+                    .map(|column| (specified_width(column, lengths).or(group_width), span(column)))
+                    .collect(),
+                _ => Vec::new(),
+            };
+            for (width, span) in columns {
+                let span = span.min(MAX_COLUMNS - table.column_widths.len());
+                table.column_widths.extend(std::iter::repeat_n(width, span));
+            }
+        }
+        table.columns = table
+            .cells
+            .iter()
+            .map(|cell| cell.columns.end)
+            .chain([table.column_widths.len()])
+            .max()
+            .unwrap_or(0);
+
+        // Where the borders collapse, there is no space between the cells.
+        if self.collapses_borders() {
+            table.collapsed_borders = Some(self.collapsed_borders(&table));
+        } else {
+            table.spacing = border_spacing(self.get_style_node(), lengths);
+        }
+        table
+    }
+
+    /// Resolve the borders of the cells in the collapsing border model, where each border
+    /// between cells is the most eye-catching of the borders of the boxes on either side.
+    ///
+    /// https://www.w3.org/TR/CSS2/tables.html#collapsing-borders
+    fn collapsed_borders(&self, table: &Table) -> Vec<CellBorder> {
+        // The cell covering each slot of the grid.
+        let mut slots = vec![vec![None; table.columns]; table.rows.len()];
+        for (index, cell) in table.cells.iter().enumerate() {
+            for row in &mut slots[cell.rows.clone()] {
+                row[cell.columns.clone()].fill(Some(index));
+            }
+        }
+        let slot = |row: usize, column: usize| slots.get(row).and_then(|row| row.get(column).copied().flatten());
+        let cell_style = |index: usize| self.table_cell(table, &table.cells[index]).get_style_node();
+        let row_style = |row: usize| self.table_row(table.rows[row]).get_style_node();
+        // The row group of a row, and the rows in it.
+        let group = |row: usize| {
+            table.sections.iter().find(|(_, rows)| rows.contains(&row)).and_then(|(group, rows)| {
+                group.map(|group| (self.children[group].get_style_node(), rows.clone()))
+            })
+        };
+        let rows = table.rows.len();
+        let table_style = self.get_style_node();
+
+        table
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let (top, left) = (cell.rows.start, cell.columns.start);
+                let (bottom, right) = (cell.rows.end, cell.columns.end);
+                let style = cell_style(index);
+
+                // Each list of candidates is in order of precedence for borders that are
+                // otherwise equal: cells first, with those above and to the left before
+                // those below and to the right, then rows, row groups and the table.
+                let mut top_sides = Vec::new();
+                if top > 0 {
+                    top_sides.extend(slot(top - 1, left).map(|above| (cell_style(above), "bottom")));
+                }
+                top_sides.push((style, "top"));
+                if top > 0 {
+                    top_sides.push((row_style(top - 1), "bottom"));
+                }
+                top_sides.push((row_style(top), "top"));
+                top_sides.extend(group(top).filter(|(_, rows)| rows.start == top).map(|(group, _)| (group, "top")));
+                if top > 0 {
+                    top_sides.extend(group(top - 1).filter(|(_, rows)| rows.end == top).map(|(group, _)| (group, "bottom")));
+                }
+                if top == 0 {
+                    top_sides.push((table_style, "top"));
+                }
+
+                let mut left_sides = Vec::new();
+                if left > 0 {
+                    left_sides.extend(slot(top, left - 1).map(|before| (cell_style(before), "right")));
+                }
+                left_sides.push((style, "left"));
+                if left == 0 {
+                    left_sides.push((row_style(top), "left"));
+                    left_sides.extend(group(top).map(|(group, _)| (group, "left")));
+                    left_sides.push((table_style, "left"));
+                }
+
+                // The borders after the cell are drawn by the cells there, if there are any.
+                let mut right_sides = Vec::new();
+                if slot(top, right).is_none() {
+                    right_sides.push((style, "right"));
+                    if right == table.columns {
+                        right_sides.push((row_style(top), "right"));
+                        right_sides.extend(group(top).map(|(group, _)| (group, "right")));
+                        right_sides.push((table_style, "right"));
+                    }
+                }
+
+                let mut bottom_sides = Vec::new();
+                if slot(bottom, left).is_none() {
+                    bottom_sides.push((style, "bottom"));
+                    bottom_sides.push((row_style(bottom - 1), "bottom"));
+                    if bottom < rows {
+                        bottom_sides.push((row_style(bottom), "top"));
+                    }
+                    bottom_sides.extend(group(bottom - 1).filter(|(_, rows)| rows.end == bottom).map(|(group, _)| (group, "bottom")));
+                    if bottom == rows {
+                        bottom_sides.push((table_style, "bottom"));
+                    }
+                }
+
+                let [top, right, bottom, left] = [top_sides, right_sides, bottom_sides, left_sides]
+                    .map(|sides| collapse(sides.into_iter().map(|(style, side)| BorderSide::of(style, side))));
+                CellBorder {
+                    widths: EdgeSizes { top: top.width, right: right.width, bottom: bottom.width, left: left.width },
+                    colors: BorderColors { top: top.color, right: right.color, bottom: bottom.color, left: left.color },
+                }
+            })
+            .collect()
+    }
+
+    /// How wide each column of a table can be, from the cells in it, with the automatic table
+    /// layout algorithm.
+    fn column_widths<'a>(&self, table: &'a Table, lengths: LengthContext) -> &'a [ColumnWidth] {
+        table.auto_column_widths.get_or_init(|| self.find_column_widths(table, lengths))
+    }
+
+    /// Find how wide each column of a table can be.
+    ///
+    /// https://www.w3.org/TR/CSS2/tables.html#auto-table-layout
+    fn find_column_widths(&self, table: &Table, lengths: LengthContext) -> Vec<ColumnWidth> {
+        let mut columns = vec![ColumnWidth::default(); table.columns];
+        for (column, width) in columns.iter_mut().zip(&table.column_widths) {
+            column.width = *width;
+        }
+
+        // A cell is at least as wide as its `width`, which also replaces its max-content width.
+        let contributions: Vec<_> = table
+            .cells
+            .iter()
+            .enumerate()
+            .map(|(index, cell)| {
+                let child = self.table_cell(table, cell);
+                let edges = table.cell_edges(child, index, lengths);
+                let width = specified_width(child, lengths);
+                let mut min = child.intrinsic_width(IntrinsicSize::MinContent, lengths) + edges;
+                let max = match width {
+                    Some(Fixed(width)) => {
+                        min = min.max(width + edges);
+                        min
+                    }
+                    _ => min.max(child.intrinsic_width(IntrinsicSize::MaxContent, lengths) + edges),
+                };
+                (cell, ColumnWidth { min, max, width })
+            })
+            .collect();
+
+        // Cells in a single column size it, where a percentage outranks a length.
+        for (cell, contribution) in contributions.iter().filter(|(cell, _)| cell.columns.len() == 1) {
+            let column = &mut columns[cell.columns.start];
+            column.min = column.min.max(contribution.min);
+            column.max = column.max.max(contribution.max);
+            column.width = match (column.width, contribution.width) {
+                (Some(Percent(a)), Some(Percent(b))) => Some(Percent(a.max(b))),
+                (Some(Fixed(a)), Some(Fixed(b))) => Some(Fixed(a.max(b))),
+                (Some(Percent(percent)), _) | (_, Some(Percent(percent))) => Some(Percent(percent)),
+                (width, None) | (None, width) => width,
+            };
+        }
+        for column in &mut columns {
+            column.max = match column.width {
+                Some(Fixed(width)) => width.max(column.min),
+                _ => column.max.max(column.min),
+            };
+        }
+
+        // Cells that span columns widen them if they need to, the narrowest spans first.
+        let mut spanning: Vec<_> = contributions.iter().filter(|(cell, _)| cell.columns.len() > 1).collect();
+        spanning.sort_by_key(|(cell, _)| cell.columns.len());
+        for (cell, contribution) in spanning {
+            let spacing = table.spacing.0 * (cell.columns.len() - 1) as f32;
+            let columns = &mut columns[cell.columns.clone()];
+            widen(columns, contribution.min - spacing, IntrinsicSize::MinContent);
+            widen(columns, contribution.max - spacing, IntrinsicSize::MaxContent);
+        }
+        columns
+    }
+
+    /// The min-content and max-content widths of a table's content box, which is at least as wide
+    /// as its captions.
+    fn table_widths(&self, table: &Table, lengths: LengthContext) -> (f32, f32) {
+        let columns = self.column_widths(table, lengths);
+        let captions = self
+            .children
+            .iter()
+            .filter(|child| child.table_part() == Some(TablePart::Caption))
+            .map(|caption| caption.outer_intrinsic_width(IntrinsicSize::MinContent, lengths))
+            .fold(0.0, f32::max);
+        let spacing = table.spacing_width();
+        let min_width = (sum(columns.iter().map(|column| column.min)) + spacing).max(captions);
+        let max_width = (sum(columns.iter().map(|column| column.max)) + spacing).max(min_width);
+        (min_width, max_width)
+    }
+
+    /// Whether a table's columns are sized with the fixed table layout algorithm, which needs
+    /// the table to have a `width`.
+    fn has_fixed_layout(&self) -> bool {
+        let style = self.get_style_node();
+        keyword(style, "table-layout", "auto") == "fixed" && !matches!(style.get_value("width"), None | Some(Keyword(_)))
+    }
+
+    /// The widths of the columns of a table `width` px wide, not counting the spacing, with the
+    /// fixed table layout algorithm. Only the columns and the cells in the first row give the
+    /// columns widths, and the columns without one share what is left.
+    ///
+    /// https://www.w3.org/TR/CSS2/tables.html#fixed-table-layout
+    fn fixed_column_widths(&self, table: &Table, lengths: LengthContext, width: f32) -> Vec<f32> {
+        let resolve = |specified: SpecifiedWidth, edges: f32| match specified {
+            Fixed(specified) => specified + edges,
+            Percent(percent) => percent / 100.0 * width,
+        };
+        let mut widths: Vec<Option<f32>> = table.column_widths.iter().map(|column| column.map(|column| resolve(column, 0.0))).collect();
+        widths.resize(table.columns, None);
+
+        // A cell that spans columns shares its width among them.
+        for (index, cell) in table.cells.iter().enumerate().filter(|(_, cell)| cell.row == 0) {
+            let child = self.table_cell(table, cell);
+            let Some(specified) = specified_width(child, lengths) else {
+                continue;
+            };
+            let span = cell.columns.len();
+            let spacing = table.spacing.0 * (span - 1) as f32;
+            let cell_width = resolve(specified, table.cell_edges(child, index, lengths));
+            for width in &mut widths[cell.columns.clone()] {
+                width.get_or_insert(((cell_width - spacing) / span as f32).max(0.0));
+            }
+        }
+
+        // Without any columns to share it, the space left widens the columns in proportion.
+        let given = sum(widths.iter().flatten().copied());
+        let remaining = (width - given).max(0.0);
+        let shared = widths.iter().filter(|width| width.is_none()).count();
+        widths
+            .into_iter()
+            .map(|column| match column {
+                None => remaining / shared as f32,
+                Some(column) if shared == 0 && given > 0.0 => column + remaining * column / given,
+                Some(column) => column,
+            })
+            .collect()
+    }
+
+    fn table_row(&self, (group, row): (Option<usize>, usize)) -> &LayoutBox {
+        match group {
+            Some(group) => &self.children[group].children[row],
+            None => &self.children[row],
+        }
+    }
+
+    fn table_row_mut(&mut self, (group, row): (Option<usize>, usize)) -> &mut LayoutBox {
+        match group {
+            Some(group) => &mut self.children[group].children[row],
+            None => &mut self.children[row],
+        }
+    }
+
+    fn table_cell(&self, table: &Table, cell: &Cell) -> &LayoutBox {
+        &self.table_row(table.rows[cell.row]).children[cell.index]
+    }
+
+    fn table_cell_mut(&mut self, table: &Table, cell: &Cell) -> &mut LayoutBox {
+        &mut self.table_row_mut(table.rows[cell.row]).children[cell.index]
+    }
+}
+
+impl Table {
+    /// The horizontal space between and around the columns.
+    fn spacing_width(&self) -> f32 {
+        match self.columns {
+            0 => 0.0,
+            columns => self.spacing.0 * (columns + 1) as f32,
+        }
+    }
+
+    /// The horizontal padding and border of the cell at `index`.
+    fn cell_edges(&self, cell: &LayoutBox, index: usize, lengths: LengthContext) -> f32 {
+        let style = cell.get_style_node();
+        let zero = Length(0.0, Px);
+        let lookup = |name: &str| lengths.px(&style.lookup(name, &zero));
+        let border = match &self.collapsed_borders {
+            Some(borders) => borders[index].widths.left + borders[index].widths.right,
+            None => lookup("border-left-width") + lookup("border-right-width"),
+        };
+        lookup("padding-left") + lookup("padding-right") + border
+    }
+}
+
+impl BorderSide {
+    fn of(style: &StyleNode, side: &str) -> Self {
+        const STYLES: [&str; 9] = ["none", "inset", "groove", "outset", "ridge", "dotted", "dashed", "solid", "double"];
+        let border_style = keyword(style, &format!("border-{side}-style"), "none");
+        BorderSide {
+            width: match style.get_value(&format!("border-{side}-width")) {
+                Some(Length(width, Px)) => width,
+                _ => 0.0,
+            },
+            rank: STYLES.iter().position(|name| *name == border_style).unwrap_or(0),
+            hidden: border_style == "hidden",
+            color: style.get_color(&format!("border-{side}-color")),
+        }
+    }
+}
+
+/// Resolve a collapsed border from the sides of the borders that meet there, in order of
+/// precedence for sides that are otherwise equal.
+///
+/// https://www.w3.org/TR/CSS2/tables.html#border-conflict-resolution
+fn collapse(sides: impl Iterator<Item = BorderSide>) -> BorderSide {
+    let mut winner: Option<BorderSide> = None;
+    for side in sides {
+        // A hidden border suppresses all the others. Otherwise the widest border wins, and then
+        // the one whose style ranks highest.
+        if side.hidden {
+            return BorderSide::default();
+        }
+        if !matches!(winner, Some(winner) if (winner.width, winner.rank) >= (side.width, side.rank)) {
+            winner = Some(side);
+        }
+    }
+    winner.unwrap_or_default()
+}
+
+/// Widen columns so that together they are at least `width` wide at the given size, in
+/// proportion to their max-content widths, or equally if those are all zero.
+fn widen(columns: &mut [ColumnWidth], width: f32, size: IntrinsicSize) {
+    let get = |column: &ColumnWidth| match size {
+        IntrinsicSize::MinContent => column.min,
+        IntrinsicSize::MaxContent => column.max,
+    };
+    let current = sum(columns.iter().map(get));
+    if width <= current {
+        return;
+    }
+    let total = sum(columns.iter().map(|column| column.max));
+    let count = columns.len() as f32;
+    for column in columns.iter_mut() {
+        let share = match total > 0.0 {
+            true => column.max / total,
+            false => 1.0 / count,
+        };
+        let extra = (width - current) * share;
+        match size {
+            IntrinsicSize::MinContent => column.min += extra,
+            IntrinsicSize::MaxContent => column.max += extra,
+        }
+        column.max = column.max.max(column.min);
+    }
+}
+
+/// Share the width available for the columns of a table among them, with the automatic table
+/// layout algorithm. Each column gets its min-content width, then the columns with a percentage
+/// grow towards it, then those with a length, and then the others towards their max-content
+/// widths. Whatever is left widens the columns without a width, or failing that all of them.
+fn distribute_widths(columns: &[ColumnWidth], available: f32) -> Vec<f32> {
+    let mut widths: Vec<f32> = columns.iter().map(|column| column.min).collect();
+    let mut remaining = available - sum(widths.iter().copied());
+
+    // Grow columns towards the widths `target` gives them, in proportion to how far they are
+    // from them.
+    let mut grow = |target: &dyn Fn(&ColumnWidth) -> Option<f32>| {
+        let wanted: Vec<f32> = columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, width)| target(column).map_or(0.0, |target| (target - width).max(0.0)))
+            .collect();
+        let total = sum(wanted.iter().copied());
+        if total <= 0.0 || remaining <= 0.0 {
+            return;
+        }
+        let scale = (remaining / total).min(1.0);
+        for (width, wanted) in widths.iter_mut().zip(wanted) {
+            *width += wanted * scale;
+        }
+        remaining -= total * scale;
+    };
+    grow(&|column| match column.width {
+        Some(Percent(percent)) => Some(percent / 100.0 * available),
+        _ => None,
+    });
+    grow(&|column| matches!(column.width, Some(Fixed(_))).then_some(column.max));
+    grow(&|column| column.width.is_none().then_some(column.max));
+
+    if remaining > 0.0 {
+        let any_auto = columns.iter().any(|column| column.width.is_none());
+        let weights: Vec<Option<f32>> = columns
+            .iter()
+            .zip(&widths)
+            .map(|(column, width)| (!any_auto || column.width.is_none()).then_some(column.max.max(*width)))
+            .collect();
+        let eligible = weights.iter().flatten().count() as f32;
+        let total = sum(weights.iter().flatten().copied());
+        for (width, weight) in widths.iter_mut().zip(weights) {
+            if let Some(weight) = weight {
+                *width += match total > 0.0 {
+                    true => remaining * weight / total,
+                    false => remaining / eligible,
+                };
+            }
+        }
+    }
+    widths
+}
+
+/// The width given to a column or cell by its `width`, if it isn't `auto`.
+fn specified_width(column: &LayoutBox, lengths: LengthContext) -> Option<SpecifiedWidth> {
+    match column.get_style_node().get_value("width") {
+        Some(Length(percent, Unit::Percent)) => Some(Percent(percent)),
+        _ => match column.width_constraints(lengths).0 {
+            Length(width, Px) => Some(Fixed(width)),
+            _ => None,
+        },
+    }
+}
+
+/// A number of rows or columns given by an attribute of a box's element, such as `colspan`.
+fn span_attribute(style: &StyleNode, name: &str) -> Option<usize> {
+    let element = style.node.element()?;
+    element.attributes.get(name)?.as_deref()?.trim().parse().ok()
+}
+
+/// The horizontal and vertical space between the cells of a table, from `border-spacing`.
+fn border_spacing(style: &StyleNode, lengths: LengthContext) -> (f32, f32) {
+    match style.get_value("border-spacing") {
+        Some(List(values, _)) if values.len() == 2 => (lengths.px(&values[0]), lengths.px(&values[1])),
+        Some(value) => (lengths.px(&value), lengths.px(&value)),
+        None => (0.0, 0.0),
+    }
+}
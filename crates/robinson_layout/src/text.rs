@@ -0,0 +1,51 @@
+//! Font selection and text measurement used by inline layout.
+
+use std::sync::Arc;
+
+use robinson_css::Unit::Px;
+use robinson_css::Separator;
+use robinson_css::Value::{self, Keyword, Length, List, Number, StringLiteral};
+use robinson_font::{select_font, Font};
+use robinson_style::{StyleNode, MEDIUM_FONT_SIZE};
+
+/// Used font size of an element, in px.
+pub fn font_size(style: &StyleNode) -> f32 {
+    match style.get_value("font-size") {
+        Some(Length(size, Px)) => size,
+        _ => MEDIUM_FONT_SIZE,
+    }
+}
+
+/// Select the font for an element from its `font-family`, `font-weight` and `font-style`.
+pub fn font(style: &StyleNode) -> Arc<Font> {
+    let families = match style.get_value("font-family") {
+        Some(List(families, Separator::Comma)) => families.into_iter().filter_map(family_name).collect(),
+        Some(family) => family_name(family).into_iter().collect(),
+        None => Vec::new(),
+    };
+    let bold = match style.get_value("font-weight") {
+        Some(Keyword(weight)) => weight == "bold" || weight == "bolder",
+        Some(Number(weight)) => weight >= 600.0,
+        _ => false,
+    };
+    let italic = matches!(style.get_value("font-style"), Some(Keyword(slant)) if slant == "italic" || slant == "oblique");
+
+    select_font(&families, bold, italic)
+}
+
+fn family_name(family: Value) -> Option<String> {
+    match family {
+        Keyword(family) | StringLiteral(family) => Some(family),
+        _ => None,
+    }
+}
+
+/// Distance from the baseline to the top of the font's content area.
+pub fn ascent(font: &Font, font_size: f32) -> f32 {
+    font.metrics(font_size).ascent
+}
+
+/// Distance from the baseline to the bottom of the font's content area.
+pub fn descent(font: &Font, font_size: f32) -> f32 {
+    font.metrics(font_size).descent
+}
@@ -7,4 +7,8 @@ pub enum Error {
     Io(#[from] io::Error),
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
+    #[error(transparent)]
+    Css(#[from] robinson_css::Error),
+    #[error("{location}: {message}")]
+    Html { location: String, message: String },
 }
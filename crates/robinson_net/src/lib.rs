@@ -1,5 +1,7 @@
 mod error;
 mod client;
+mod page;
 
 pub use error::*;
 pub use client::*;
+pub use page::*;
@@ -0,0 +1,54 @@
+use robinson_css::StyleSheet;
+use robinson_dom::{Element, Node};
+
+use crate::{Client, Error};
+
+/// A page and the stylesheets it links to or embeds, in document order.
+pub struct Page {
+    pub root_node: Node,
+    pub stylesheets: Vec<StyleSheet>,
+}
+
+impl Client {
+    /// Fetch the page at `location`, a URL or file path, along with its stylesheets. Linked
+    /// stylesheets are resolved relative to the page.
+    pub async fn load_page(&self, location: &str) -> Result<Page, Error> {
+        let url = self.get_url(location)?;
+        let html = self.get_to_string(url.clone()).await?;
+        let html_error = |message: String| Error::Html { location: location.to_string(), message };
+        let dom = robinson_dom::parse(&html).map_err(|error| html_error(error.to_string()))?;
+        let root_node = dom.children.into_iter().next().ok_or_else(|| html_error("document is empty".to_string()))?;
+
+        let mut stylesheets = Vec::new();
+        for element in head_children(&root_node) {
+            let is_stylesheet = element.attributes.get("rel") == Some(&Some("stylesheet".to_string()));
+            let css = match &*element.name {
+                "link" if is_stylesheet => match element.attributes.get("href") {
+                    Some(Some(href)) => {
+                        let css_url = url.join(href).map_err(|error| html_error(format!("{}: {}", href, error)))?;
+                        self.get_to_string(css_url).await?
+                    }
+                    _ => continue,
+                },
+                "style" => match element.children.first().and_then(|child| child.text()) {
+                    Some(text) => text.to_string(),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            stylesheets.push(StyleSheet::parse(&css)?);
+        }
+
+        Ok(Page { root_node, stylesheets })
+    }
+}
+
+/// The elements directly inside the page's `<head>`.
+fn head_children(root_node: &Node) -> impl Iterator<Item = &Element> {
+    root_node
+        .element()
+        .into_iter()
+        .flat_map(|root| root.children.iter().filter_map(|child| child.element()))
+        .filter(|element| element.name == "head")
+        .flat_map(|head| head.children.iter().filter_map(|child| child.element()))
+}
@@ -0,0 +1,27 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use robinson_css::StyleSheet;
+
+static RAINBOW_HTML: &str = include_str!("../../../examples/perf-rainbow.html");
+static RAINBOW_CSS: &str = include_str!("../../../examples/perf-rainbow.css");
+
+/// Render the rainbow page from its source, as `robinson render` does once it has loaded the
+/// page: parse the document and its stylesheet, then style, lay out and paint it.
+fn render_rainbow(c: &mut Criterion) {
+    // Each iteration parses a page of over a hundred thousand elements, so take fewer samples.
+    let mut group = c.benchmark_group("render");
+    group.sample_size(10);
+    group.bench_function("parse perf-rainbow", |b| {
+        b.iter(|| robinson_dom::parse(black_box(RAINBOW_HTML)).unwrap())
+    });
+    group.bench_function("perf-rainbow", |b| {
+        b.iter(|| {
+            let dom = robinson_dom::parse(black_box(RAINBOW_HTML)).unwrap();
+            let stylesheets = vec![StyleSheet::parse(black_box(RAINBOW_CSS)).unwrap()];
+            robinson_paint::render_page(&dom.children[0], &stylesheets, 800, 600)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, render_rainbow);
+criterion_main!(benches);
@@ -1,10 +1,20 @@
-use robinson_css::Color;
-use robinson_layout::{Rect, RenderTree, RenderBox, RenderBlockBox};
+use std::sync::Arc;
+
+use image::{Rgba, RgbaImage};
+use robinson_css::{Color, StyleSheet};
+use robinson_dom::Node;
+use robinson_font::{Font, ShapedGlyph};
+use robinson_layout::{
+    BorderColors, Dimensions, InlineFragment, Rect, RenderAnonymousBox, RenderBlockBox, RenderBox, RenderTree, TextFragment,
+};
+use robinson_style::{SharingStats, StyleTree};
 
 pub struct Canvas {
     pub render_tree: RenderTree,
     pub width: usize,
     pub height: usize,
+    /// The areas that painting is clipped to, innermost last.
+    clips: Vec<Rect>,
 }
 
 pub struct SolidColor {
@@ -12,7 +22,27 @@ pub struct SolidColor {
     pub color: Color,
 }
 
-pub type DisplayList = Vec<SolidColor>;
+/// A run of glyphs drawn with a single font, size and color.
+pub struct Text {
+    /// Left end of the run's baseline.
+    pub x: f32,
+    pub baseline: f32,
+    pub glyphs: Vec<ShapedGlyph>,
+    pub font: Arc<Font>,
+    pub font_size: f32,
+    pub color: Color,
+}
+
+pub enum DisplayItem {
+    SolidColor(SolidColor),
+    Text(Text),
+    /// Clip the items up to the matching `PopClip` to a rectangle, within any clip already in
+    /// effect.
+    PushClip(Rect),
+    PopClip,
+}
+
+pub type DisplayList = Vec<DisplayItem>;
 
 impl Canvas {
     pub fn new(render_tree: RenderTree, width: usize, height: usize) -> Self {
@@ -20,6 +50,7 @@ impl Canvas {
             render_tree,
             width,
             height,
+            clips: Vec::new(),
         }
     }
 
@@ -33,12 +64,33 @@ impl Canvas {
         pixels
     }
 
-    fn paint_item(&mut self, pixels: &mut [Color], item: &SolidColor) {
-        // Clip the rectangle to the canvas boundaries.
-        let x0 = item.rect.x.clamp(0.0, self.width as f32) as usize;
-        let y0 = item.rect.y.clamp(0.0, self.height as f32) as usize;
-        let x1 = (item.rect.x + item.rect.width).clamp(0.0, self.width as f32) as usize;
-        let y1 = (item.rect.y + item.rect.height).clamp(0.0, self.height as f32) as usize;
+    fn paint_item(&mut self, pixels: &mut [Color], item: &DisplayItem) {
+        match item {
+            DisplayItem::SolidColor(solid) => self.paint_solid_color(pixels, solid),
+            DisplayItem::Text(text) => self.paint_text(pixels, text),
+            DisplayItem::PushClip(rect) => {
+                let clip = self.clips.last().map_or(*rect, |clip| clip.intersection(*rect));
+                self.clips.push(clip);
+            }
+            DisplayItem::PopClip => {
+                self.clips.pop();
+            }
+        }
+    }
+
+    /// The part of the canvas that can be painted on: the canvas, within the current clip.
+    fn paintable_area(&self) -> Rect {
+        let canvas = Rect { x: 0.0, y: 0.0, width: self.width as f32, height: self.height as f32 };
+        self.clips.last().map_or(canvas, |clip| clip.intersection(canvas))
+    }
+
+    fn paint_solid_color(&mut self, pixels: &mut [Color], item: &SolidColor) {
+        // Clip the rectangle to the canvas boundaries and the current clip.
+        let rect = item.rect.intersection(self.paintable_area());
+        let x0 = rect.x as usize;
+        let y0 = rect.y as usize;
+        let x1 = (rect.x + rect.width) as usize;
+        let y1 = (rect.y + rect.height) as usize;
 
         for y in y0..y1 {
             for x in x0..x1 {
@@ -47,80 +99,304 @@ impl Canvas {
             }
         }
     }
+
+    fn paint_text(&mut self, pixels: &mut [Color], item: &Text) {
+        let area = self.paintable_area();
+        let (x0, y0) = (area.x as i32, area.y as i32);
+        let (x1, y1) = ((area.x + area.width) as i32, (area.y + area.height) as i32);
+        for glyph in &item.glyphs {
+            let origin_x = (item.x + glyph.x).round();
+            let origin_y = (item.baseline + glyph.y).round();
+
+            // Glyphs that can't be seen aren't rasterized.
+            let Some(bounds) = item.font.glyph_bounds(glyph.glyph_id, item.font_size) else { continue };
+            let glyph_rect = Rect {
+                x: origin_x + bounds.left,
+                y: origin_y + bounds.top,
+                width: bounds.right - bounds.left,
+                height: bounds.bottom - bounds.top,
+            };
+            let visible = glyph_rect.intersection(area);
+            if visible.width == 0.0 || visible.height == 0.0 {
+                continue;
+            }
+
+            let bitmap = item.font.rasterize(glyph.glyph_id, item.font_size);
+            let left = origin_x as i32 + bitmap.left;
+            let top = origin_y as i32 - bitmap.top;
+            let width = (bitmap.width as f32 * bitmap.scale) as i32;
+            let height = (bitmap.height as f32 * bitmap.scale) as i32;
+
+            // Each pixel takes the coverage of the bitmap texel under it, so glyphs rasterized
+            // at a smaller size are scaled up.
+            for y in top.max(y0)..(top + height).min(y1) {
+                let row = (((y - top) as f32 / bitmap.scale) as usize).min(bitmap.height - 1);
+                for x in left.max(x0)..(left + width).min(x1) {
+                    let column = (((x - left) as f32 / bitmap.scale) as usize).min(bitmap.width - 1);
+                    let coverage = bitmap.coverage[row * bitmap.width + column];
+                    let pixel = &mut pixels[y as usize * self.width + x as usize];
+                    *pixel = blend(*pixel, item.color, coverage);
+                }
+            }
+        }
+    }
+}
+
+/// Composite `color` over `background` with the given coverage.
+fn blend(background: Color, color: Color, coverage: u8) -> Color {
+    let alpha = coverage as f32 / 255.0 * color.a as f32 / 255.0;
+    let mix = |under: u8, over: u8| (over as f32 * alpha + under as f32 * (1.0 - alpha)).round() as u8;
+    Color {
+        r: mix(background.r, color.r),
+        g: mix(background.g, color.g),
+        b: mix(background.b, color.b),
+        a: background.a,
+    }
+}
+
+/// Build the display list for a render tree, in the order the CSS painting order gives.
+///
+/// https://www.w3.org/TR/CSS2/zindex.html
+/// Style, lay out and paint a document in software, in a viewport `width` by `height` px.
+///
+/// Also returns how often elements shared a computed style while styling it.
+pub fn render_page(root_node: &Node, stylesheets: &[StyleSheet], width: u32, height: u32) -> (RgbaImage, SharingStats) {
+    let viewport = Dimensions {
+        content: Rect {
+            width: width as f32,
+            height: height as f32,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let style_tree = StyleTree::new(root_node, stylesheets);
+    let render_tree = RenderTree::new(&style_tree.root, &viewport);
+    let mut canvas = Canvas::new(render_tree, width as usize, height as usize);
+    let pixels = canvas.get_pixels();
+
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        let color = pixels[(y * width + x) as usize];
+        Rgba([color.r, color.g, color.b, color.a])
+    });
+    (image, style_tree.sharing_stats)
 }
 
 pub fn build_display_list(render_box: &RenderBox) -> DisplayList {
     let mut list = Vec::new();
-    render_layout_box(&mut list, render_box);
+    match render_box {
+        // The root element's box is always the root of a stacking context.
+        RenderBox::Block(block) => paint_stacking_context(&mut list, block),
+        RenderBox::Anonymous(anonymous) => paint_lines(&mut list, anonymous),
+    }
     list
 }
 
-fn render_layout_box(list: &mut DisplayList, render_box: &RenderBox) {
-    if let RenderBox::Block(block) = render_box {
-        make_background(list, block);
-        if let Some(color) = block.border_color {
-            make_border(list, block, color);
+/// A box to paint later, and the clip to paint it in.
+type Clipped<'a> = (&'a RenderBlockBox, Option<Rect>);
+
+/// Paint a box that is the root of a stacking context, and everything painted in it.
+fn paint_stacking_context(list: &mut DisplayList, root: &RenderBlockBox) {
+    // The positioned descendants are painted in layers by stack level, and otherwise in tree
+    // order. Sorting is stable, so boxes with the same stack level stay in tree order.
+    let mut layers = Vec::new();
+    collect_layers(&root.children, None, &mut layers);
+    layers.sort_by_key(|(layer, _)| (layer.z_index.unwrap_or(0), layer.tree_order));
+    let split = layers.partition_point(|(layer, _)| layer.z_index.unwrap_or(0) < 0);
+    let (negative, rest) = layers.split_at(split);
+
+    make_background(list, &root.dimensions, root.background_color);
+    make_border(list, &root.dimensions, root.border_colors);
+    clip_to(list, root.clip_rect(), |list| {
+        for &(layer, clip) in negative {
+            clip_to(list, clip, |list| paint_stacking_context(list, layer));
         }
-        for child in &block.children {
-            render_layout_box(list, child);
+        paint_flow(list, &root.children);
+        for &(layer, clip) in rest {
+            clip_to(list, clip, |list| match layer.z_index {
+                Some(_) => paint_stacking_context(list, layer),
+                // A positioned box with `z-index: auto` is painted as if it were the root of a
+                // stacking context, but its positioned descendants are layers of the parent one.
+                None => paint_atomically(list, layer),
+            });
         }
+    });
+}
+
+/// Paint a box and its descendants that aren't positioned, as if the box were the root of a
+/// stacking context.
+fn paint_atomically(list: &mut DisplayList, root: &RenderBlockBox) {
+    make_background(list, &root.dimensions, root.background_color);
+    make_border(list, &root.dimensions, root.border_colors);
+    clip_to(list, root.clip_rect(), |list| paint_flow(list, &root.children));
+}
+
+/// Paint the boxes that aren't positioned among `boxes` and their descendants: the backgrounds
+/// and borders of blocks in normal flow, then floats, then inline content.
+fn paint_flow(list: &mut DisplayList, boxes: &[RenderBox]) {
+    paint_blocks(list, boxes);
+    let mut floats = Vec::new();
+    collect_floats(boxes, None, &mut floats);
+    for (float, clip) in floats {
+        clip_to(list, clip, |list| paint_atomically(list, float));
     }
+    paint_inline_content(list, boxes);
 }
 
-fn make_background(list: &mut DisplayList, render_block: &RenderBlockBox) {
-    if let Some(color) = render_block.background_color {
-        list.push(SolidColor {
+fn paint_blocks(list: &mut DisplayList, boxes: &[RenderBox]) {
+    for render_box in boxes {
+        match render_box {
+            RenderBox::Block(block) if block.is_positioned() || block.is_float() => {}
+            RenderBox::Block(block) => {
+                make_background(list, &block.dimensions, block.background_color);
+                make_border(list, &block.dimensions, block.border_colors);
+                clip_to(list, block.clip_rect(), |list| paint_blocks(list, &block.children));
+            }
+            RenderBox::Anonymous(_) => {}
+        }
+    }
+}
+
+/// Collect the floats among `boxes` and their descendants in normal flow, without those inside
+/// another float, with the clip each is painted in.
+fn collect_floats<'a>(boxes: &'a [RenderBox], clip: Option<Rect>, floats: &mut Vec<Clipped<'a>>) {
+    for render_box in boxes {
+        match render_box {
+            RenderBox::Block(block) if block.is_positioned() => {}
+            RenderBox::Block(block) if block.is_float() => floats.push((block, clip)),
+            RenderBox::Block(block) => collect_floats(&block.children, within(clip, block.clip_rect()), floats),
+            RenderBox::Anonymous(_) => {}
+        }
+    }
+}
+
+fn paint_inline_content(list: &mut DisplayList, boxes: &[RenderBox]) {
+    for render_box in boxes {
+        match render_box {
+            RenderBox::Block(block) if block.is_positioned() || block.is_float() => {}
+            RenderBox::Block(block) => {
+                clip_to(list, block.clip_rect(), |list| paint_inline_content(list, &block.children));
+            }
+            RenderBox::Anonymous(anonymous) => paint_lines(list, anonymous),
+        }
+    }
+}
+
+fn paint_lines(list: &mut DisplayList, anonymous: &RenderAnonymousBox) {
+    for fragment in anonymous.lines.iter().flat_map(|line| &line.fragments) {
+        match fragment {
+            InlineFragment::Box(inline_box) => {
+                make_background(list, &inline_box.dimensions, inline_box.background_color);
+                make_border(list, &inline_box.dimensions, inline_box.border_colors);
+            }
+            InlineFragment::Text(text) => make_text(list, text),
+        }
+    }
+}
+
+/// Collect the positioned descendants among `boxes` that are painted in the stacking context
+/// they are in, without those inside another stacking context, with the clip each is painted in.
+///
+/// Absolutely positioned boxes are children of their containing block in the render tree, so
+/// they are clipped by the boxes they are positioned in, and not by others around them.
+fn collect_layers<'a>(boxes: &'a [RenderBox], clip: Option<Rect>, layers: &mut Vec<Clipped<'a>>) {
+    for render_box in boxes {
+        if let RenderBox::Block(block) = render_box {
+            if block.is_positioned() {
+                layers.push((block, clip));
+            }
+            if block.z_index.is_none() {
+                collect_layers(&block.children, within(clip, block.clip_rect()), layers);
+            }
+        }
+    }
+}
+
+/// Paint with `paint`, clipped to `clip` if there is one.
+fn clip_to(list: &mut DisplayList, clip: Option<Rect>, paint: impl FnOnce(&mut DisplayList)) {
+    match clip {
+        Some(clip) => {
+            let start = list.len();
+            list.push(DisplayItem::PushClip(clip));
+            paint(list);
+            // A clip around nothing is left out.
+            match list.len() == start + 1 {
+                true => list.truncate(start),
+                false => list.push(DisplayItem::PopClip),
+            }
+        }
+        None => paint(list),
+    }
+}
+
+/// The area within both of two clips, either of which may be missing.
+fn within(clip: Option<Rect>, other: Option<Rect>) -> Option<Rect> {
+    match (clip, other) {
+        (Some(clip), Some(other)) => Some(clip.intersection(other)),
+        (clip, other) => clip.or(other),
+    }
+}
+
+fn make_text(list: &mut DisplayList, text: &TextFragment) {
+    list.push(DisplayItem::Text(Text {
+        x: text.rect.x,
+        baseline: text.baseline,
+        glyphs: text.glyphs.clone(),
+        font: Arc::clone(&text.font),
+        font_size: text.font_size,
+        // The initial value of `color` is black.
+        color: text.color.unwrap_or_else(|| Color::from_hex("#000000")),
+    }));
+}
+
+fn make_background(list: &mut DisplayList, dimensions: &Dimensions, background_color: Option<Color>) {
+    // Fully transparent colors (such as the initial `background-color`) draw nothing.
+    if let Some(color) = background_color.filter(|color| color.a > 0) {
+        list.push(DisplayItem::SolidColor(SolidColor {
             color,
-            rect: render_block.dimensions.border_box(),
-        });
+            rect: dimensions.border_box(),
+        }));
     }
 }
 
-fn make_border(list: &mut DisplayList, render_block: &RenderBlockBox, color: Color) {
-    let d = &render_block.dimensions;
+fn make_border(list: &mut DisplayList, dimensions: &Dimensions, colors: BorderColors) {
+    let d = dimensions;
     let border_box = d.border_box();
 
-    // Left border
-    list.push(SolidColor {
-        color,
-        rect: Rect {
+    let sides = [
+        // Left border
+        (colors.left, Rect {
             x: border_box.x,
             y: border_box.y,
             width: d.border.left,
             height: border_box.height,
-        },
-    });
-
-    // Right border
-    list.push(SolidColor {
-        color,
-        rect: Rect {
+        }),
+        // Right border
+        (colors.right, Rect {
             x: border_box.x + border_box.width - d.border.right,
             y: border_box.y,
             width: d.border.right,
             height: border_box.height,
-        },
-    });
-
-    // Top border
-    list.push(SolidColor {
-        color,
-        rect: Rect {
+        }),
+        // Top border
+        (colors.top, Rect {
             x: border_box.x,
             y: border_box.y,
             width: border_box.width,
             height: d.border.top,
-        },
-    });
-
-    // Bottom border
-    list.push(SolidColor {
-        color,
-        rect: Rect {
+        }),
+        // Bottom border
+        (colors.bottom, Rect {
             x: border_box.x,
             y: border_box.y + border_box.height - d.border.bottom,
             width: border_box.width,
             height: d.border.bottom,
-        },
-    });
+        }),
+    ];
+
+    for (color, rect) in sides {
+        if let Some(color) = color.filter(|color| color.a > 0) {
+            list.push(DisplayItem::SolidColor(SolidColor { color, rect }));
+        }
+    }
 }
@@ -1,6 +1,9 @@
+use robinson_css::Color;
+use robinson_font::{Font, OutlineFormat};
 use robinson_layout::RenderTree;
-use robinson_paint::{build_display_list, SolidColor};
-use std::io::{self, Seek, SeekFrom, Write};
+use robinson_paint::{build_display_list, DisplayItem, DisplayList, SolidColor, Text};
+use std::io::{self, Seek, Write};
+use std::sync::Arc;
 
 fn px_to_pt(value: f32) -> f32 {
     // 96px = 1in = 72pt
@@ -15,25 +18,54 @@ pub fn render<W: Write + Seek>(
     file: &mut W,
 ) -> io::Result<()> {
     let display_list = build_display_list(&render_tree.root);
+    let fonts = used_fonts(&display_list);
     let mut pdf = Pdf::new(file)?;
+    let font_object_ids = fonts
+        .iter()
+        .map(|font| pdf.write_font(font))
+        .collect::<io::Result<Vec<_>>>()?;
     // We map CSS pt to Poscript points (which is the default length unit in PDF).
-    pdf.render_page(px_to_pt(width), px_to_pt(height), |output| {
-        for item in display_list {
-            render_item(&item, output)?;
+    pdf.render_page(px_to_pt(width), px_to_pt(height), &font_object_ids, |output| {
+        for item in &display_list {
+            render_item(item, &fonts, output)?;
         }
         Ok(())
     })?;
     pdf.finish()
 }
 
-fn render_item<W: Write>(item: &SolidColor, output: &mut W) -> io::Result<()> {
+/// The distinct fonts referenced by text in the display list.
+fn used_fonts(display_list: &DisplayList) -> Vec<Arc<Font>> {
+    let mut fonts: Vec<Arc<Font>> = Vec::new();
+    for item in display_list {
+        if let DisplayItem::Text(text) = item {
+            if !fonts.iter().any(|font| Arc::ptr_eq(font, &text.font)) {
+                fonts.push(Arc::clone(&text.font));
+            }
+        }
+    }
+    fonts
+}
+
+fn render_item<W: Write>(item: &DisplayItem, fonts: &[Arc<Font>], output: &mut W) -> io::Result<()> {
+    match item {
+        DisplayItem::SolidColor(solid) => render_solid_color(solid, output),
+        DisplayItem::Text(text) => render_text(text, fonts, output),
+        // The clip path is part of the graphics state, so saving the state before intersecting
+        // it and restoring it afterwards undoes the clip.
+        DisplayItem::PushClip(rect) => {
+            writeln!(output, "q {} {} {} {} re W n", rect.x, rect.y, rect.width, rect.height)
+        }
+        DisplayItem::PopClip => writeln!(output, "Q"),
+    }
+}
+
+fn render_solid_color<W: Write>(item: &SolidColor, output: &mut W) -> io::Result<()> {
     writeln!(
         output,
-        "{} {} {} sc {} {} {} {} re f",
+        "{} sc {} {} {} {} re f",
         // FIMXE: alpha transparency
-        item.color.r,
-        item.color.g,
-        item.color.b,
+        pdf_color(item.color),
         item.rect.x,
         item.rect.y,
         item.rect.width,
@@ -41,6 +73,38 @@ fn render_item<W: Write>(item: &SolidColor, output: &mut W) -> io::Result<()> {
     )
 }
 
+fn render_text<W: Write>(item: &Text, fonts: &[Arc<Font>], output: &mut W) -> io::Result<()> {
+    let font_index = fonts
+        .iter()
+        .position(|font| Arc::ptr_eq(font, &item.font))
+        .expect("font was collected by used_fonts");
+
+    writeln!(output, "BT")?;
+    writeln!(output, "/F{} {} Tf", font_index, item.font_size)?;
+    writeln!(output, "{} sc", pdf_color(item.color))?;
+    for glyph in &item.glyphs {
+        // The page is flipped so that y points down; flip each glyph back upright.
+        writeln!(
+            output,
+            "1 0 0 -1 {} {} Tm <{:04X}> Tj",
+            item.x + glyph.x,
+            item.baseline + glyph.y,
+            glyph.glyph_id
+        )?;
+    }
+    writeln!(output, "ET")
+}
+
+/// Components of an RGB color in the 0-1 range used by PDF operators.
+fn pdf_color(color: Color) -> String {
+    format!(
+        "{} {} {}",
+        color.r as f32 / 255.0,
+        color.g as f32 / 255.0,
+        color.b as f32 / 255.0
+    )
+}
+
 struct Pdf<'a, W: 'a + Write + Seek> {
     output: &'a mut W,
     object_offsets: Vec<i64>,
@@ -65,10 +129,16 @@ impl<'a, W: Write + Seek> Pdf<'a, W> {
 
     /// Return the current read/write position in the output file.
     fn tell(&mut self) -> io::Result<u64> {
-        self.output.seek(SeekFrom::Current(0))
+        self.output.stream_position()
     }
 
-    fn render_page<F>(&mut self, width: f32, height: f32, render_contents: F) -> io::Result<()>
+    fn render_page<F>(
+        &mut self,
+        width: f32,
+        height: f32,
+        font_object_ids: &[usize],
+        render_contents: F,
+    ) -> io::Result<()>
     where
         F: FnOnce(&mut W) -> io::Result<()>,
     {
@@ -95,7 +165,11 @@ impl<'a, W: Write + Seek> Pdf<'a, W> {
         let page_object_id = self.write_new_object(|page_object_id, pdf| {
             writeln!(pdf.output, "<<  /Type /Page")?;
             writeln!(pdf.output, "    /Parent {} 0 R", PAGES_OBJECT_ID)?;
-            writeln!(pdf.output, "    /Resources << >>")?;
+            write!(pdf.output, "    /Resources << /Font << ")?;
+            for (index, font_object_id) in font_object_ids.iter().enumerate() {
+                write!(pdf.output, "/F{} {} 0 R ", index, font_object_id)?;
+            }
+            writeln!(pdf.output, ">> >>")?;
             writeln!(pdf.output, "    /MediaBox [ 0 0 {} {} ]", width, height)?;
             writeln!(pdf.output, "    /Contents {} 0 R", contents_object_id)?;
             writeln!(pdf.output, ">>")?;
@@ -105,6 +179,85 @@ impl<'a, W: Write + Seek> Pdf<'a, W> {
         Ok(())
     }
 
+    /// Embed a TrueType or OpenType font, addressed by glyph ID, and return the ID of its font
+    /// object.
+    fn write_font(&mut self, font: &Font) -> io::Result<usize> {
+        let info = font.info();
+        // Font descriptor metrics are expressed in units of 1/1000 em.
+        let scale = |value: i16| value as f32 * 1000.0 / info.units_per_em as f32;
+        let name: String = info
+            .postscript_name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+            .collect();
+
+        // TrueType outlines are embedded as a TrueType font program, and CFF ones as an OpenType
+        // font program, which needs a CID font of type 0 and uses glyph IDs as CIDs.
+        let (font_file_key, cid_font_subtype) = match info.outline_format {
+            OutlineFormat::TrueType => ("FontFile2", "CIDFontType2"),
+            OutlineFormat::Cff => ("FontFile3", "CIDFontType0"),
+        };
+
+        let font_file_id = self.write_new_object(|font_file_id, pdf| {
+            let data = font.face_data();
+            match info.outline_format {
+                OutlineFormat::TrueType => {
+                    writeln!(pdf.output, "<<  /Length {} /Length1 {}", data.len(), data.len())?
+                }
+                OutlineFormat::Cff => {
+                    writeln!(pdf.output, "<<  /Length {} /Subtype /OpenType", data.len())?
+                }
+            }
+            writeln!(pdf.output, ">>")?;
+            writeln!(pdf.output, "stream")?;
+            pdf.output.write_all(&data)?;
+            writeln!(pdf.output)?;
+            writeln!(pdf.output, "endstream")?;
+            Ok(font_file_id)
+        })?;
+        let descriptor_id = self.write_new_object(|descriptor_id, pdf| {
+            let [x_min, y_min, x_max, y_max] = info.bounding_box;
+            writeln!(pdf.output, "<<  /Type /FontDescriptor")?;
+            writeln!(pdf.output, "    /FontName /{}", name)?;
+            // Nonsymbolic
+            writeln!(pdf.output, "    /Flags 32")?;
+            writeln!(
+                pdf.output,
+                "    /FontBBox [ {} {} {} {} ]",
+                scale(x_min), scale(y_min), scale(x_max), scale(y_max)
+            )?;
+            writeln!(pdf.output, "    /ItalicAngle {}", info.italic_angle)?;
+            writeln!(pdf.output, "    /Ascent {}", scale(info.ascender))?;
+            writeln!(pdf.output, "    /Descent {}", scale(info.descender))?;
+            writeln!(pdf.output, "    /CapHeight {}", scale(info.cap_height))?;
+            writeln!(pdf.output, "    /StemV 80")?;
+            writeln!(pdf.output, "    /{} {} 0 R", font_file_key, font_file_id)?;
+            writeln!(pdf.output, ">>")?;
+            Ok(descriptor_id)
+        })?;
+        let cid_font_id = self.write_new_object(|cid_font_id, pdf| {
+            writeln!(pdf.output, "<<  /Type /Font")?;
+            writeln!(pdf.output, "    /Subtype /{}", cid_font_subtype)?;
+            writeln!(pdf.output, "    /BaseFont /{}", name)?;
+            writeln!(pdf.output, "    /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >>")?;
+            writeln!(pdf.output, "    /FontDescriptor {} 0 R", descriptor_id)?;
+            if info.outline_format == OutlineFormat::TrueType {
+                writeln!(pdf.output, "    /CIDToGIDMap /Identity")?;
+            }
+            writeln!(pdf.output, ">>")?;
+            Ok(cid_font_id)
+        })?;
+        self.write_new_object(|font_id, pdf| {
+            writeln!(pdf.output, "<<  /Type /Font")?;
+            writeln!(pdf.output, "    /Subtype /Type0")?;
+            writeln!(pdf.output, "    /BaseFont /{}", name)?;
+            writeln!(pdf.output, "    /Encoding /Identity-H")?;
+            writeln!(pdf.output, "    /DescendantFonts [ {} 0 R ]", cid_font_id)?;
+            writeln!(pdf.output, ">>")?;
+            Ok(font_id)
+        })
+    }
+
     fn write_new_object<F, T>(&mut self, write_content: F) -> io::Result<T>
     where
         F: FnOnce(usize, &mut Pdf<W>) -> io::Result<T>,
@@ -159,7 +312,7 @@ impl<'a, W: Write + Seek> Pdf<'a, W> {
             writeln!(pdf.output, ">>")?;
             Ok(())
         })?;
-        let startxref = self.tell();
+        let startxref = self.tell()?;
         writeln!(self.output, "xref")?;
         writeln!(self.output, "0 {}", self.object_offsets.len())?;
         // Object 0 is special
@@ -174,7 +327,7 @@ impl<'a, W: Write + Seek> Pdf<'a, W> {
         writeln!(self.output, "    /Root {} 0 R", ROOT_OBJECT_ID)?;
         writeln!(self.output, ">>")?;
         writeln!(self.output, "startxref")?;
-        writeln!(self.output, "{:?}", startxref)?;
+        writeln!(self.output, "{}", startxref)?;
         writeln!(self.output, "%%EOF")?;
         Ok(())
     }
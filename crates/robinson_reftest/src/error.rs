@@ -0,0 +1,16 @@
+use std::{io, path::PathBuf};
+use thiserror::Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
+    #[error(transparent)]
+    Net(#[from] robinson_net::Error),
+    #[error("{path}:{line}: {message}")]
+    Manifest { path: PathBuf, line: usize, message: String },
+}
@@ -0,0 +1,102 @@
+//! Reference tests: render a test page and a reference page and compare the pixels.
+
+mod error;
+mod manifest;
+mod page;
+
+use std::path::{Path, PathBuf};
+
+use image::{Rgba, RgbaImage};
+
+pub use error::*;
+pub use manifest::*;
+pub use page::*;
+
+/// Viewport size reftests are rendered at, unless a runner asks for something else.
+pub const DEFAULT_WIDTH: u32 = 800;
+pub const DEFAULT_HEIGHT: u32 = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expectation {
+    /// The pages must render identically (`==`).
+    Equal,
+    /// The pages must render differently (`!=`).
+    NotEqual,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reftest {
+    pub test: PathBuf,
+    pub reference: PathBuf,
+    pub expectation: Expectation,
+}
+
+pub struct ReftestResult {
+    pub test_image: RgbaImage,
+    pub reference_image: RgbaImage,
+    /// Number of pixels that differ between the two renderings.
+    pub differing_pixels: usize,
+    /// Largest difference in any single color channel.
+    pub max_difference: u8,
+}
+
+impl Reftest {
+    pub fn run(&self, width: u32, height: u32) -> Result<ReftestResult> {
+        let test_image = render_page(&self.test, width, height)?;
+        let reference_image = render_page(&self.reference, width, height)?;
+
+        let mut differing_pixels = 0;
+        let mut max_difference = 0;
+        for (test, reference) in test_image.pixels().zip(reference_image.pixels()) {
+            let difference = channel_difference(test, reference);
+            if difference > 0 {
+                differing_pixels += 1;
+                max_difference = max_difference.max(difference);
+            }
+        }
+
+        Ok(ReftestResult {
+            test_image,
+            reference_image,
+            differing_pixels,
+            max_difference,
+        })
+    }
+
+    /// A short name for reports, relative to `base`.
+    pub fn name(&self, base: &Path) -> String {
+        let strip = |path: &Path| path.strip_prefix(base).unwrap_or(path).display().to_string();
+        let operator = match self.expectation {
+            Expectation::Equal => "==",
+            Expectation::NotEqual => "!=",
+        };
+        format!("{} {} {}", strip(&self.test), operator, strip(&self.reference))
+    }
+}
+
+impl ReftestResult {
+    pub fn passed(&self, expectation: Expectation) -> bool {
+        match expectation {
+            Expectation::Equal => self.differing_pixels == 0,
+            Expectation::NotEqual => self.differing_pixels > 0,
+        }
+    }
+
+    /// An image highlighting differing pixels in red over a faded copy of the test rendering.
+    pub fn diff_image(&self) -> RgbaImage {
+        RgbaImage::from_fn(self.test_image.width(), self.test_image.height(), |x, y| {
+            let test = self.test_image.get_pixel(x, y);
+            let reference = self.reference_image.get_pixel(x, y);
+            if channel_difference(test, reference) > 0 {
+                Rgba([255, 0, 0, 255])
+            } else {
+                let fade = |channel: u8| 192 + channel / 4;
+                Rgba([fade(test[0]), fade(test[1]), fade(test[2]), 255])
+            }
+        })
+    }
+}
+
+fn channel_difference(a: &Rgba<u8>, b: &Rgba<u8>) -> u8 {
+    a.0.iter().zip(b.0.iter()).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or(0)
+}
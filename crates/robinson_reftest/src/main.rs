@@ -0,0 +1,56 @@
+use std::{fs, path::PathBuf, process::ExitCode};
+
+use clap::Parser;
+use robinson_reftest::{parse_manifest, Result, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+
+/// Run reference tests and report pixel differences
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Reftest manifest to run
+    #[arg(default_value = "reftests/reftest.list")]
+    manifest: PathBuf,
+
+    /// Directory to write the test, reference and diff images of failing tests to
+    #[arg(long)]
+    diff_dir: Option<PathBuf>,
+
+    /// Viewport width in px
+    #[arg(long, default_value_t = DEFAULT_WIDTH)]
+    width: u32,
+
+    /// Viewport height in px
+    #[arg(long, default_value_t = DEFAULT_HEIGHT)]
+    height: u32,
+}
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse();
+    let base = args.manifest.parent().unwrap_or(&args.manifest).to_path_buf();
+    let reftests = parse_manifest(&args.manifest)?;
+
+    let mut failures = 0;
+    for (index, reftest) in reftests.iter().enumerate() {
+        let name = reftest.name(&base);
+        let result = reftest.run(args.width, args.height)?;
+        if result.passed(reftest.expectation) {
+            println!("PASS {}", name);
+            continue;
+        }
+
+        failures += 1;
+        println!(
+            "FAIL {} ({} pixels differ, max difference {})",
+            name, result.differing_pixels, result.max_difference
+        );
+        if let Some(diff_dir) = &args.diff_dir {
+            fs::create_dir_all(diff_dir)?;
+            result.test_image.save(diff_dir.join(format!("{}-test.png", index)))?;
+            result.reference_image.save(diff_dir.join(format!("{}-reference.png", index)))?;
+            result.diff_image().save(diff_dir.join(format!("{}-diff.png", index)))?;
+        }
+    }
+
+    println!("{} passed, {} failed", reftests.len() - failures, failures);
+    Ok(if failures == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE })
+}
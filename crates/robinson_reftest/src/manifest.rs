@@ -0,0 +1,49 @@
+use std::{fs, path::Path};
+
+use crate::{Error, Expectation, Reftest, Result};
+
+/// Read a reftest manifest.
+///
+/// Each non-empty line holds an expectation followed by the test and reference pages, relative
+/// to the manifest:
+///
+/// ```text
+/// # Comments start with a hash.
+/// == margin-auto.html margin-auto-ref.html
+/// != background.html blank-ref.html
+/// ```
+pub fn parse_manifest(path: &Path) -> Result<Vec<Reftest>> {
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    let contents = fs::read_to_string(path)?;
+
+    let mut reftests = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: &str| Error::Manifest {
+            path: path.to_path_buf(),
+            line: index + 1,
+            message: message.to_string(),
+        };
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let [expectation, test, reference] = fields[..] else {
+            return Err(error("expected `==` or `!=` followed by two files"));
+        };
+        let expectation = match expectation {
+            "==" => Expectation::Equal,
+            "!=" => Expectation::NotEqual,
+            _ => return Err(error("expected `==` or `!=`")),
+        };
+
+        reftests.push(Reftest {
+            test: directory.join(test),
+            reference: directory.join(reference),
+            expectation,
+        });
+    }
+    Ok(reftests)
+}
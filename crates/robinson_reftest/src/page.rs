@@ -0,0 +1,16 @@
+use std::path::Path;
+
+use image::RgbaImage;
+use robinson_net::{Client, Page};
+
+use crate::Result;
+
+/// Render a local HTML file through the software `Canvas` path.
+///
+/// The page and its stylesheets are loaded and rendered the same way `robinson render` does.
+pub fn render_page(path: &Path, width: u32, height: u32) -> Result<RgbaImage> {
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let Page { root_node, stylesheets } = runtime.block_on(Client::default().load_page(&path.to_string_lossy()))?;
+    let (image, _) = robinson_paint::render_page(&root_node, &stylesheets, width, height);
+    Ok(image)
+}
@@ -0,0 +1,28 @@
+use std::{fs, path::Path};
+
+use robinson_reftest::{parse_manifest, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+
+#[test]
+fn reftests() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../reftests/reftest.list");
+    let base = manifest.parent().unwrap();
+    let diff_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("reftest-diffs");
+
+    let mut failures = Vec::new();
+    for (index, reftest) in parse_manifest(&manifest).unwrap().iter().enumerate() {
+        let result = reftest.run(DEFAULT_WIDTH, DEFAULT_HEIGHT).unwrap();
+        if !result.passed(reftest.expectation) {
+            fs::create_dir_all(&diff_dir).unwrap();
+            let diff = diff_dir.join(format!("{}-diff.png", index));
+            result.diff_image().save(&diff).unwrap();
+            failures.push(format!(
+                "{} ({} pixels differ, diff image at {})",
+                reftest.name(base),
+                result.differing_pixels,
+                diff.display()
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "reftests failed:\n{}", failures.join("\n"));
+}
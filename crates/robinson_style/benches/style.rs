@@ -0,0 +1,71 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use robinson_css::StyleSheet;
+use robinson_dom::Node;
+use robinson_style::StyleTree;
+
+static RAINBOW_HTML: &str = include_str!("../../../examples/perf-rainbow.html");
+static RAINBOW_CSS: &str = include_str!("../../../examples/perf-rainbow.css");
+
+/// Parse the rainbow page and return its root element.
+fn rainbow_page() -> Node {
+    let dom = robinson_dom::parse(RAINBOW_HTML).unwrap();
+    dom.children.into_iter().next().unwrap()
+}
+
+/// Give every element of the page an id of its own, so that no element can share the style of
+/// another and each one is matched against the stylesheets.
+fn with_unique_ids(node: &mut Node, next_id: &mut usize) {
+    if let Node::Element(element) = node {
+        element.id = Some(format!("element-{}", next_id));
+        *next_id += 1;
+        for child in &mut element.children {
+            with_unique_ids(child, next_id);
+        }
+    }
+}
+
+/// The rainbow stylesheet followed by a thousand rules that match nothing on the page, closer to
+/// the size of a real site's stylesheet.
+fn large_stylesheet() -> String {
+    let mut css = RAINBOW_CSS.to_string();
+    for index in 0..1000 {
+        css.push_str(&format!(".unused-{} > div.item#row-{} {{ background: red; }}\n", index, index));
+    }
+    css
+}
+
+fn style_rainbow(c: &mut Criterion) {
+    let root_node = rainbow_page();
+    let stylesheets = vec![StyleSheet::parse(RAINBOW_CSS).unwrap()];
+    let large_stylesheets = vec![StyleSheet::parse(&large_stylesheet()).unwrap()];
+
+    // Each iteration styles over a hundred thousand elements, so take fewer samples.
+    let mut group = c.benchmark_group("style");
+    group.sample_size(10);
+    group.bench_function("perf-rainbow", |b| {
+        b.iter(|| StyleTree::new(black_box(&root_node), black_box(&stylesheets)))
+    });
+    group.bench_function("perf-rainbow with a large stylesheet", |b| {
+        b.iter(|| StyleTree::new(black_box(&root_node), black_box(&large_stylesheets)))
+    });
+    group.finish();
+
+    // Style sharing skips matching for almost every element of the rainbow page, so the rule
+    // index is compared against a linear scan of the rules on a page without shared styles.
+    let mut unshared_node = root_node.clone();
+    with_unique_ids(&mut unshared_node, &mut 0);
+    let mut group = c.benchmark_group("rule matching");
+    group.sample_size(10);
+    for (name, stylesheets) in [("perf-rainbow", &stylesheets), ("perf-rainbow with a large stylesheet", &large_stylesheets)] {
+        group.bench_function(format!("{} with the rule index", name), |b| {
+            b.iter(|| StyleTree::new(black_box(&unshared_node), black_box(stylesheets)))
+        });
+        group.bench_function(format!("{} with a linear scan", name), |b| {
+            b.iter(|| StyleTree::new_without_rule_index(black_box(&unshared_node), black_box(stylesheets)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, style_rainbow);
+criterion_main!(benches);
@@ -0,0 +1,126 @@
+//! Custom properties (`--name: value`) and their substitution with `var()`.
+//!
+//! https://www.w3.org/TR/css-variables-1/
+
+use std::collections::{HashMap, HashSet};
+
+use robinson_css::Value;
+
+use crate::PropertyMap;
+
+pub fn is_custom_property(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+/// Substitute the `var()` references in the values of the custom properties in `values`.
+///
+/// Properties that refer to each other in a cycle, or to a property that is missing without a
+/// fallback, are invalid and are removed, as if they had never been set.
+pub fn resolve_custom_properties(values: &mut PropertyMap) {
+    let raw: HashMap<String, String> = values
+        .iter()
+        .filter_map(|(name, value)| match value {
+            Value::Unparsed(text) if is_custom_property(name) => Some((name.clone(), text.clone())),
+            _ => None,
+        })
+        .collect();
+
+    let mut resolver = Resolver {
+        raw: &raw,
+        resolved: HashMap::new(),
+        in_progress: Vec::new(),
+        cyclic: HashSet::new(),
+    };
+    for name in raw.keys() {
+        match resolver.resolve(name) {
+            Some(text) => values.insert(name.clone(), Value::Unparsed(text)),
+            None => values.remove(name),
+        };
+    }
+}
+
+/// Replace every `var()` in `text` with the value of the custom property it names in `values`,
+/// or with its fallback. Returns `None` if a reference can't be substituted.
+pub fn substitute(text: &str, values: &PropertyMap) -> Option<String> {
+    substitute_with(text, &mut |name| match values.get(name) {
+        Some(Value::Unparsed(text)) => Some(text.clone()),
+        _ => None,
+    })
+}
+
+/// Resolves custom properties on demand, so that each is resolved after those it refers to.
+struct Resolver<'a> {
+    /// Values as specified, which may contain `var()` references.
+    raw: &'a HashMap<String, String>,
+    /// Values with their references substituted, or `None` if they are invalid.
+    resolved: HashMap<String, Option<String>>,
+    /// Properties currently being resolved, outermost first.
+    in_progress: Vec<String>,
+    /// Properties found to be part of a reference cycle.
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        if let Some(resolved) = self.resolved.get(name) {
+            return resolved.clone();
+        }
+        // A property that is already being resolved refers to itself, through every property
+        // since it in the chain.
+        if let Some(start) = self.in_progress.iter().position(|other| other == name) {
+            self.cyclic.extend(self.in_progress[start..].iter().cloned());
+            return None;
+        }
+        let raw = self.raw.get(name)?;
+
+        self.in_progress.push(name.to_string());
+        let text = substitute_with(raw, &mut |name| self.resolve(name));
+        self.in_progress.pop();
+
+        let resolved = text.filter(|_| !self.cyclic.contains(name));
+        self.resolved.insert(name.to_string(), resolved.clone());
+        resolved
+    }
+}
+
+fn substitute_with(text: &str, lookup: &mut impl FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = find_ignore_case(rest, "var(") {
+        result.push_str(&rest[..start]);
+        let arguments_start = start + "var(".len();
+        let arguments_end = arguments_start + closing_parenthesis(&rest[arguments_start..])?;
+        let arguments = &rest[arguments_start..arguments_end];
+
+        let (name, fallback) = match arguments.split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (arguments.trim(), None),
+        };
+        let value = match lookup(name) {
+            Some(value) => value,
+            None => substitute_with(fallback?, lookup)?,
+        };
+        result.push_str(&value);
+        rest = &rest[arguments_end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+fn find_ignore_case(text: &str, pattern: &str) -> Option<usize> {
+    text.to_ascii_lowercase().find(pattern)
+}
+
+/// Position of the parenthesis that closes the group `text` starts inside of.
+fn closing_parenthesis(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(index),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
@@ -1,58 +1,294 @@
-use robinson_css::{Value, StyleSheet, CssRule, Selector, SimpleSelector, Specificity, NormalRule, Color};
+use robinson_css::{Value, StyleSheet, CssRule, Selector, SimpleSelector, Specificity, NormalRule, Color, Combinator, Origin, Declaration};
 use robinson_dom::{Node, Element};
-use std::{cell::RefCell, rc::Rc, collections::HashMap};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+use std::{collections::HashMap, sync::Arc};
+
+pub use properties::*;
+pub use sharing::SharingStats;
+
+use rule_index::RuleIndex;
+use sharing::{SharedStyle, StyleSharingCache};
+
+mod custom_properties;
+mod properties;
+mod rule_index;
+mod sharing;
 
 pub type PropertyMap = HashMap<String, Value>;
 
+/// Default styles for HTML elements, applied beneath every page stylesheet.
+static USER_AGENT_STYLESHEET: Lazy<StyleSheet> = Lazy::new(|| {
+    StyleSheet::parse_with_origin(include_str!("ua.css"), Origin::UserAgent)
+        .expect("user-agent stylesheet is valid")
+});
+
+/// Where a declaration ranks in the cascade. Declarations that sort higher win.
+///
+/// Fields are compared in order, so origin and importance outrank the `style` attribute, which
+/// outranks specificity, which outranks source order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct CascadePriority {
+    level: CascadeLevel,
+    style_attribute: bool,
+    specificity: Specificity,
+    /// Index of the stylesheet, and of the rule within it.
+    source_order: (usize, usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum CascadeLevel {
+    UserAgent,
+    User,
+    Author,
+    ImportantAuthor,
+    ImportantUser,
+    ImportantUserAgent,
+}
+
+impl CascadeLevel {
+    /// Important declarations reverse the order of the origins, so that users and the user
+    /// agent can override pages.
+    fn new(origin: Origin, important: bool) -> Self {
+        match (origin, important) {
+            (Origin::UserAgent, false) => CascadeLevel::UserAgent,
+            (Origin::User, false) => CascadeLevel::User,
+            (Origin::Author, false) => CascadeLevel::Author,
+            (Origin::Author, true) => CascadeLevel::ImportantAuthor,
+            (Origin::User, true) => CascadeLevel::ImportantUser,
+            (Origin::UserAgent, true) => CascadeLevel::ImportantUserAgent,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Display {
     Block,
     Inline,
     InlineBlock,
+    Flex,
+    InlineFlex,
+    Grid,
+    InlineGrid,
     Table,
+    InlineTable,
     TableRowGroup,
+    TableHeaderGroup,
+    TableFooterGroup,
     TableRow,
+    TableColumnGroup,
+    TableColumn,
     TableCell,
+    TableCaption,
     ListItem,
     None,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Position {
+    #[default]
+    Static,
+    Relative,
+    Absolute,
+    Fixed,
+    Sticky,
+}
+
+impl Position {
+    /// Whether a box with this position is taken out of normal flow.
+    pub fn is_absolute(self) -> bool {
+        matches!(self, Position::Absolute | Position::Fixed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Float {
+    Left,
+    Right,
+    None,
+}
+
+/// The sides of earlier floats that an element must be placed below.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Clear {
+    Left,
+    Right,
+    Both,
+    None,
+}
+
+/// What happens to content that doesn't fit in an element's box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    #[default]
+    Visible,
+    Hidden,
+    Clip,
+    Scroll,
+    Auto,
+}
+
+impl Overflow {
+    /// Whether content is clipped to the element's padding box. Nothing can be scrolled, so
+    /// `scroll` and `auto` clip like `hidden`.
+    pub fn clips(self) -> bool {
+        self != Overflow::Visible
+    }
+}
+
 #[derive(Debug)]
 pub struct StyleNode {
     pub node: Node,
-    pub specified_values: PropertyMap,
-    pub children: RefCell<Vec<Rc<StyleNode>>>,
+    /// Declarations that matched the node itself, after the cascade.
+    ///
+    /// Nodes that are guaranteed to style identically share their values.
+    pub specified_values: Arc<PropertyMap>,
+    /// Specified values with inheritance and the `inherit`/`initial`/`unset` keywords applied.
+    pub computed_values: Arc<PropertyMap>,
+    pub children: Vec<Arc<StyleNode>>,
 }
 
 #[derive(Debug)]
 pub struct StyleTree {
-    pub root: RefCell<Rc<StyleNode>>,
+    pub root: Arc<StyleNode>,
+    /// How often nodes reused the style of an equivalent node.
+    pub sharing_stats: SharingStats,
+}
+
+/// State shared by every node while building a style tree.
+struct StyleContext<'a> {
+    rule_indices: Vec<RuleIndex<'a>>,
+    sharing_cache: StyleSharingCache,
+}
+
+impl<'a> StyleContext<'a> {
+    fn new(stylesheets: &'a [StyleSheet]) -> Self {
+        Self::with_rule_index(stylesheets, RuleIndex::new)
+    }
+
+    fn with_rule_index(stylesheets: &'a [StyleSheet], rule_index: fn(&'a StyleSheet) -> RuleIndex<'a>) -> Self {
+        let stylesheets = || std::iter::once(&*USER_AGENT_STYLESHEET).chain(stylesheets);
+        Self {
+            rule_indices: stylesheets().map(rule_index).collect(),
+            sharing_cache: StyleSharingCache::new(stylesheets()),
+        }
+    }
+}
+
+/// An element being matched, along with its place in the document so that combinators can
+/// walk to its parent and previous siblings.
+#[derive(Clone, Copy)]
+struct ElementRef<'a> {
+    element: &'a Element,
+    /// The children of the element's parent (or just the element itself, for the root).
+    siblings: &'a [Node],
+    /// Position of the element within `siblings`.
+    index: usize,
+    parent: Option<&'a ElementRef<'a>>,
+}
+
+impl<'a> ElementRef<'a> {
+    fn parent(&self) -> Option<ElementRef<'a>> {
+        self.parent.copied()
+    }
+
+    fn ancestors(&self) -> impl Iterator<Item = ElementRef<'a>> {
+        std::iter::successors(self.parent(), |element| element.parent())
+    }
+
+    /// Element siblings that come before this one, nearest first.
+    fn previous_siblings(&self) -> impl Iterator<Item = ElementRef<'a>> + '_ {
+        self.siblings[..self.index]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(move |(index, node)| {
+                node.element().map(|element| ElementRef { element, index, ..*self })
+            })
+    }
 }
 
 impl StyleNode {
-    pub fn new(node: &Node, stylesheets: &Vec<StyleSheet>) -> Rc<Self> {
-        Rc::new(Self {
+    pub fn new(node: &Node, stylesheets: &[StyleSheet]) -> Arc<Self> {
+        let context = StyleContext::new(stylesheets);
+        Self::build(std::slice::from_ref(node), 0, None, None, None, &context)
+    }
+
+    /// The style of an anonymous box generated around some of this node's children, such as a
+    /// table row around cells that aren't in one. It inherits from this node, and only sets
+    /// `display`.
+    pub fn anonymous(&self, display: &str) -> Arc<Self> {
+        let specified_values = HashMap::from([("display".to_string(), Value::Keyword(display.to_string()))]);
+        let computed_values = computed_values(&specified_values, Some(&self.computed_values), None);
+        Arc::new(Self {
+            node: Node::Element(Element::default()),
+            specified_values: Arc::new(specified_values),
+            computed_values: Arc::new(computed_values),
+            children: Vec::new(),
+        })
+    }
+
+    /// Style `siblings[index]` and its descendants, where `parent` is the element containing
+    /// `siblings`, `parent_values` are its computed values and `root_values` are those of the
+    /// root element.
+    fn build(
+        siblings: &[Node],
+        index: usize,
+        parent: Option<&ElementRef>,
+        parent_values: Option<&Arc<PropertyMap>>,
+        root_values: Option<&Arc<PropertyMap>>,
+        context: &StyleContext,
+    ) -> Arc<Self> {
+        let node = &siblings[index];
+        let element = node.element().map(|element| ElementRef {
+            element,
+            siblings,
+            index,
+            parent,
+        });
+
+        let style = context.sharing_cache.get_or_insert_with(node.element(), parent_values, || {
+            let specified_values = match &element {
+                Some(element) => specified_values(element, &context.rule_indices),
+                None => HashMap::new()
+            };
+            let computed_values = computed_values(
+                &specified_values,
+                parent_values.map(|values| &**values),
+                root_values.map(|values| &**values),
+            );
+            SharedStyle {
+                specified_values: Arc::new(specified_values),
+                computed_values: Arc::new(computed_values),
+            }
+        });
+
+        // Siblings only depend on their parent and on the DOM, so their subtrees are styled in
+        // parallel on the current rayon thread pool.
+        let root_values = root_values.unwrap_or(&style.computed_values);
+        let children = element
+            .as_ref()
+            .map(|element_ref| {
+                let children = &element_ref.element.children;
+                (0..children.len())
+                    .into_par_iter()
+                    .map(|index| {
+                        Self::build(children, index, Some(element_ref), Some(&style.computed_values), Some(root_values), context)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Arc::new(Self {
             node: node.clone(),
-            specified_values: match node {
-                Node::Element(elem) => specified_values(elem, stylesheets),
-                Node::Text(_) | Node::Comment(_) => HashMap::new()
-            },
-            children: RefCell::new(node
-                .element()
-                .map(|element| {
-                    element
-                        .children
-                        .iter()
-                        .map(|child| Self::new(child, stylesheets))
-                        .collect()
-                })
-                .unwrap_or_else(Vec::new),
-            ),
+            specified_values: style.specified_values,
+            computed_values: style.computed_values,
+            children,
         })
     }
 
     pub fn get_value(&self, name: &str) -> Option<Value> {
-        self.specified_values.get(name).cloned()
+        self.computed_values.get(name).cloned()
     }
 
     pub fn lookup(&self, name: &str, default: &Value) -> Value {
@@ -60,12 +296,6 @@ impl StyleNode {
             .unwrap_or_else(|| default.clone())
     }
 
-    pub fn lookup_with_fallback(&self, name: &str, fallback_name: &str, default: &Value) -> Value {
-        self.get_value(name)
-            .or_else(|| self.get_value(fallback_name))
-            .unwrap_or_else(|| default.clone())
-    }
-
     pub fn display(&self) -> Display {
         if matches!(self.node, Node::Text(_)) {
             return Display::Inline;
@@ -77,10 +307,20 @@ impl StyleNode {
                     "block" => Display::Block,
                     "none" => Display::None,
                     "inline-block" => Display::InlineBlock,
+                    "flex" => Display::Flex,
+                    "inline-flex" => Display::InlineFlex,
+                    "grid" => Display::Grid,
+                    "inline-grid" => Display::InlineGrid,
                     "table" => Display::Table,
+                    "inline-table" => Display::InlineTable,
                     "table-row-group" => Display::TableRowGroup,
+                    "table-header-group" => Display::TableHeaderGroup,
+                    "table-footer-group" => Display::TableFooterGroup,
                     "table-row" => Display::TableRow,
+                    "table-column-group" => Display::TableColumnGroup,
+                    "table-column" => Display::TableColumn,
                     "table-cell" => Display::TableCell,
+                    "table-caption" => Display::TableCaption,
                     "list-item" => Display::ListItem,
                     _ => Display::Inline,
                 }),
@@ -89,6 +329,64 @@ impl StyleNode {
             .unwrap_or(Display::Inline)
     }
 
+    pub fn position(&self) -> Position {
+        match self.get_value("position") {
+            Some(Value::Keyword(s)) => match &*s {
+                "relative" => Position::Relative,
+                "absolute" => Position::Absolute,
+                "fixed" => Position::Fixed,
+                "sticky" => Position::Sticky,
+                _ => Position::Static,
+            },
+            _ => Position::Static,
+        }
+    }
+
+    /// The side an element floats to. Absolutely positioned elements don't float.
+    pub fn float(&self) -> Float {
+        if self.position().is_absolute() {
+            return Float::None;
+        }
+        match self.get_value("float") {
+            Some(Value::Keyword(s)) if s == "left" => Float::Left,
+            Some(Value::Keyword(s)) if s == "right" => Float::Right,
+            _ => Float::None,
+        }
+    }
+
+    /// The stack level given by `z-index`, or `None` if it is `auto`.
+    pub fn z_index(&self) -> Option<i32> {
+        match self.get_value("z-index") {
+            Some(Value::Number(z_index)) if z_index.fract() == 0.0 => Some(z_index as i32),
+            _ => None,
+        }
+    }
+
+    pub fn clear(&self) -> Clear {
+        match self.get_value("clear") {
+            Some(Value::Keyword(s)) => match &*s {
+                "left" => Clear::Left,
+                "right" => Clear::Right,
+                "both" => Clear::Both,
+                _ => Clear::None,
+            },
+            _ => Clear::None,
+        }
+    }
+
+    pub fn overflow(&self) -> Overflow {
+        match self.get_value("overflow") {
+            Some(Value::Keyword(s)) => match &*s {
+                "hidden" => Overflow::Hidden,
+                "clip" => Overflow::Clip,
+                "scroll" => Overflow::Scroll,
+                "auto" => Overflow::Auto,
+                _ => Overflow::Visible,
+            },
+            _ => Overflow::Visible,
+        }
+    }
+
     pub fn get_color(&self, name: &str) -> Option<Color> {
         self.get_value(name)
             .and_then(|value| match value {
@@ -99,64 +397,124 @@ impl StyleNode {
 }
 
 impl StyleTree {
-    pub fn new(node: &Node, stylesheets: &Vec<StyleSheet>) -> Self {
+    pub fn new(node: &Node, stylesheets: &[StyleSheet]) -> Self {
+        Self::build(node, StyleContext::new(stylesheets))
+    }
+
+    /// Style the tree testing every element against every rule, without indexing the rules
+    /// first. This is only useful as a baseline when measuring the index.
+    pub fn new_without_rule_index(node: &Node, stylesheets: &[StyleSheet]) -> Self {
+        Self::build(node, StyleContext::with_rule_index(stylesheets, RuleIndex::linear))
+    }
+
+    fn build(node: &Node, context: StyleContext) -> Self {
+        let root = StyleNode::build(std::slice::from_ref(node), 0, None, None, None, &context);
         Self {
-            root: RefCell::new(StyleNode::new(node, stylesheets))
+            root,
+            sharing_stats: context.sharing_cache.stats(),
         }
     }
 }
 
 /// Apply styles to a single element, returning the specified styles.
-fn specified_values(elem: &Element, stylesheets: &Vec<StyleSheet>) -> PropertyMap {
-    let mut values = HashMap::new();
-    let mut rules = Vec::new();
-    for stylesheet in stylesheets {
-        rules.extend(matching_rules(elem, stylesheet));
+fn specified_values(elem: &ElementRef, rule_indices: &[RuleIndex]) -> PropertyMap {
+    let mut declarations: Vec<(CascadePriority, &Declaration)> = Vec::new();
+    for (sheet_index, index) in rule_indices.iter().enumerate() {
+        for (rule_index, specificity, rule) in matching_rules(elem, index) {
+            declarations.extend(rule.declarations.iter().map(|declaration| {
+                let priority = CascadePriority {
+                    level: CascadeLevel::new(index.stylesheet.origin, declaration.important),
+                    style_attribute: false,
+                    specificity,
+                    source_order: (sheet_index, rule_index),
+                };
+                (priority, declaration)
+            }));
+        }
     }
 
-    // Sort the matched rules by specificity, highest to lowest.
-    rules.sort_by(|&(a, _), &(b, _)| b.cmp(&a));
+    // Declarations in the `style` attribute take precedence over every author rule of the
+    // same importance.
+    let style_attribute = style_attribute(elem.element);
+    declarations.extend(style_attribute.iter().flatten().map(|declaration| {
+        let priority = CascadePriority {
+            level: CascadeLevel::new(Origin::Author, declaration.important),
+            style_attribute: true,
+            specificity: (0, 0, 0),
+            source_order: (0, 0),
+        };
+        (priority, declaration)
+    }));
 
-    for (_, rule) in rules {
-        for declaration in &rule.declarations {
-            values.insert(declaration.0.clone(), declaration.1.clone());
-        }
+    // Apply the declarations from lowest to highest priority. The sort is stable, so
+    // declarations within a rule are applied in the order they were written.
+    declarations.sort_by_key(|&(priority, _)| priority);
+
+    // Shorthands were expanded when parsing, so every declaration sets a single longhand.
+    let mut values = HashMap::new();
+    for (_, declaration) in declarations {
+        values.insert(declaration.name.clone(), declaration.value.clone());
     }
     values
 }
 
-/// A single CSS rule and the specificity of its most specific matching selector.
-type MatchedRule<'a> = (Specificity, &'a NormalRule);
+/// Parse the declarations in an element's `style` attribute, ignoring it if it's invalid.
+fn style_attribute(elem: &Element) -> Option<Vec<Declaration>> {
+    let style = elem.attributes.get("style")?.as_deref()?;
+    StyleSheet::parse_declarations(style).ok()
+}
+
+/// A single CSS rule, its index in the stylesheet and the specificity of its most specific
+/// matching selector.
+type MatchedRule<'a> = (usize, Specificity, &'a NormalRule);
 
 /// Find all CSS rules that match the given element.
-fn matching_rules<'a>(elem: &Element, stylesheet: &'a StyleSheet) -> Vec<MatchedRule<'a>> {
-    // For now, we just do a linear scan of all the rules.  For large
-    // documents, it would be more efficient to store the rules in hash tables
-    // based on tag name, id, class, etc.
-    stylesheet
-        .rules
-        .iter()
-        .flat_map(|rule| match rule {
-            CssRule::Normal(norm) => Some(norm),
+fn matching_rules<'a>(elem: &ElementRef, rule_index: &RuleIndex<'a>) -> Vec<MatchedRule<'a>> {
+    // Only the rules filed under the element's id, classes or tag name (and the universal ones)
+    // can match, so the rest of the stylesheet is skipped.
+    let rules = &rule_index.stylesheet.rules;
+    rule_index
+        .candidates(elem.element)
+        .into_iter()
+        .flat_map(|index| match &rules[index] {
+            CssRule::Normal(norm) => Some((index, norm)),
             _ => None
         })
-        .filter_map(|rule| match_rule(elem, rule).map(|spec| (spec, rule)))
+        .filter_map(|(index, rule)| match_rule(elem, rule).map(|spec| (index, spec, rule)))
         .collect()
 }
 
 /// If `rule` matches `elem`, return a `MatchedRule`. Otherwise return `None`.
-fn match_rule(elem: &Element, rule: &NormalRule) -> Option<Specificity> {
-    // Find the first (most specific) matching selector.
+fn match_rule(elem: &ElementRef, rule: &NormalRule) -> Option<Specificity> {
+    // Find the most specific matching selector.
     rule.selectors
         .iter()
-        .find(|selector| matches(elem, selector))
-        .map(|selector| selector.specificity().unwrap())
+        .filter(|selector| matches(elem, selector))
+        .filter_map(|selector| selector.specificity())
+        .max()
 }
 
 /// Selector matching:
-fn matches(elem: &Element, selector: &Selector) -> bool {
+fn matches(elem: &ElementRef, selector: &Selector) -> bool {
     match *selector {
-        Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector)
+        Selector::Simple(ref simple_selector) => matches_simple_selector(elem.element, simple_selector),
+        Selector::Complex { ref left, combinator, ref right } => {
+            if !matches_simple_selector(elem.element, right) {
+                return false;
+            }
+
+            match combinator {
+                Combinator::Descendant => elem.ancestors().any(|ancestor| matches(&ancestor, left)),
+                Combinator::Child => elem.parent().is_some_and(|parent| matches(&parent, left)),
+                Combinator::NextSibling => elem
+                    .previous_siblings()
+                    .next()
+                    .is_some_and(|sibling| matches(&sibling, left)),
+                Combinator::SubsequentSibling => elem
+                    .previous_siblings()
+                    .any(|sibling| matches(&sibling, left)),
+            }
+        }
     }
 }
 
@@ -0,0 +1,294 @@
+//! Per-property data needed by the cascade: which properties inherit and their initial values.
+//!
+//! Computing values also resolves font-relative and absolute lengths into px. Percentages and
+//! viewport units depend on layout and are left for it to resolve.
+
+use robinson_css::{expand_shorthand, Color, StyleSheet, Unit, Value};
+
+use crate::custom_properties::{is_custom_property, resolve_custom_properties, substitute};
+use crate::PropertyMap;
+
+/// Initial value of `font-size` (`medium`), in px.
+pub const MEDIUM_FONT_SIZE: f32 = 16.0;
+
+/// Properties whose value is taken from the parent element when the element doesn't specify one.
+const INHERITED_PROPERTIES: &[&str] = &[
+    "border-collapse",
+    "border-spacing",
+    "caption-side",
+    "color",
+    "cursor",
+    "direction",
+    "font",
+    "font-family",
+    "font-size",
+    "font-style",
+    "font-variant",
+    "font-weight",
+    "letter-spacing",
+    "line-height",
+    "list-style",
+    "list-style-position",
+    "list-style-type",
+    "text-align",
+    "text-indent",
+    "text-transform",
+    "visibility",
+    "white-space",
+    "word-spacing",
+];
+
+/// Whether a property inherits. Every custom property does.
+pub fn is_inherited(name: &str) -> bool {
+    INHERITED_PROPERTIES.contains(&name) || is_custom_property(name)
+}
+
+/// The initial value of a property, or `None` if it isn't known, in which case the property is
+/// left unset and layout falls back to its own default.
+pub fn initial_value(name: &str) -> Option<Value> {
+    let keyword = |keyword: &str| Some(Value::Keyword(keyword.to_string()));
+    let zero = Some(Value::Length(0.0, Unit::Px));
+
+    match name {
+        "display" => keyword("inline"),
+        "position" => keyword("static"),
+        "float" | "clear" => keyword("none"),
+        "z-index" | "align-self" | "flex-basis" => keyword("auto"),
+        "flex-direction" => keyword("row"),
+        "flex-wrap" => keyword("nowrap"),
+        "justify-content" | "align-items" | "align-content" | "row-gap" | "column-gap" => keyword("normal"),
+        "flex-grow" | "order" => Some(Value::Number(0.0)),
+        "flex-shrink" => Some(Value::Number(1.0)),
+        "grid-template-columns" | "grid-template-rows" => keyword("none"),
+        "grid-auto-columns" | "grid-auto-rows" | "justify-self" => keyword("auto"),
+        "grid-row-start" | "grid-row-end" | "grid-column-start" | "grid-column-end" => keyword("auto"),
+        "grid-auto-flow" => keyword("row"),
+        "justify-items" => keyword("legacy"),
+        "table-layout" => keyword("auto"),
+        "border-collapse" => keyword("separate"),
+        "border-spacing" => zero,
+        "caption-side" => keyword("top"),
+        "vertical-align" => keyword("baseline"),
+        "visibility" | "overflow" => keyword("visible"),
+        "color" => Some(Value::Color(Color { r: 0, g: 0, b: 0, a: 255 })),
+        "font-size" => Some(Value::Length(MEDIUM_FONT_SIZE, Unit::Px)),
+        "font-style" | "font-weight" | "font-variant" | "line-height" | "white-space" => keyword("normal"),
+        "text-align" => keyword("left"),
+        "width" | "height" | "top" | "right" | "bottom" | "left" => keyword("auto"),
+        "min-width" | "min-height" => zero,
+        "max-width" | "max-height" => keyword("none"),
+        "box-sizing" => keyword("content-box"),
+        "margin-top" | "margin-right" | "margin-bottom" | "margin-left" => zero,
+        "padding-top" | "padding-right" | "padding-bottom" | "padding-left" => zero,
+        "border-top-width" | "border-right-width" | "border-bottom-width" | "border-left-width" => keyword("medium"),
+        "border-top-style" | "border-right-style" | "border-bottom-style" | "border-left-style" => keyword("none"),
+        "border-top-color" | "border-right-color" | "border-bottom-color" | "border-left-color" => keyword("currentcolor"),
+        "background-color" => Some(Value::Color(Color { r: 0, g: 0, b: 0, a: 0 })),
+        _ => None,
+    }
+}
+
+/// Resolve the cascaded values of an element (or text node) into computed values.
+///
+/// Inherited properties the element doesn't specify are copied from `parent`, and the `inherit`,
+/// `initial` and `unset` keywords are replaced by the values they stand for, and `var()`
+/// references by the custom properties they name. `em` and `rem`
+/// lengths are resolved against the font sizes of the element and of `root`, the computed values
+/// of the root element (`None` when computing the root itself).
+pub fn computed_values(
+    specified: &PropertyMap,
+    parent: Option<&PropertyMap>,
+    root: Option<&PropertyMap>,
+) -> PropertyMap {
+    let inherit = |name: &str| {
+        parent
+            .and_then(|parent| parent.get(name).cloned())
+            .or_else(|| initial_value(name))
+    };
+
+    let mut values: PropertyMap = parent
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| is_inherited(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    let cascade = |values: &mut PropertyMap, name: &String, value: &Value| {
+        let value = match value {
+            Value::Keyword(keyword) if keyword == "inherit" => inherit(name),
+            Value::Keyword(keyword) if keyword == "initial" => initial_value(name),
+            Value::Keyword(keyword) if keyword == "unset" => {
+                if is_inherited(name) {
+                    inherit(name)
+                } else {
+                    initial_value(name)
+                }
+            }
+            value => Some(value.clone()),
+        };
+
+        match value {
+            Some(value) => values.insert(name.clone(), value),
+            None => values.remove(name),
+        };
+    };
+
+    // Custom properties are resolved first, since other values can refer to them.
+    for (name, value) in specified.iter().filter(|(name, _)| is_custom_property(name)) {
+        cascade(&mut values, name, value);
+    }
+    resolve_custom_properties(&mut values);
+
+    for (name, value) in specified.iter().filter(|(name, _)| !is_custom_property(name)) {
+        // A value whose references can't be substituted is invalid at computed-value time, and
+        // behaves as `unset`.
+        let value = match value {
+            Value::Unparsed(text) => substitute(text, &values)
+                .and_then(|text| StyleSheet::parse_value(&text).ok()),
+            Value::PendingShorthand { shorthand, text } => substitute(text, &values)
+                .and_then(|text| StyleSheet::parse_value(&text).ok())
+                .and_then(|value| expand_shorthand(shorthand, &value))
+                .and_then(|longhands| longhands.into_iter().find(|(longhand, _)| longhand == name))
+                .map(|(_, value)| value),
+            value => Some(value.clone()),
+        };
+        let value = value.unwrap_or_else(|| Value::Keyword("unset".to_string()));
+        cascade(&mut values, name, &value);
+    }
+
+    compute_borders(&mut values);
+
+    // `font-size` is resolved first, since the other lengths are relative to it.
+    let parent_font_size = parent.map_or(MEDIUM_FONT_SIZE, font_size);
+    let root_font_size = root.map(font_size);
+    if let Some(size) = values.get_mut("font-size") {
+        *size = computed_font_size(size, parent_font_size, root_font_size.unwrap_or(MEDIUM_FONT_SIZE));
+    }
+
+    let font_size = font_size(&values);
+    let root_font_size = root_font_size.unwrap_or(font_size);
+    let current_color = values.get("color").cloned().or_else(|| initial_value("color"));
+    let to_px = |length, unit| absolute_length(length, unit, font_size, root_font_size);
+    for (name, value) in values.iter_mut() {
+        match value {
+            // Percentages of `line-height` refer to the element's own font size, and are
+            // inherited as lengths rather than as percentages.
+            Value::Length(percent, Unit::Percent) if name == "line-height" => {
+                *value = Value::Length(*percent / 100.0 * font_size, Unit::Px);
+            }
+            // `currentcolor` is the value of `color`.
+            Value::Keyword(keyword) if keyword.eq_ignore_ascii_case("currentcolor") => {
+                if let Some(color) = current_color.clone() {
+                    *value = color;
+                }
+            }
+            value => compute_lengths(value, &to_px),
+        }
+    }
+    values
+}
+
+/// Convert the lengths in a value to px where `to_px` can, including those in lists and functions
+/// such as the tracks of a grid.
+fn compute_lengths(value: &mut Value, to_px: &impl Fn(f32, Unit) -> Option<f32>) {
+    match value {
+        Value::Length(length, unit) => {
+            if let Some(px) = to_px(*length, *unit) {
+                *value = Value::Length(px, Unit::Px);
+            }
+        }
+        // Math functions are evaluated now if they only involve font-relative and absolute
+        // lengths, and otherwise kept for layout with those lengths converted.
+        Value::Calc(calc) => {
+            *value = match calc.evaluate(to_px) {
+                Some(value) => value,
+                None => Value::Calc(calc.map_lengths(to_px)),
+            };
+        }
+        Value::List(values, _) | Value::Function(_, values) => {
+            values.iter_mut().for_each(|value| compute_lengths(value, to_px));
+        }
+        _ => {}
+    }
+}
+
+/// Resolve the border width keywords, and the width of borders without a style, which is zero.
+/// Borders without a color are given `currentcolor`, which is resolved with the other values.
+///
+/// https://www.w3.org/TR/css-backgrounds-3/#border-width
+fn compute_borders(values: &mut PropertyMap) {
+    for side in ["top", "right", "bottom", "left"] {
+        values
+            .entry(format!("border-{side}-color"))
+            .or_insert_with(|| Value::Keyword("currentcolor".to_string()));
+
+        let width_name = format!("border-{side}-width");
+        let has_style = matches!(
+            values.get(&format!("border-{side}-style")),
+            Some(Value::Keyword(style)) if style != "none" && style != "hidden"
+        );
+        let width = match values.get(&width_name) {
+            _ if !has_style => 0.0,
+            Some(Value::Keyword(width)) if width == "thin" => 1.0,
+            Some(Value::Keyword(width)) if width == "thick" => 5.0,
+            None | Some(Value::Keyword(_)) => 3.0,
+            Some(_) => continue,
+        };
+        values.insert(width_name, Value::Length(width, Unit::Px));
+    }
+}
+
+/// The computed `font-size` of an element in px, assuming it has been resolved already.
+fn font_size(values: &PropertyMap) -> f32 {
+    match values.get("font-size") {
+        Some(Value::Length(size, Unit::Px)) => *size,
+        _ => MEDIUM_FONT_SIZE,
+    }
+}
+
+/// Resolve a `font-size` value, where relative sizes refer to the parent's font size.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-size-prop
+fn computed_font_size(value: &Value, parent_font_size: f32, root_font_size: f32) -> Value {
+    let relative_font_size = |length: f32, unit| match unit {
+        Unit::Percent => Some(length / 100.0 * parent_font_size),
+        unit => absolute_length(length, unit, parent_font_size, root_font_size),
+    };
+    let px = match value {
+        Value::Keyword(keyword) => match keyword.as_str() {
+            "xx-small" => Some(MEDIUM_FONT_SIZE * 3.0 / 5.0),
+            "x-small" => Some(MEDIUM_FONT_SIZE * 3.0 / 4.0),
+            "small" => Some(MEDIUM_FONT_SIZE * 8.0 / 9.0),
+            "medium" => Some(MEDIUM_FONT_SIZE),
+            "large" => Some(MEDIUM_FONT_SIZE * 6.0 / 5.0),
+            "x-large" => Some(MEDIUM_FONT_SIZE * 3.0 / 2.0),
+            "xx-large" => Some(MEDIUM_FONT_SIZE * 2.0),
+            "larger" => Some(parent_font_size * 1.2),
+            "smaller" => Some(parent_font_size / 1.2),
+            _ => None,
+        },
+        Value::Number(0.0) => Some(0.0),
+        Value::Length(length, unit) => relative_font_size(*length, *unit),
+        Value::Calc(calc) => match calc.evaluate(&relative_font_size) {
+            Some(Value::Length(px, _)) => Some(px),
+            _ => None,
+        },
+        _ => None,
+    };
+    match px {
+        Some(px) => Value::Length(px, Unit::Px),
+        None => value.clone(),
+    }
+}
+
+/// Convert a length to px, unless it depends on layout (percentages and viewport units).
+///
+/// There are no font metrics here, so `ex` and `ch` use the common fallback of half an `em`.
+fn absolute_length(length: f32, unit: Unit, font_size: f32, root_font_size: f32) -> Option<f32> {
+    match unit {
+        Unit::Em => Some(length * font_size),
+        Unit::Ex | Unit::Ch => Some(length * font_size / 2.0),
+        Unit::Rem => Some(length * root_font_size),
+        unit => unit.absolute_px().map(|px| length * px),
+    }
+}
@@ -0,0 +1,88 @@
+//! Bucketing of stylesheet rules so that each element is only tested against rules that could
+//! match it.
+
+use std::collections::HashMap;
+
+use robinson_css::{CssRule, StyleSheet};
+use robinson_dom::Element;
+
+/// A stylesheet's rules, indexed by the id, class or tag name that their selectors require the
+/// matched element to have.
+///
+/// Each selector is filed under a single key taken from its rightmost simple selector, preferring
+/// the most selective one: its id, then its first class, then its tag name. Selectors that
+/// require none of these go in the universal bucket.
+pub struct RuleIndex<'a> {
+    pub stylesheet: &'a StyleSheet,
+    by_id: HashMap<&'a str, Vec<usize>>,
+    by_class: HashMap<&'a str, Vec<usize>>,
+    by_tag_name: HashMap<&'a str, Vec<usize>>,
+    universal: Vec<usize>,
+}
+
+impl<'a> RuleIndex<'a> {
+    pub fn new(stylesheet: &'a StyleSheet) -> Self {
+        let mut index = Self::empty(stylesheet);
+        for (rule_index, rule) in stylesheet.rules.iter().enumerate() {
+            let CssRule::Normal(rule) = rule else { continue };
+            for selector in &rule.selectors {
+                let subject = selector.subject();
+                let bucket = if let Some(id) = &subject.id {
+                    index.by_id.entry(id).or_default()
+                } else if let Some(class) = subject.class.first() {
+                    index.by_class.entry(class).or_default()
+                } else if let Some(tag_name) = &subject.tag_name {
+                    index.by_tag_name.entry(tag_name).or_default()
+                } else {
+                    &mut index.universal
+                };
+
+                // A rule with several selectors under the same key only needs to be listed once.
+                if bucket.last() != Some(&rule_index) {
+                    bucket.push(rule_index);
+                }
+            }
+        }
+        index
+    }
+
+    /// An index that files every rule in the universal bucket, so that each element is tested
+    /// against the whole stylesheet.
+    pub fn linear(stylesheet: &'a StyleSheet) -> Self {
+        let mut index = Self::empty(stylesheet);
+        index.universal = (0..stylesheet.rules.len())
+            .filter(|&rule_index| matches!(stylesheet.rules[rule_index], CssRule::Normal(_)))
+            .collect();
+        index
+    }
+
+    fn empty(stylesheet: &'a StyleSheet) -> Self {
+        Self {
+            stylesheet,
+            by_id: HashMap::new(),
+            by_class: HashMap::new(),
+            by_tag_name: HashMap::new(),
+            universal: Vec::new(),
+        }
+    }
+
+    /// Indices of the rules that might match `elem`, in source order.
+    pub fn candidates(&self, elem: &Element) -> Vec<usize> {
+        let mut candidates = self.universal.clone();
+        if let Some(rules) = elem.id.as_deref().and_then(|id| self.by_id.get(id)) {
+            candidates.extend(rules);
+        }
+        for class in &elem.classes {
+            if let Some(rules) = self.by_class.get(&**class) {
+                candidates.extend(rules);
+            }
+        }
+        if let Some(rules) = self.by_tag_name.get(&*elem.name) {
+            candidates.extend(rules);
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
@@ -0,0 +1,169 @@
+//! Reuse of styles between elements that are guaranteed to style identically.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use robinson_css::{Combinator, CssRule, Selector, StyleSheet};
+use robinson_dom::Element;
+
+use crate::PropertyMap;
+
+/// The styles of a node, shared between every node that styles the same way.
+#[derive(Clone)]
+pub struct SharedStyle {
+    pub specified_values: Arc<PropertyMap>,
+    pub computed_values: Arc<PropertyMap>,
+}
+
+/// Everything selector matching and inheritance can depend on.
+///
+/// Two nodes whose parents share a style have the same inherited values and, by induction, the
+/// same tag names, ids and classes all the way up their ancestor chains. If they also have the
+/// same tag name, id and classes themselves, every selector without a sibling combinator
+/// matches both or neither.
+#[derive(PartialEq, Eq, Hash)]
+struct SharingKey {
+    /// Address of the parent's computed values.
+    parent: usize,
+    /// `None` for text and comment nodes.
+    name: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+}
+
+/// Hit and miss counts of the style sharing cache while building a style tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SharingStats {
+    /// Nodes that reused a style computed for an earlier node.
+    pub hits: usize,
+    /// Nodes whose style had to be computed.
+    pub misses: usize,
+}
+
+impl SharingStats {
+    /// Fraction of nodes that reused a style, between 0 and 1.
+    pub fn hit_rate(&self) -> f32 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f32 / total as f32,
+        }
+    }
+}
+
+impl fmt::Display for SharingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "style sharing: {} hits, {} misses ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
+        )
+    }
+}
+
+pub struct StyleSharingCache {
+    /// Sharing is turned off if any selector looks at siblings, since siblings differ between
+    /// otherwise identical elements.
+    enabled: bool,
+    entries: Mutex<HashMap<SharingKey, SharedStyle>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl StyleSharingCache {
+    pub fn new<'a>(stylesheets: impl IntoIterator<Item = &'a StyleSheet>) -> Self {
+        Self {
+            enabled: !stylesheets.into_iter().any(uses_sibling_combinators),
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Return the style of a node with the given parent, computing it with `compute` unless an
+    /// equivalent node has been styled already.
+    pub fn get_or_insert_with(
+        &self,
+        element: Option<&Element>,
+        parent_values: Option<&Arc<PropertyMap>>,
+        compute: impl FnOnce() -> SharedStyle,
+    ) -> SharedStyle {
+        let key = match parent_values {
+            Some(parent_values) if self.enabled => sharing_key(element, parent_values),
+            _ => None,
+        };
+        let Some(key) = key else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return compute();
+        };
+
+        if let Some(style) = self.entries.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return style.clone();
+        }
+
+        // Compute without holding the lock so that other threads aren't blocked meanwhile.
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let style = compute();
+        self.entries.lock().unwrap().insert(key, style.clone());
+        style
+    }
+
+    pub fn stats(&self) -> SharingStats {
+        SharingStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// The cache key of a node, or `None` if its style can't be shared.
+fn sharing_key(element: Option<&Element>, parent_values: &Arc<PropertyMap>) -> Option<SharingKey> {
+    let parent = Arc::as_ptr(parent_values) as usize;
+    let Some(element) = element else {
+        return Some(SharingKey {
+            parent,
+            name: None,
+            id: None,
+            classes: Vec::new(),
+        });
+    };
+
+    // Declarations in a `style` attribute only apply to their own element.
+    if element.attributes.contains_key("style") {
+        return None;
+    }
+
+    let mut classes = element.classes.clone();
+    classes.sort_unstable();
+    Some(SharingKey {
+        parent,
+        name: Some(element.name.clone()),
+        id: element.id.clone(),
+        classes,
+    })
+}
+
+fn uses_sibling_combinators(stylesheet: &StyleSheet) -> bool {
+    fn has_sibling_combinator(selector: &Selector) -> bool {
+        match selector {
+            Selector::Simple(_) => false,
+            Selector::Complex { left, combinator, .. } => {
+                matches!(combinator, Combinator::NextSibling | Combinator::SubsequentSibling)
+                    || has_sibling_combinator(left)
+            }
+        }
+    }
+
+    stylesheet.rules.iter().any(|rule| match rule {
+        CssRule::Normal(rule) => rule.selectors.iter().any(has_sibling_combinator),
+        _ => false,
+    })
+}
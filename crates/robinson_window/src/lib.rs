@@ -8,8 +8,9 @@ use winit::{
 };
 
 mod state;
+mod text;
 
-pub async fn create_window(title: &str, root_node: &Node, stylesheets: &Vec<StyleSheet>) {
+pub async fn create_window(title: &str, root_node: &Node, stylesheets: &[StyleSheet]) {
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
         .with_title(title)
@@ -27,18 +28,13 @@ pub async fn create_window(title: &str, root_node: &Node, stylesheets: &Vec<Styl
             WindowEvent::KeyboardInput {
                 input: KeyboardInput {
                     // state,
-                    virtual_keycode: Some(keycode),
+                    virtual_keycode: Some(VirtualKeyCode::P),
                     ..
                 },
                 ..
             } => {
-                match keycode {
-                    VirtualKeyCode::P => {
-                        // Make the pdf (temp keybind).
-                        state.print_pdf().unwrap();
-                    }
-                    _ => {}
-                }
+                // Make the pdf (temp keybind).
+                state.print_pdf().unwrap();
             },
             _ => (),
         },
@@ -1,14 +1,16 @@
-use std::{fs::File, io::{BufWriter, self}};
+use std::{fs::File, io::{BufWriter, self}, ops::Range};
 
 use glam::{Mat4, Vec3};
 use robinson_css::StyleSheet;
 use robinson_dom::Node;
 use robinson_layout::{Dimensions, Rect, RenderTree};
-use robinson_paint::{build_display_list, Canvas, SolidColor};
+use robinson_paint::{build_display_list, Canvas, DisplayItem, SolidColor};
 use robinson_style::StyleTree;
 use wgpu::util::DeviceExt;
 use winit::{dpi::PhysicalSize, window::Window};
 
+use crate::text::{normalize_tex_coords, push_text, GlyphAtlas, TextVertex};
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct Vertex {
@@ -31,6 +33,23 @@ impl Vertex {
     }
 }
 
+/// A range of consecutive display items drawn with the same pipeline, or a change of the clip
+/// that the batches after it are drawn in.
+enum Batch {
+    Rects(Range<u32>),
+    Text(Range<u32>),
+    /// The clip in effect, or `None` to draw anywhere.
+    Clip(Option<Rect>),
+}
+
+/// Everything needed to draw a display list, in paint order.
+struct Geometry {
+    vertices: Vec<Vertex>,
+    text_vertices: Vec<TextVertex>,
+    atlas: GlyphAtlas,
+    batches: Vec<Batch>,
+}
+
 pub struct State {
     root_node: Node,
     stylesheets: Vec<StyleSheet>,
@@ -39,15 +58,20 @@ pub struct State {
     device: wgpu::Device,
     queue: wgpu::Queue,
     render_pipeline: wgpu::RenderPipeline,
+    text_pipeline: wgpu::RenderPipeline,
+    atlas_bind_group_layout: wgpu::BindGroupLayout,
+    atlas_sampler: wgpu::Sampler,
+    atlas_bind_group: wgpu::BindGroup,
     vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
+    text_vertex_buffer: wgpu::Buffer,
+    batches: Vec<Batch>,
     camera_uniform: [[f32; 4]; 4],
     camera_buffer: wgpu::Buffer,
     camera_bind_group: wgpu::BindGroup,
 }
 
 impl State {
-    pub async fn new(window: &Window, root_node: &Node, stylesheets: &Vec<StyleSheet>) -> Self {
+    pub async fn new(window: &Window, root_node: &Node, stylesheets: &[StyleSheet]) -> Self {
         let window_size = window.inner_size();
 
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
@@ -59,8 +83,7 @@ impl State {
 
         let adapter = instance
             .enumerate_adapters(wgpu::Backends::all())
-            .filter(|adapter| adapter.is_surface_supported(&surface))
-            .next()
+            .find(|adapter| adapter.is_surface_supported(&surface))
             .unwrap();
 
         let (device, queue) = adapter
@@ -84,8 +107,7 @@ impl State {
             .formats
             .iter()
             .copied()
-            .filter(|f| f.is_srgb())
-            .next()
+            .find(|f| f.is_srgb())
             .unwrap_or(surface_caps.formats[0]);
         let config = wgpu::SurfaceConfiguration {
             usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
@@ -180,6 +202,74 @@ impl State {
             multiview: None,
         });
 
+        let atlas_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+                label: Some("atlas_bind_group_layout"),
+            });
+
+        let atlas_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let text_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+        });
+
+        let text_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Text Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout, &atlas_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let text_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&text_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &text_shader,
+                entry_point: "vs_main",
+                buffers: &[TextVertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &text_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
         let canvas = Self::generate_canvas(
             window_size.width as f32,
             window_size.height as f32,
@@ -187,26 +277,31 @@ impl State {
             stylesheets,
         );
 
-        let vertices = Self::generate_vertices(canvas);
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let num_vertices = vertices.len() as u32;
+        let geometry = Self::generate_geometry(canvas);
+        let (vertex_buffer, text_vertex_buffer) = Self::create_vertex_buffers(&device, &geometry);
+        let atlas_bind_group = Self::create_atlas_bind_group(
+            &device,
+            &queue,
+            &atlas_bind_group_layout,
+            &atlas_sampler,
+            &geometry.atlas,
+        );
 
         Self {
             root_node: root_node.clone(),
-            stylesheets: stylesheets.clone().to_vec(),
+            stylesheets: stylesheets.to_vec(),
             window_size,
             surface,
             device,
             queue,
             render_pipeline,
+            text_pipeline,
+            atlas_bind_group_layout,
+            atlas_sampler,
+            atlas_bind_group,
             vertex_buffer,
-            num_vertices,
+            text_vertex_buffer,
+            batches: geometry.batches,
             camera_uniform,
             camera_buffer,
             camera_bind_group,
@@ -217,9 +312,9 @@ impl State {
         width: f32,
         height: f32,
         root_node: &Node,
-        stylesheets: &Vec<StyleSheet>,
+        stylesheets: &[StyleSheet],
     ) -> Canvas {
-        let mut viewport = Dimensions {
+        let viewport = Dimensions {
             content: Rect {
                 width: width / 2.0,
                 height: height / 2.0,
@@ -229,7 +324,7 @@ impl State {
         };
 
         let style_tree = StyleTree::new(root_node, stylesheets);
-        let render_tree = RenderTree::new(&style_tree.root.borrow(), &mut viewport);
+        let render_tree = RenderTree::new(&style_tree.root, &viewport);
 
         Canvas::new(
             render_tree,
@@ -238,14 +333,130 @@ impl State {
         )
     }
 
-    fn generate_vertices(canvas: Canvas) -> Vec<Vertex> {
-        let mut vertices = vec![];
+    fn generate_geometry(canvas: Canvas) -> Geometry {
+        let mut geometry = Geometry {
+            vertices: vec![],
+            text_vertices: vec![],
+            atlas: GlyphAtlas::new(),
+            batches: vec![],
+        };
         let display_list = build_display_list(&canvas.render_tree.root);
+        let mut clips: Vec<Rect> = Vec::new();
         for item in &display_list {
-            paint_item(&mut vertices, item);
+            match item {
+                DisplayItem::SolidColor(solid) => {
+                    let start = geometry.vertices.len() as u32;
+                    paint_item(&mut geometry.vertices, solid);
+                    let end = geometry.vertices.len() as u32;
+                    match geometry.batches.last_mut() {
+                        Some(Batch::Rects(range)) => range.end = end,
+                        _ => geometry.batches.push(Batch::Rects(start..end)),
+                    }
+                }
+                DisplayItem::Text(text) => {
+                    let start = geometry.text_vertices.len() as u32;
+                    push_text(&mut geometry.text_vertices, &mut geometry.atlas, text);
+                    let end = geometry.text_vertices.len() as u32;
+                    match geometry.batches.last_mut() {
+                        Some(Batch::Text(range)) => range.end = end,
+                        _ => geometry.batches.push(Batch::Text(start..end)),
+                    }
+                }
+                DisplayItem::PushClip(rect) => {
+                    let clip = clips.last().map_or(*rect, |clip| clip.intersection(*rect));
+                    clips.push(clip);
+                    geometry.batches.push(Batch::Clip(Some(clip)));
+                }
+                DisplayItem::PopClip => {
+                    clips.pop();
+                    geometry.batches.push(Batch::Clip(clips.last().copied()));
+                }
+            }
         }
+        normalize_tex_coords(&mut geometry.text_vertices, &geometry.atlas);
+
+        geometry
+    }
+
+    fn create_vertex_buffers(device: &wgpu::Device, geometry: &Geometry) -> (wgpu::Buffer, wgpu::Buffer) {
+        // Buffers can't be empty, so pad them with a single unused vertex.
+        let vertices = if geometry.vertices.is_empty() {
+            vec![Vertex { position: [0.0; 2], color: [0.0; 4] }]
+        } else {
+            geometry.vertices.clone()
+        };
+        let text_vertices = if geometry.text_vertices.is_empty() {
+            vec![TextVertex::default()]
+        } else {
+            geometry.text_vertices.clone()
+        };
+
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        let text_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Text Vertex Buffer"),
+            contents: bytemuck::cast_slice(&text_vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+        (vertex_buffer, text_vertex_buffer)
+    }
+
+    fn create_atlas_bind_group(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        atlas: &GlyphAtlas,
+    ) -> wgpu::BindGroup {
+        let size = wgpu::Extent3d {
+            width: atlas.width as u32,
+            height: atlas.height as u32,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas.data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(atlas.width as u32),
+                rows_per_image: Some(atlas.height as u32),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        vertices
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("atlas_bind_group"),
+        })
     }
 
     fn generate_matrix(size: PhysicalSize<u32>) -> [[f32; 4]; 4] {
@@ -288,10 +499,26 @@ impl State {
                 depth_stencil_attachment: None,
             });
 
-            render_pass.set_pipeline(&self.render_pipeline);
             render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.draw(0..self.num_vertices, 0..1);
+            for batch in &self.batches {
+                match batch {
+                    Batch::Rects(range) => {
+                        render_pass.set_pipeline(&self.render_pipeline);
+                        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+                        render_pass.draw(range.clone(), 0..1);
+                    }
+                    Batch::Text(range) => {
+                        render_pass.set_pipeline(&self.text_pipeline);
+                        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+                        render_pass.set_vertex_buffer(0, self.text_vertex_buffer.slice(..));
+                        render_pass.draw(range.clone(), 0..1);
+                    }
+                    Batch::Clip(clip) => {
+                        let (x, y, width, height) = self.scissor_rect(*clip);
+                        render_pass.set_scissor_rect(x, y, width, height);
+                    }
+                }
+            }
         }
 
         // submit will accept anything that implements IntoIter
@@ -301,6 +528,20 @@ impl State {
         Ok(())
     }
 
+    /// The scissor rectangle for a clip, in physical pixels, as `(x, y, width, height)`. Without
+    /// a clip it is the whole window.
+    fn scissor_rect(&self, clip: Option<Rect>) -> (u32, u32, u32, u32) {
+        let size = self.window_size;
+        let Some(clip) = clip else {
+            return (0, 0, size.width, size.height);
+        };
+        // The page is laid out at half the size of the window, as in `generate_canvas`.
+        let window = Rect { x: 0.0, y: 0.0, width: size.width as f32, height: size.height as f32 };
+        let rect = Rect { x: clip.x * 2.0, y: clip.y * 2.0, width: clip.width * 2.0, height: clip.height * 2.0 }
+            .intersection(window);
+        (rect.x as u32, rect.y as u32, rect.width as u32, rect.height as u32)
+    }
+
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         self.window_size = size;
 
@@ -310,9 +551,18 @@ impl State {
             &self.root_node,
             &self.stylesheets,
         );
-        let verts = Self::generate_vertices(canvas);
-        self.queue
-            .write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&verts));
+        // The page may need more vertices or a bigger atlas than before, so recreate the buffers.
+        let geometry = Self::generate_geometry(canvas);
+        (self.vertex_buffer, self.text_vertex_buffer) =
+            Self::create_vertex_buffers(&self.device, &geometry);
+        self.atlas_bind_group = Self::create_atlas_bind_group(
+            &self.device,
+            &self.queue,
+            &self.atlas_bind_group_layout,
+            &self.atlas_sampler,
+            &geometry.atlas,
+        );
+        self.batches = geometry.batches;
 
         self.camera_uniform = Self::generate_matrix(size);
         self.queue.write_buffer(
@@ -329,7 +579,7 @@ impl State {
             &self.root_node,
             &self.stylesheets,
         );
-        let mut file = BufWriter::new(File::create(&"output.pdf").unwrap());
+        let mut file = BufWriter::new(File::create("output.pdf").unwrap());
         robinson_pdf::render(
             &canvas.render_tree,
             canvas.width as f32,
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, sync::Arc};
+
+use robinson_font::GlyphBitmap;
+use robinson_paint::Text;
+
+/// Width of the glyph atlas texture. Its height grows to fit the glyphs on the page.
+const ATLAS_WIDTH: usize = 1024;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TextVertex {
+    position: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+}
+
+impl TextVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4];
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Identifies a glyph rendered with a particular font (by address) and size.
+type GlyphKey = (usize, u16, u32);
+
+/// Position of a glyph's bitmap within the atlas, in texels.
+#[derive(Clone, Copy)]
+struct AtlasEntry {
+    x: usize,
+    y: usize,
+}
+
+/// A single-channel texture holding the coverage masks of every glyph on the page, packed into
+/// rows ("shelves").
+pub struct GlyphAtlas {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+    entries: HashMap<GlyphKey, AtlasEntry>,
+    cursor_x: usize,
+    cursor_y: usize,
+    row_height: usize,
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        Self {
+            width: ATLAS_WIDTH,
+            // Textures can't be empty.
+            height: 1,
+            data: vec![0; ATLAS_WIDTH],
+            entries: HashMap::new(),
+            cursor_x: 0,
+            cursor_y: 0,
+            row_height: 0,
+        }
+    }
+
+    fn insert(&mut self, key: GlyphKey, bitmap: &GlyphBitmap) -> AtlasEntry {
+        if let Some(entry) = self.entries.get(&key) {
+            return *entry;
+        }
+
+        // Leave a one texel gap around each glyph so that filtering doesn't bleed.
+        let width = (bitmap.width + 1).min(self.width);
+        if self.cursor_x + width > self.width {
+            self.cursor_x = 0;
+            self.cursor_y += self.row_height;
+            self.row_height = 0;
+        }
+        let entry = AtlasEntry {
+            x: self.cursor_x,
+            y: self.cursor_y,
+        };
+        self.cursor_x += width;
+        self.row_height = self.row_height.max(bitmap.height + 1);
+
+        let needed_height = self.cursor_y + self.row_height;
+        if needed_height > self.height {
+            self.height = needed_height;
+            self.data.resize(self.width * self.height, 0);
+        }
+        for row in 0..bitmap.height {
+            let source = &bitmap.coverage[row * bitmap.width..][..bitmap.width.min(self.width)];
+            let start = (entry.y + row) * self.width + entry.x;
+            self.data[start..start + source.len()].copy_from_slice(source);
+        }
+
+        self.entries.insert(key, entry);
+        entry
+    }
+}
+
+/// Add textured quads for a run of glyphs, rasterizing any glyphs not in the atlas yet.
+///
+/// Texture coordinates are in texels; call `normalize_tex_coords` once the atlas is complete.
+pub fn push_text(vertices: &mut Vec<TextVertex>, atlas: &mut GlyphAtlas, item: &Text) {
+    let color = [
+        item.color.r as f32 / 255.0,
+        item.color.g as f32 / 255.0,
+        item.color.b as f32 / 255.0,
+        item.color.a as f32 / 255.0,
+    ];
+
+    for glyph in &item.glyphs {
+        let bitmap = item.font.rasterize(glyph.glyph_id, item.font_size);
+        if bitmap.width == 0 || bitmap.height == 0 {
+            continue;
+        }
+        let key = (Arc::as_ptr(&item.font) as usize, glyph.glyph_id, item.font_size.to_bits());
+        let entry = atlas.insert(key, &bitmap);
+
+        let x0 = (item.x + glyph.x).round() + bitmap.left as f32;
+        let y0 = (item.baseline + glyph.y).round() - bitmap.top as f32;
+        let x1 = x0 + bitmap.width as f32 * bitmap.scale;
+        let y1 = y0 + bitmap.height as f32 * bitmap.scale;
+
+        let u0 = entry.x as f32;
+        let v0 = entry.y as f32;
+        let u1 = u0 + bitmap.width as f32;
+        let v1 = v0 + bitmap.height as f32;
+
+        let vertex = |position, tex_coords| TextVertex { position, tex_coords, color };
+
+        // Triangle 1
+        vertices.push(vertex([x0, y0], [u0, v0]));
+        vertices.push(vertex([x0, y1], [u0, v1]));
+        vertices.push(vertex([x1, y1], [u1, v1]));
+
+        // Triangle 2
+        vertices.push(vertex([x1, y1], [u1, v1]));
+        vertices.push(vertex([x1, y0], [u1, v0]));
+        vertices.push(vertex([x0, y0], [u0, v0]));
+    }
+}
+
+/// Convert texel coordinates to the 0-1 range now that the atlas size is final.
+pub fn normalize_tex_coords(vertices: &mut [TextVertex], atlas: &GlyphAtlas) {
+    for vertex in vertices {
+        vertex.tex_coords[0] /= atlas.width as f32;
+        vertex.tex_coords[1] /= atlas.height as f32;
+    }
+}
@@ -13,4 +13,8 @@ pub enum Error {
     Css(#[from] robinson_css::Error),
     #[error(transparent)]
     Net(#[from] robinson_net::Error),
+    #[error(transparent)]
+    Font(#[from] robinson_font::Error),
+    #[error(transparent)]
+    ThreadPool(#[from] rayon::ThreadPoolBuildError),
 }
@@ -1,79 +1,104 @@
 mod error;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use error::Result;
-use robinson_css::StyleSheet;
-use robinson_dom::Dom;
-use robinson_net::Client;
+use robinson_css::{Origin, StyleSheet};
+use robinson_dom::Node;
+use robinson_net::{Client, Page};
 use robinson_window::create_window;
 
 /// A toy web rendering engine
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Website URL
-    #[arg(long, default_value = "examples/test.html")]
+    #[arg(long, global = true, default_value = "examples/test.html")]
     website: String,
+
+    /// Extra font files to make available to pages by family name
+    #[arg(long = "font", global = true)]
+    fonts: Vec<PathBuf>,
+
+    /// Stylesheets to apply to every page, with the user's preferences
+    #[arg(long = "user-stylesheet", global = true)]
+    user_stylesheets: Vec<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render the page to an image file without opening a window
+    Render {
+        /// Image file to write; the format follows the extension
+        #[arg(long)]
+        output: PathBuf,
+
+        /// Viewport width in px
+        #[arg(long, default_value_t = 800)]
+        width: u32,
+
+        /// Viewport height in px
+        #[arg(long, default_value_t = 600)]
+        height: u32,
+
+        /// Print how often elements shared a computed style
+        #[arg(long)]
+        style_stats: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    // Styling runs on rayon's global pool, with one worker per CPU.
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_cpus::get())
+        .build_global()?;
+
+    for font in &args.fonts {
+        robinson_font::load_font_file(font)?;
+    }
+
     // Create the network connection.
     let client = Client::default();
+    let Page { root_node, mut stylesheets } = client.load_page(&args.website).await?;
+    for location in &args.user_stylesheets {
+        let css = client.get_to_string(client.get_url(location)?).await?;
+        stylesheets.push(StyleSheet::parse_with_origin(&css, Origin::User)?);
+    }
 
-    // Read and parse html
-    let html = client.get_to_string(client.get_url(&args.website)?).await?;
-    let dom = Dom::parse(&html).unwrap();
-    let root_node = dom.children.first().unwrap();
-
-    let mut stylesheet_links = Vec::new();
-
-    if let Some(root_element) = root_node.element() {
-        for ele in root_element.children.iter().filter_map(|e| e.element()) {
-            if ele.name == "head" {
-                for eee in ele.children.iter().filter_map(|e| e.element()) {
-                    if eee.name == "link" {
-                        if let Some(_rel) = eee
-                            .attributes
-                            .get("rel")
-                            .filter(|&rel| rel == &Some(String::from("stylesheet")))
-                        {
-                            if let Some(href) = eee.attributes.get("href").cloned() {
-                                let css_url = href.unwrap();
-                                let css_path = Path::new(&css_url);
-                                let html_path = Path::new(&args.website);
-                                let html_url = html_path.parent().unwrap();
-                                let connected_path = html_url.join(css_path);
-                                let css_str = connected_path.to_str().unwrap();
-                                let css = client.get_to_string(client.get_url(css_str)?).await?;
-                                stylesheet_links.push(css);
-                            }
-                        }
-                    } else if eee.name == "style" {
-                        if let Some(first_child) = eee.children.first() {
-                            if let Some(text) = first_child.text() {
-                                stylesheet_links.push(text.to_string());
-                            }
-                        }
-                    }
-                }
-            }
+    match args.command {
+        Some(Command::Render { output, width, height, style_stats }) => {
+            render_to_file(&root_node, &stylesheets, width, height, &output, style_stats)?;
+        }
+        None => {
+            // Render to window
+            create_window("Robinson", &root_node, &stylesheets).await;
         }
     }
 
-    // Read and parse css
-    let mut stylesheets = Vec::new();
-    for css in stylesheet_links {
-        let stylesheet = StyleSheet::parse(&css)?;
-        stylesheets.push(stylesheet);
-    }
+    Ok(())
+}
 
-    // Render to window
-    create_window("Robinson", root_node, &stylesheets).await;
+/// Style, lay out and paint the page in software, and save the result as an image.
+fn render_to_file(
+    root_node: &Node,
+    stylesheets: &[StyleSheet],
+    width: u32,
+    height: u32,
+    output: &Path,
+    style_stats: bool,
+) -> Result<()> {
+    let (image, sharing_stats) = robinson_paint::render_page(root_node, stylesheets, width, height);
+    if style_stats {
+        eprintln!("{}", sharing_stats);
+    }
+    image.save(output)?;
 
     Ok(())
 }